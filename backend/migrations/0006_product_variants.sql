@@ -0,0 +1,50 @@
+-- Option types (e.g. Size, Colour) defined per product
+CREATE TABLE product_options (
+    id UUID PRIMARY KEY,
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    position INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(product_id, name)
+);
+
+-- Values an option can take (e.g. S, M, L)
+CREATE TABLE product_option_values (
+    id UUID PRIMARY KEY,
+    option_id UUID NOT NULL REFERENCES product_options(id) ON DELETE CASCADE,
+    value VARCHAR(100) NOT NULL,
+    position INT NOT NULL DEFAULT 0,
+    UNIQUE(option_id, value)
+);
+
+-- Sellable variants; a NULL price falls back to the product price
+CREATE TABLE product_variants (
+    id UUID PRIMARY KEY,
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    sku VARCHAR(64) NOT NULL UNIQUE,
+    price NUMERIC(10, 2),
+    stock_quantity INT NOT NULL DEFAULT 0,
+    barcode VARCHAR(64),
+    deleted_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_product_variants_product_id ON product_variants(product_id);
+
+-- Which option values make up a variant (one value per option)
+CREATE TABLE product_variant_option_values (
+    variant_id UUID NOT NULL REFERENCES product_variants(id) ON DELETE CASCADE,
+    option_value_id UUID NOT NULL REFERENCES product_option_values(id) ON DELETE CASCADE,
+    PRIMARY KEY (variant_id, option_value_id)
+);
+
+-- Cart lines may point at a specific variant
+ALTER TABLE cart_items
+ADD COLUMN variant_id UUID REFERENCES product_variants(id);
+
+ALTER TABLE cart_items
+DROP CONSTRAINT cart_items_user_id_product_id_key;
+
+ALTER TABLE cart_items
+ADD CONSTRAINT cart_items_user_product_variant_key UNIQUE NULLS NOT DISTINCT (user_id, product_id, variant_id);
@@ -0,0 +1,60 @@
+CREATE TYPE stock_movement_kind AS ENUM ('receipt', 'sale', 'return', 'adjustment', 'damage');
+
+-- Append-only ledger: stock levels only change through a movement row.
+-- quantity is the signed delta applied to the product (or variant) stock.
+CREATE TABLE stock_movements (
+    id UUID PRIMARY KEY,
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    variant_id UUID REFERENCES product_variants(id) ON DELETE CASCADE,
+    kind stock_movement_kind NOT NULL,
+    quantity INT NOT NULL CHECK (quantity <> 0),
+    reason TEXT,
+    actor_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_stock_movements_product_id ON stock_movements(product_id, created_at DESC);
+
+-- Rows are never edited or deleted, except by the foreign keys' own actions: deleting a user
+-- sets actor_id to NULL, and deleting a product (or variant) takes its ledger with it
+CREATE FUNCTION forbid_stock_movement_change() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        IF NOT EXISTS (SELECT 1 FROM products WHERE id = OLD.product_id)
+           OR (OLD.variant_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM product_variants WHERE id = OLD.variant_id)) THEN
+            RETURN OLD;
+        END IF;
+    ELSIF OLD.actor_id IS NOT NULL AND NEW.actor_id IS NULL
+          AND (to_jsonb(NEW) - 'actor_id') = (to_jsonb(OLD) - 'actor_id') THEN
+        RETURN NEW;
+    END IF;
+    RAISE EXCEPTION 'stock_movements is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER stock_movements_append_only
+BEFORE UPDATE OR DELETE ON stock_movements
+FOR EACH ROW EXECUTE FUNCTION forbid_stock_movement_change();
+
+-- Drift between the ledger and stock_quantity found by the reconciliation job
+CREATE TABLE stock_drift_flags (
+    id UUID PRIMARY KEY,
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    variant_id UUID REFERENCES product_variants(id) ON DELETE CASCADE,
+    ledger_quantity BIGINT NOT NULL,
+    recorded_quantity INT NOT NULL,
+    detected_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    checked_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE NULLS NOT DISTINCT (product_id, variant_id)
+);
+
+-- Opening balances so existing stock reconciles against the ledger
+INSERT INTO stock_movements (id, product_id, kind, quantity, reason)
+SELECT gen_random_uuid(), id, 'adjustment', stock_quantity, 'opening balance'
+FROM products
+WHERE stock_quantity <> 0;
+
+INSERT INTO stock_movements (id, product_id, variant_id, kind, quantity, reason)
+SELECT gen_random_uuid(), product_id, id, 'adjustment', stock_quantity, 'opening balance'
+FROM product_variants
+WHERE stock_quantity <> 0;
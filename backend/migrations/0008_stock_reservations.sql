@@ -0,0 +1,19 @@
+CREATE TYPE reservation_status AS ENUM ('active', 'released', 'expired', 'converted');
+
+-- Stock held for a checkout between cart and payment.
+-- Available-to-sell = stock_quantity - SUM(active, unexpired reservations).
+CREATE TABLE stock_reservations (
+    id UUID PRIMARY KEY,
+    checkout_id UUID NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    variant_id UUID REFERENCES product_variants(id) ON DELETE CASCADE,
+    quantity INT NOT NULL CHECK (quantity > 0),
+    status reservation_status NOT NULL DEFAULT 'active',
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_stock_reservations_checkout_id ON stock_reservations(checkout_id);
+CREATE INDEX idx_stock_reservations_active ON stock_reservations(product_id, variant_id) WHERE status = 'active';
@@ -0,0 +1,25 @@
+-- NULL threshold means the product is not monitored
+ALTER TABLE products
+ADD COLUMN reorder_threshold INT CHECK (reorder_threshold >= 0);
+
+-- One open alert per product; resolved once stock is back above the threshold
+CREATE TABLE low_stock_alerts (
+    id UUID PRIMARY KEY,
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    stock_quantity INT NOT NULL,
+    reorder_threshold INT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    notified_at TIMESTAMP,
+    resolved_at TIMESTAMP
+);
+
+CREATE UNIQUE INDEX idx_low_stock_alerts_open ON low_stock_alerts(product_id) WHERE resolved_at IS NULL;
+
+-- Notifications shown to admins inside the dashboard
+CREATE TABLE admin_notifications (
+    id UUID PRIMARY KEY,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    read_at TIMESTAMP
+);
@@ -0,0 +1,59 @@
+-- Physical stock locations. products.stock_quantity stays the total across all of them.
+CREATE TABLE warehouses (
+    id UUID PRIMARY KEY,
+    name VARCHAR(100) NOT NULL,
+    code VARCHAR(32) NOT NULL UNIQUE,
+    address TEXT,
+    priority INT NOT NULL DEFAULT 0, -- lower fulfils first
+    is_default BOOLEAN NOT NULL DEFAULT FALSE, -- receives stock when no location is given
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX idx_warehouses_single_default ON warehouses(is_default) WHERE is_default;
+
+CREATE TABLE warehouse_stock (
+    id UUID PRIMARY KEY,
+    warehouse_id UUID NOT NULL REFERENCES warehouses(id),
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    variant_id UUID REFERENCES product_variants(id) ON DELETE CASCADE,
+    quantity INT NOT NULL DEFAULT 0 CHECK (quantity >= 0),
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT warehouse_stock_location_key UNIQUE NULLS NOT DISTINCT (warehouse_id, product_id, variant_id)
+);
+
+CREATE INDEX idx_warehouse_stock_product_id ON warehouse_stock(product_id);
+
+-- Ledger rows now say which location they touched (NULL for rows recorded before locations existed)
+ALTER TABLE stock_movements
+ADD COLUMN warehouse_id UUID REFERENCES warehouses(id);
+
+ALTER TYPE stock_movement_kind ADD VALUE 'transfer';
+
+CREATE TABLE stock_transfers (
+    id UUID PRIMARY KEY,
+    from_warehouse_id UUID NOT NULL REFERENCES warehouses(id),
+    to_warehouse_id UUID NOT NULL REFERENCES warehouses(id),
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    variant_id UUID REFERENCES product_variants(id) ON DELETE CASCADE,
+    quantity INT NOT NULL CHECK (quantity > 0),
+    reason TEXT,
+    actor_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (from_warehouse_id <> to_warehouse_id)
+);
+
+-- Everything on hand so far lives in the main stockroom
+INSERT INTO warehouses (id, name, code, priority, is_default)
+VALUES (gen_random_uuid(), 'Main stockroom', 'MAIN', 0, TRUE);
+
+INSERT INTO warehouse_stock (id, warehouse_id, product_id, variant_id, quantity)
+SELECT gen_random_uuid(), w.id, p.id, NULL, p.stock_quantity
+FROM products p, warehouses w
+WHERE w.is_default AND p.stock_quantity > 0;
+
+INSERT INTO warehouse_stock (id, warehouse_id, product_id, variant_id, quantity)
+SELECT gen_random_uuid(), w.id, v.product_id, v.id, v.stock_quantity
+FROM product_variants v, warehouses w
+WHERE w.is_default AND v.stock_quantity > 0;
@@ -0,0 +1,41 @@
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Weighted document for full-text search: name > category name > description.
+-- Kept up to date by triggers because a generated column can't read the category name.
+ALTER TABLE products
+ADD COLUMN search_vector tsvector;
+
+CREATE FUNCTION products_search_vector_refresh() RETURNS trigger AS $$
+BEGIN
+    NEW.search_vector :=
+        setweight(to_tsvector('english', COALESCE(NEW.name, '')), 'A') ||
+        setweight(to_tsvector('english', COALESCE((SELECT name FROM categories WHERE id = NEW.category_id), '')), 'B') ||
+        setweight(to_tsvector('english', COALESCE(NEW.description, '')), 'C');
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER products_search_vector_refresh
+BEFORE INSERT OR UPDATE OF name, description, category_id ON products
+FOR EACH ROW EXECUTE FUNCTION products_search_vector_refresh();
+
+-- Renaming a category re-indexes its products
+CREATE FUNCTION categories_search_vector_refresh() RETURNS trigger AS $$
+BEGIN
+    UPDATE products SET category_id = category_id WHERE category_id = NEW.id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER categories_search_vector_refresh
+AFTER UPDATE OF name ON categories
+FOR EACH ROW WHEN (OLD.name IS DISTINCT FROM NEW.name)
+EXECUTE FUNCTION categories_search_vector_refresh();
+
+-- Backfill existing products
+UPDATE products SET name = name;
+
+CREATE INDEX idx_products_search_vector ON products USING GIN (search_vector);
+
+-- Trigram index for the fuzzy fallback on misspelled names
+CREATE INDEX idx_products_name_trgm ON products USING GIN (name gin_trgm_ops);
@@ -0,0 +1,6 @@
+-- Prefix lookups for autocomplete: lower(name) LIKE 'abc%'
+CREATE INDEX idx_products_name_prefix ON products (lower(name) text_pattern_ops) WHERE deleted_at IS NULL;
+CREATE INDEX idx_categories_name_prefix ON categories (lower(name) text_pattern_ops);
+
+-- Word-similarity matches on category names (products already have idx_products_name_trgm)
+CREATE INDEX idx_categories_name_trgm ON categories USING GIN (name gin_trgm_ops);
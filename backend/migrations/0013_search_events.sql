@@ -0,0 +1,15 @@
+-- One row per search (first page only). No user, session or IP is stored;
+-- the query is lower-cased and whitespace-collapsed before it lands here.
+CREATE TABLE search_events (
+    id UUID PRIMARY KEY,
+    query TEXT NOT NULL,
+    filters JSONB NOT NULL DEFAULT '{}',
+    result_count BIGINT NOT NULL,
+    fuzzy BOOLEAN NOT NULL DEFAULT FALSE,
+    clicked_product_id UUID REFERENCES products(id) ON DELETE SET NULL,
+    clicked_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_search_events_created_at ON search_events(created_at);
+CREATE INDEX idx_search_events_query ON search_events(query, created_at);
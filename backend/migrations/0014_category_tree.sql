@@ -0,0 +1,36 @@
+-- Nested categories: NULL parent_id is a top-level category
+ALTER TABLE categories
+ADD COLUMN parent_id UUID REFERENCES categories(id),
+ADD CONSTRAINT categories_parent_not_self CHECK (parent_id <> id);
+
+CREATE INDEX idx_categories_parent_id ON categories(parent_id);
+
+-- Reject a parent that is the category itself or one of its descendants
+CREATE FUNCTION categories_prevent_cycle() RETURNS trigger AS $$
+BEGIN
+    IF NEW.parent_id IS NULL THEN
+        RETURN NEW;
+    END IF;
+
+    -- serialise tree changes so two concurrent moves can't close a loop between them
+    PERFORM pg_advisory_xact_lock(hashtext('categories_tree'));
+
+    IF EXISTS (
+        WITH RECURSIVE ancestors AS (
+            SELECT id, parent_id FROM categories WHERE id = NEW.parent_id
+            UNION
+            SELECT c.id, c.parent_id FROM categories c JOIN ancestors a ON c.id = a.parent_id
+        )
+        SELECT 1 FROM ancestors WHERE id = NEW.id
+    ) THEN
+        RAISE EXCEPTION 'category % cannot be placed under its own descendant', NEW.id
+            USING ERRCODE = 'check_violation';
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER categories_prevent_cycle
+BEFORE INSERT OR UPDATE OF parent_id ON categories
+FOR EACH ROW EXECUTE FUNCTION categories_prevent_cycle();
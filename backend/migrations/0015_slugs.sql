@@ -0,0 +1,78 @@
+-- URL slugs for products and categories. Existing rows get a slug from their name; the
+-- first row with a name keeps it and the others take the next free -2, -3, ... in creation
+-- order, skipping slugs another row already has (a product named "Lamp 2" owns lamp-2).
+ALTER TABLE products ADD COLUMN slug TEXT;
+ALTER TABLE categories ADD COLUMN slug TEXT;
+
+CREATE FUNCTION pg_temp.name_slug(name TEXT, fallback TEXT) RETURNS TEXT AS $$
+    SELECT COALESCE(NULLIF(trim(BOTH '-' FROM regexp_replace(lower(name), '[^a-z0-9]+', '-', 'g')), ''), fallback)
+$$ LANGUAGE sql IMMUTABLE;
+
+WITH numbered AS (
+    SELECT id, pg_temp.name_slug(name, 'product') AS slug,
+           ROW_NUMBER() OVER (PARTITION BY pg_temp.name_slug(name, 'product') ORDER BY created_at NULLS FIRST, id) AS n
+    FROM products
+)
+UPDATE products p
+SET slug = n.slug
+FROM numbered n
+WHERE n.id = p.id AND n.n = 1;
+
+WITH numbered AS (
+    SELECT id, pg_temp.name_slug(name, 'category') AS slug,
+           ROW_NUMBER() OVER (PARTITION BY pg_temp.name_slug(name, 'category') ORDER BY created_at NULLS FIRST, id) AS n
+    FROM categories
+)
+UPDATE categories c
+SET slug = n.slug
+FROM numbered n
+WHERE n.id = c.id AND n.n = 1;
+
+-- unique already, so the lookups below use the index; NULL until numbered
+ALTER TABLE products ADD CONSTRAINT products_slug_key UNIQUE (slug);
+ALTER TABLE categories ADD CONSTRAINT categories_slug_key UNIQUE (slug);
+
+DO $$
+DECLARE
+    r RECORD;
+    suffix INT;
+BEGIN
+    FOR r IN
+        SELECT id, pg_temp.name_slug(name, 'product') AS slug FROM products
+        WHERE slug IS NULL ORDER BY created_at NULLS FIRST, id
+    LOOP
+        suffix := 2;
+        WHILE EXISTS (SELECT 1 FROM products WHERE slug = r.slug || '-' || suffix) LOOP
+            suffix := suffix + 1;
+        END LOOP;
+        UPDATE products SET slug = r.slug || '-' || suffix WHERE id = r.id;
+    END LOOP;
+
+    FOR r IN
+        SELECT id, pg_temp.name_slug(name, 'category') AS slug FROM categories
+        WHERE slug IS NULL ORDER BY created_at NULLS FIRST, id
+    LOOP
+        suffix := 2;
+        WHILE EXISTS (SELECT 1 FROM categories WHERE slug = r.slug || '-' || suffix) LOOP
+            suffix := suffix + 1;
+        END LOOP;
+        UPDATE categories SET slug = r.slug || '-' || suffix WHERE id = r.id;
+    END LOOP;
+END;
+$$;
+
+ALTER TABLE products ALTER COLUMN slug SET NOT NULL;
+ALTER TABLE categories ALTER COLUMN slug SET NOT NULL;
+
+-- Previous slugs, so old links can be redirected to the current one
+CREATE TABLE product_slug_history (
+    slug TEXT PRIMARY KEY,
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE category_slug_history (
+    slug TEXT PRIMARY KEY,
+    category_id UUID NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,11 @@
+-- One soft-delete marker: deleted_at. Categories flagged through the old is_deleted
+-- column keep their place in the trash from the time they were last touched.
+UPDATE categories
+SET deleted_at = COALESCE(updated_at, NOW())
+WHERE is_deleted AND deleted_at IS NULL;
+
+ALTER TABLE categories DROP COLUMN is_deleted;
+
+-- trash listings and the purge job only look at deleted rows
+CREATE INDEX idx_products_deleted_at ON products(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX idx_categories_deleted_at ON categories(deleted_at) WHERE deleted_at IS NOT NULL;
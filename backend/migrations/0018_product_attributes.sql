@@ -0,0 +1,28 @@
+-- Free-form tags, and typed attributes defined per category. Values live in a JSONB
+-- column on products so filters can use the GIN index instead of joining per attribute.
+CREATE TABLE product_tags (
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (product_id, tag)
+);
+
+CREATE INDEX idx_product_tags_tag ON product_tags(tag);
+
+CREATE TYPE attribute_kind AS ENUM ('text', 'number', 'boolean', 'enum');
+
+-- a definition applies to products in the category and in all of its subcategories
+CREATE TABLE attribute_definitions (
+    id UUID PRIMARY KEY,
+    category_id UUID NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    key TEXT NOT NULL,
+    name TEXT NOT NULL,
+    kind attribute_kind NOT NULL,
+    allowed_values TEXT[] NOT NULL DEFAULT '{}', -- the choices for an enum
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (category_id, key),
+    CONSTRAINT attribute_definitions_enum_values CHECK ((kind = 'enum') = (cardinality(allowed_values) > 0))
+);
+
+ALTER TABLE products ADD COLUMN attributes JSONB NOT NULL DEFAULT '{}';
+
+CREATE INDEX idx_products_attributes ON products USING GIN (attributes jsonb_path_ops);
@@ -0,0 +1,33 @@
+-- Product-level SKU, the key bulk imports upsert on
+ALTER TABLE products ADD COLUMN sku TEXT;
+ALTER TABLE products ADD CONSTRAINT products_sku_key UNIQUE (sku);
+
+CREATE TYPE import_format AS ENUM ('csv', 'ndjson');
+CREATE TYPE import_status AS ENUM ('queued', 'running', 'completed', 'failed');
+
+-- one uploaded file; counts are what a dry run would have done
+CREATE TABLE import_jobs (
+    id UUID PRIMARY KEY,
+    format import_format NOT NULL,
+    dry_run BOOLEAN NOT NULL,
+    create_categories BOOLEAN NOT NULL,
+    status import_status NOT NULL DEFAULT 'queued',
+    total_rows INT NOT NULL DEFAULT 0,
+    processed_rows INT NOT NULL DEFAULT 0,
+    created_count INT NOT NULL DEFAULT 0,
+    updated_count INT NOT NULL DEFAULT 0,
+    error_count INT NOT NULL DEFAULT 0,
+    error TEXT, -- why the whole job failed, e.g. an unreadable file
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    started_at TIMESTAMP,
+    finished_at TIMESTAMP
+);
+
+CREATE TABLE import_job_errors (
+    job_id UUID NOT NULL REFERENCES import_jobs(id) ON DELETE CASCADE,
+    row_number INT NOT NULL, -- line in the uploaded file
+    sku TEXT,
+    message TEXT NOT NULL,
+    PRIMARY KEY (job_id, row_number)
+);
@@ -0,0 +1,134 @@
+-- Versioned history of product and category changes. Deferred triggers record the state
+-- each transaction leaves a row in, so one save is one version even when it also rewrites
+-- tags or secondary categories. The acting user comes from the transaction-local
+-- easy_buy.actor_id setting
+CREATE TYPE history_entity AS ENUM ('product', 'category');
+CREATE TYPE history_action AS ENUM ('created', 'updated', 'deleted');
+
+CREATE TABLE change_history (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    entity history_entity NOT NULL,
+    entity_id UUID NOT NULL, -- no foreign key, history outlives a hard delete
+    version INT NOT NULL,
+    action history_action NOT NULL,
+    changes JSONB NOT NULL, -- {"price": {"from": 10.0, "to": 8.0}}
+    snapshot JSONB, -- state after the change, NULL once deleted
+    actor_id UUID,
+    changed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (entity, entity_id, version)
+);
+
+-- stock has its own ledger; timestamps and the search vector follow from other columns
+CREATE FUNCTION product_history_snapshot(target UUID) RETURNS JSONB AS $$
+    SELECT to_jsonb(p) - ARRAY['stock_quantity', 'search_vector', 'created_at', 'updated_at']
+        || jsonb_build_object(
+            'tags', ARRAY(SELECT t.tag FROM product_tags t WHERE t.product_id = p.id ORDER BY t.tag),
+            'secondary_category_ids',
+            ARRAY(SELECT pc.category_id FROM product_categories pc WHERE pc.product_id = p.id ORDER BY pc.category_id)
+        )
+    FROM products p
+    WHERE p.id = target
+$$ LANGUAGE sql STABLE;
+
+CREATE FUNCTION category_history_snapshot(target UUID) RETURNS JSONB AS $$
+    SELECT to_jsonb(c) - ARRAY['created_at', 'updated_at'] FROM categories c WHERE c.id = target
+$$ LANGUAGE sql STABLE;
+
+-- a new version when the row differs from its latest one; several firings in one
+-- transaction all see the final state, so only the first writes anything
+CREATE FUNCTION record_history(kind history_entity, target UUID, current JSONB) RETURNS void AS $$
+DECLARE
+    latest change_history%ROWTYPE;
+    diff JSONB;
+BEGIN
+    PERFORM pg_advisory_xact_lock(hashtext('change_history:' || target));
+
+    SELECT * INTO latest FROM change_history
+    WHERE entity = kind AND entity_id = target
+    ORDER BY version DESC
+    LIMIT 1;
+
+    IF latest.snapshot IS NOT DISTINCT FROM current THEN
+        RETURN;
+    END IF;
+
+    SELECT COALESCE(jsonb_object_agg(key, jsonb_build_object('from', prev.value, 'to', cur.value)), '{}')
+    INTO diff
+    FROM jsonb_each(COALESCE(latest.snapshot, '{}')) prev
+    FULL JOIN jsonb_each(COALESCE(current, '{}')) cur USING (key)
+    WHERE COALESCE(prev.value, 'null') IS DISTINCT FROM COALESCE(cur.value, 'null');
+
+    INSERT INTO change_history (entity, entity_id, version, action, changes, snapshot, actor_id)
+    VALUES (
+        kind,
+        target,
+        COALESCE(latest.version, 0) + 1,
+        (CASE WHEN current IS NULL THEN 'deleted' WHEN latest.id IS NULL THEN 'created' ELSE 'updated' END)::history_action,
+        diff,
+        current,
+        NULLIF(current_setting('easy_buy.actor_id', true), '')::uuid
+    );
+END;
+$$ LANGUAGE plpgsql;
+
+-- TG_ARGV[0] is the column holding the product id
+CREATE FUNCTION product_history_trigger() RETURNS trigger AS $$
+DECLARE
+    target UUID;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        target := (to_jsonb(OLD) ->> TG_ARGV[0])::uuid;
+    ELSE
+        target := (to_jsonb(NEW) ->> TG_ARGV[0])::uuid;
+    END IF;
+
+    PERFORM record_history('product', target, product_history_snapshot(target));
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION category_history_trigger() RETURNS trigger AS $$
+DECLARE
+    target UUID;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        target := OLD.id;
+    ELSE
+        target := NEW.id;
+    END IF;
+
+    PERFORM record_history('category', target, category_history_snapshot(target));
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE CONSTRAINT TRIGGER products_history
+AFTER INSERT OR UPDATE OR DELETE ON products
+DEFERRABLE INITIALLY DEFERRED
+FOR EACH ROW EXECUTE FUNCTION product_history_trigger('id');
+
+CREATE CONSTRAINT TRIGGER product_tags_history
+AFTER INSERT OR UPDATE OR DELETE ON product_tags
+DEFERRABLE INITIALLY DEFERRED
+FOR EACH ROW EXECUTE FUNCTION product_history_trigger('product_id');
+
+CREATE CONSTRAINT TRIGGER product_categories_history
+AFTER INSERT OR UPDATE OR DELETE ON product_categories
+DEFERRABLE INITIALLY DEFERRED
+FOR EACH ROW EXECUTE FUNCTION product_history_trigger('product_id');
+
+CREATE CONSTRAINT TRIGGER categories_history
+AFTER INSERT OR UPDATE OR DELETE ON categories
+DEFERRABLE INITIALLY DEFERRED
+FOR EACH ROW EXECUTE FUNCTION category_history_trigger();
+
+-- existing rows start at version 1
+INSERT INTO change_history (entity, entity_id, version, action, changes, snapshot, changed_at)
+SELECT 'product', id, 1, 'created', '{}', product_history_snapshot(id), COALESCE(created_at, CURRENT_TIMESTAMP)
+FROM products;
+
+INSERT INTO change_history (entity, entity_id, version, action, changes, snapshot, changed_at)
+SELECT 'category', id, 1, 'created', '{}', category_history_snapshot(id), COALESCE(created_at, CURRENT_TIMESTAMP)
+FROM categories;
+
+CREATE INDEX idx_change_history_actor ON change_history (actor_id) WHERE actor_id IS NOT NULL;
@@ -0,0 +1,65 @@
+-- Compare-at and time-boxed sale prices, base price changes scheduled for later, and a
+-- history of every product's pricing
+ALTER TABLE products
+    ADD COLUMN compare_at_price NUMERIC(10, 2) CHECK (compare_at_price >= 0), -- "was" price shown struck through
+    ADD COLUMN sale_price NUMERIC(10, 2) CHECK (sale_price >= 0),
+    ADD COLUMN sale_starts_at TIMESTAMP, -- NULL: the sale is already on
+    ADD COLUMN sale_ends_at TIMESTAMP, -- NULL: until the sale is removed
+    ADD CONSTRAINT products_sale_window CHECK (sale_starts_at IS NULL OR sale_ends_at IS NULL OR sale_ends_at > sale_starts_at);
+
+CREATE TYPE price_change_status AS ENUM ('pending', 'applied', 'cancelled');
+
+CREATE TABLE scheduled_price_changes (
+    id UUID PRIMARY KEY,
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    price NUMERIC(10, 2) NOT NULL CHECK (price >= 0),
+    apply_at TIMESTAMP NOT NULL,
+    status price_change_status NOT NULL DEFAULT 'pending',
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    applied_at TIMESTAMP
+);
+
+CREATE INDEX idx_scheduled_price_changes_due ON scheduled_price_changes (apply_at) WHERE status = 'pending';
+CREATE INDEX idx_scheduled_price_changes_product ON scheduled_price_changes (product_id, apply_at);
+
+CREATE TABLE price_history (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    price NUMERIC(10, 2) NOT NULL,
+    compare_at_price NUMERIC(10, 2),
+    sale_price NUMERIC(10, 2),
+    sale_starts_at TIMESTAMP,
+    sale_ends_at TIMESTAMP,
+    actor_id UUID,
+    changed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_price_history_product ON price_history (product_id, changed_at DESC);
+
+-- one row per change to any pricing column, credited like change_history
+CREATE FUNCTION record_price_history() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'UPDATE'
+        AND (OLD.price, OLD.compare_at_price, OLD.sale_price, OLD.sale_starts_at, OLD.sale_ends_at)
+            IS NOT DISTINCT FROM (NEW.price, NEW.compare_at_price, NEW.sale_price, NEW.sale_starts_at, NEW.sale_ends_at)
+    THEN
+        RETURN NULL;
+    END IF;
+
+    INSERT INTO price_history (product_id, price, compare_at_price, sale_price, sale_starts_at, sale_ends_at, actor_id)
+    VALUES (
+        NEW.id, NEW.price, NEW.compare_at_price, NEW.sale_price, NEW.sale_starts_at, NEW.sale_ends_at,
+        NULLIF(current_setting('easy_buy.actor_id', true), '')::uuid
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER products_price_history
+AFTER INSERT OR UPDATE OF price, compare_at_price, sale_price, sale_starts_at, sale_ends_at ON products
+FOR EACH ROW EXECUTE FUNCTION record_price_history();
+
+-- current prices are the first entry
+INSERT INTO price_history (product_id, price, changed_at)
+SELECT id, price, COALESCE(updated_at, created_at, CURRENT_TIMESTAMP) FROM products;
@@ -0,0 +1,28 @@
+-- Currencies prices can be shown in. Catalogue prices stay in the base currency
+-- (STORE_CURRENCY) and are converted with the rate to each other currency
+CREATE TYPE rounding_mode AS ENUM ('half_up', 'half_even', 'up', 'down');
+
+CREATE TABLE currencies (
+    code CHAR(3) PRIMARY KEY, -- ISO 4217
+    name VARCHAR(100) NOT NULL,
+    symbol VARCHAR(8),
+    decimals SMALLINT NOT NULL CHECK (decimals BETWEEN 0 AND 4),
+    rounding_increment NUMERIC(12, 4) CHECK (rounding_increment > 0), -- e.g. 5 to price in whole 5s; NULL: smallest unit
+    rounding rounding_mode NOT NULL DEFAULT 'half_up',
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- how much of `currency` one unit of the base currency buys
+CREATE TABLE exchange_rates (
+    currency CHAR(3) PRIMARY KEY REFERENCES currencies(code) ON DELETE CASCADE,
+    rate NUMERIC(20, 10) NOT NULL CHECK (rate > 0),
+    source VARCHAR(50) NOT NULL, -- 'admin' or the rate provider that fetched it
+    updated_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO currencies (code, name, symbol, decimals) VALUES
+    ('USD', 'US Dollar', '$', 2),
+    ('NGN', 'Nigerian Naira', '₦', 2),
+    ('XAF', 'Central African CFA Franc', 'FCFA', 0);
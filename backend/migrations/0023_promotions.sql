@@ -0,0 +1,61 @@
+-- Coupons (promotions with a code) and automatic promotions, the products and categories
+-- they're limited to, the coupons entered on carts, and every redemption at checkout.
+-- Amounts are in the base currency
+CREATE TYPE promotion_kind AS ENUM ('percentage', 'fixed_amount', 'free_shipping', 'buy_x_get_y');
+
+CREATE TABLE promotions (
+    id UUID PRIMARY KEY,
+    name VARCHAR(200) NOT NULL,
+    code VARCHAR(50), -- NULL: applied automatically
+    kind promotion_kind NOT NULL,
+    value NUMERIC(10, 2) CHECK (value > 0), -- percent off, amount off, or percent off the free items
+    buy_quantity INT CHECK (buy_quantity > 0), -- buy_x_get_y
+    get_quantity INT CHECK (get_quantity > 0),
+    starts_at TIMESTAMP,
+    ends_at TIMESTAMP,
+    usage_limit INT CHECK (usage_limit > 0), -- redemptions in total
+    per_user_limit INT CHECK (per_user_limit > 0),
+    min_subtotal NUMERIC(10, 2) CHECK (min_subtotal >= 0),
+    stackable BOOLEAN NOT NULL DEFAULT FALSE, -- combines with other stackable promotions
+    priority INT NOT NULL DEFAULT 0, -- higher first when discounts tie
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (starts_at IS NULL OR ends_at IS NULL OR ends_at > starts_at)
+);
+
+CREATE UNIQUE INDEX idx_promotions_code ON promotions (upper(code)) WHERE code IS NOT NULL;
+CREATE INDEX idx_promotions_automatic ON promotions (priority DESC) WHERE code IS NULL AND active;
+
+-- with neither, a promotion covers the whole cart; categories include their descendants
+CREATE TABLE promotion_products (
+    promotion_id UUID NOT NULL REFERENCES promotions(id) ON DELETE CASCADE,
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    PRIMARY KEY (promotion_id, product_id)
+);
+
+CREATE TABLE promotion_categories (
+    promotion_id UUID NOT NULL REFERENCES promotions(id) ON DELETE CASCADE,
+    category_id UUID NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    PRIMARY KEY (promotion_id, category_id)
+);
+
+CREATE TABLE cart_coupons (
+    user_id UUID NOT NULL,
+    promotion_id UUID NOT NULL REFERENCES promotions(id) ON DELETE CASCADE,
+    added_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, promotion_id)
+);
+
+CREATE TABLE promotion_redemptions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    promotion_id UUID NOT NULL REFERENCES promotions(id) ON DELETE RESTRICT,
+    user_id UUID NOT NULL,
+    checkout_id UUID NOT NULL,
+    discount NUMERIC(10, 2) NOT NULL,
+    redeemed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (promotion_id, checkout_id)
+);
+
+CREATE INDEX idx_promotion_redemptions_user ON promotion_redemptions (promotion_id, user_id);
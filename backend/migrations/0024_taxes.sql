@@ -0,0 +1,65 @@
+-- Tax classes products belong to, tax zones (a country, or one region of it) with a rate
+-- per class, and the tax charged on each line of a completed checkout
+CREATE TABLE tax_classes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL UNIQUE,
+    description TEXT,
+    is_default BOOLEAN NOT NULL DEFAULT FALSE, -- for products without a class
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX idx_tax_classes_default ON tax_classes (is_default) WHERE is_default;
+
+INSERT INTO tax_classes (name, description, is_default) VALUES
+    ('Standard', 'Most goods', TRUE),
+    ('Reduced', 'Goods taxed at a reduced rate, e.g. food and books', FALSE),
+    ('Zero rated', 'Goods sold without tax', FALSE);
+
+-- NULL: the default class
+ALTER TABLE products ADD COLUMN tax_class_id UUID REFERENCES tax_classes(id) ON DELETE SET NULL;
+
+CREATE TABLE tax_zones (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL,
+    country CHAR(2) NOT NULL, -- ISO 3166-1 alpha-2
+    region VARCHAR(100), -- state or province; NULL: the whole country
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX idx_tax_zones_area ON tax_zones (country, upper(coalesce(region, '')));
+
+-- a region's zone takes precedence over its country's for the classes it has a rate for
+CREATE TABLE tax_rates (
+    zone_id UUID NOT NULL REFERENCES tax_zones(id) ON DELETE CASCADE,
+    tax_class_id UUID NOT NULL REFERENCES tax_classes(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL, -- shown to customers, e.g. 'VAT'
+    rate NUMERIC(7, 4) NOT NULL CHECK (rate >= 0 AND rate <= 100), -- percent
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (zone_id, tax_class_id)
+);
+
+-- names and rates are copied so the record survives later changes to the tables above.
+-- Amounts are in the base currency
+CREATE TABLE checkout_tax_lines (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    checkout_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    variant_id UUID,
+    quantity INT NOT NULL,
+    tax_class VARCHAR(100),
+    zone VARCHAR(100), -- NULL: no zone covers the address
+    rate_name VARCHAR(100),
+    rate NUMERIC(7, 4) NOT NULL,
+    country CHAR(2),
+    region VARCHAR(100),
+    prices_include_tax BOOLEAN NOT NULL,
+    calculator VARCHAR(50) NOT NULL, -- the TaxCalculator that worked it out
+    taxable_amount NUMERIC(12, 2) NOT NULL, -- after discounts, before tax
+    tax_amount NUMERIC(12, 2) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_checkout_tax_lines_checkout ON checkout_tax_lines (checkout_id);
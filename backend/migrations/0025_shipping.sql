@@ -0,0 +1,55 @@
+-- Product weights and dimensions, shipping zones (countries, or regions of them) and the
+-- delivery methods offered in each. Prices are in the base currency
+ALTER TABLE products
+    ADD COLUMN weight_grams INT CHECK (weight_grams >= 0),
+    ADD COLUMN length_mm INT CHECK (length_mm > 0),
+    ADD COLUMN width_mm INT CHECK (width_mm > 0),
+    ADD COLUMN height_mm INT CHECK (height_mm > 0);
+
+CREATE TABLE shipping_zones (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- an area belongs to one zone; an address in a listed region goes by that region's zone,
+-- anywhere else in the country by the country's
+CREATE TABLE shipping_zone_areas (
+    zone_id UUID NOT NULL REFERENCES shipping_zones(id) ON DELETE CASCADE,
+    country CHAR(2) NOT NULL, -- ISO 3166-1 alpha-2
+    region VARCHAR(100) -- NULL: the whole country
+);
+
+CREATE UNIQUE INDEX idx_shipping_zone_areas_area ON shipping_zone_areas (country, upper(coalesce(region, '')));
+CREATE INDEX idx_shipping_zone_areas_zone ON shipping_zone_areas (zone_id);
+
+CREATE TYPE shipping_method_kind AS ENUM ('flat_rate', 'weight_based', 'free_over_threshold', 'local_pickup');
+
+CREATE TABLE shipping_methods (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    zone_id UUID NOT NULL REFERENCES shipping_zones(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    kind shipping_method_kind NOT NULL,
+    price NUMERIC(10, 2) NOT NULL DEFAULT 0 CHECK (price >= 0), -- flat_rate, free_over_threshold below it, local_pickup
+    free_threshold NUMERIC(10, 2) CHECK (free_threshold > 0), -- free_over_threshold: subtotal after discounts
+    pickup_warehouse_id UUID REFERENCES warehouses(id) ON DELETE SET NULL, -- local_pickup
+    min_days INT CHECK (min_days >= 0), -- delivery estimate
+    max_days INT CHECK (max_days >= 0),
+    position INT NOT NULL DEFAULT 0, -- order offered in
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (min_days IS NULL OR max_days IS NULL OR max_days >= min_days)
+);
+
+CREATE INDEX idx_shipping_methods_zone ON shipping_methods (zone_id, position);
+
+-- weight_based: the price of the lightest bracket the parcel fits in; heavier parcels
+-- can't use the method
+CREATE TABLE shipping_weight_rates (
+    method_id UUID NOT NULL REFERENCES shipping_methods(id) ON DELETE CASCADE,
+    up_to_grams INT NOT NULL CHECK (up_to_grams > 0),
+    price NUMERIC(10, 2) NOT NULL CHECK (price >= 0),
+    PRIMARY KEY (method_id, up_to_grams)
+);
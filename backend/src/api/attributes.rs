@@ -0,0 +1,126 @@
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    http::StatusCode,
+    middleware,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::middleware::auth::require_admin;
+use crate::models::attribute::{AttributeDefinition, AttributeKind, NewAttributeDefinition, TagCount};
+use crate::pagination::{Page, PageParams};
+use crate::services::attribute::{
+    category_definitions, create_definition, delete_definition, list_tags, DEFINITION_SORTS, TAG_SORTS,
+};
+
+// reading definitions and tags is public; defining attributes is admin-only
+pub fn attribute_routes(pool: PgPool) -> Router<PgPool> {
+    let admin = Router::new()
+        .route("/categories/:id/attributes", post(create_definition_handler))
+        .route("/attributes/:id", delete(delete_definition_handler))
+        .route_layer(middleware::from_fn_with_state(pool.clone(), require_admin));
+
+    Router::new()
+        .route("/categories/:id/attributes", get(category_attributes_handler))
+        .route("/tags", get(list_tags_handler))
+        .merge(admin)
+        .with_state(pool)
+}
+
+// keys end up in JSON documents and filter strings, so keep them plain
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= 64
+        && key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+// the definitions products in this category can use, including inherited ones
+pub async fn category_attributes_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Path(category_id): Path<Uuid>,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<AttributeDefinition>>, (StatusCode, String)> {
+    let request = params
+        .resolve(DEFINITION_SORTS, "key")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    category_definitions(&pool, category_id, &request)
+        .await
+        .map(|definitions| Json(Page::new(definitions, &request, &uri)))
+        .map_err(|e| {
+            eprintln!("❌ Failed to load attribute definitions: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })
+}
+
+pub async fn create_definition_handler(
+    State(pool): State<PgPool>,
+    Path(category_id): Path<Uuid>,
+    Json(payload): Json<NewAttributeDefinition>,
+) -> Result<(StatusCode, Json<AttributeDefinition>), (StatusCode, String)> {
+    if !is_valid_key(&payload.key) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "key must be lower-case letters, digits and underscores".to_string(),
+        ));
+    }
+
+    match (payload.kind, payload.allowed_values.is_empty()) {
+        (AttributeKind::Enum, true) => {
+            return Err((StatusCode::BAD_REQUEST, "an enum attribute needs allowed_values".to_string()))
+        }
+        (kind, false) if kind != AttributeKind::Enum => {
+            return Err((StatusCode::BAD_REQUEST, "only enum attributes take allowed_values".to_string()))
+        }
+        _ => {}
+    }
+
+    match create_definition(&pool, category_id, payload).await {
+        Ok(definition) => Ok((StatusCode::CREATED, Json(definition))),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Category not found".to_string())),
+        Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("23505") => Err((
+            StatusCode::CONFLICT,
+            "The category already defines this attribute".to_string(),
+        )),
+        Err(e) => {
+            eprintln!("❌ Failed to create attribute definition: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to create attribute".to_string()))
+        }
+    }
+}
+
+// existing product values are kept; they just can't be set again
+pub async fn delete_definition_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    match delete_definition(&pool, id).await {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Attribute not found".to_string())),
+        Err(e) => {
+            eprintln!("❌ Failed to delete attribute definition: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete attribute".to_string()))
+        }
+    }
+}
+
+pub async fn list_tags_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<TagCount>>, (StatusCode, String)> {
+    let request = params
+        .resolve(TAG_SORTS, "products")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    list_tags(&pool, &request)
+        .await
+        .map(|tags| Json(Page::new(tags, &request, &uri)))
+        .map_err(|e| {
+            eprintln!("❌ Failed to list tags: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })
+}
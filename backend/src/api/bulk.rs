@@ -0,0 +1,105 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    middleware,
+    routing::post,
+    Json, Router,
+};
+use bigdecimal::{BigDecimal, Zero};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::config::bulk_max_products;
+use crate::etag::if_match;
+use crate::middleware::auth::{require_admin, AuthMiddleware};
+use crate::models::bulk::{BulkOperation, BulkRequest, BulkSummary};
+use crate::services::bulk::{apply_bulk, BulkError, BulkTarget};
+
+pub fn bulk_routes(pool: PgPool) -> Router<PgPool> {
+    Router::new()
+        .route("/products/bulk", post(bulk_products_handler))
+        .route_layer(middleware::from_fn_with_state(pool.clone(), require_admin))
+        .with_state(pool)
+}
+
+fn validate_operations(operations: &[BulkOperation]) -> Result<(), String> {
+    if operations.is_empty() {
+        return Err("Give at least one operation".to_string());
+    }
+
+    for operation in operations {
+        match operation {
+            BulkOperation::SetPrice { price } if *price < BigDecimal::zero() => {
+                return Err("Price cannot be negative".to_string())
+            }
+            BulkOperation::AdjustPrice { percent, amount } if percent.is_some() == amount.is_some() => {
+                return Err("adjust_price takes either percent or amount".to_string())
+            }
+            BulkOperation::SetStock { stock_quantity } if *stock_quantity < 0 => {
+                return Err("Stock quantity cannot be negative".to_string())
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+// one set of operations over many products; failures are reported per product. Each listed
+// product is checked against its ETag in `versions`; `If-Match: *` skips the checks instead,
+// and is the only way to run over a filter
+pub async fn bulk_products_handler(
+    State(pool): State<PgPool>,
+    AuthMiddleware(claims): AuthMiddleware,
+    headers: HeaderMap,
+    Json(request): Json<BulkRequest>,
+) -> Result<Json<BulkSummary>, (StatusCode, String)> {
+    validate_operations(&request.operations).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let versions = match request.versions {
+        Some(versions) => Some(versions),
+        None => match if_match(&headers)? {
+            None => None,
+            Some(_) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "Send each product's ETag in versions; If-Match only takes * here".to_string(),
+                ))
+            }
+        },
+    };
+
+    let restores = request.operations.iter().any(|op| matches!(op, BulkOperation::Restore));
+    let target = match (request.ids, request.filter) {
+        (Some(ids), None) => {
+            if let Some(missing) = versions.as_ref().and_then(|v| ids.iter().find(|id| !v.contains_key(id))) {
+                return Err((StatusCode::BAD_REQUEST, format!("versions has no ETag for product {}", missing)));
+            }
+            BulkTarget::Ids(ids)
+        }
+        // filters only see live products, so they can't find anything to restore
+        (None, Some(_)) if restores => {
+            return Err((StatusCode::BAD_REQUEST, "Restoring needs a list of ids".to_string()))
+        }
+        // the products aren't known before the filter runs, so there are no versions to give
+        (None, Some(_)) if versions.is_some() => {
+            return Err((StatusCode::BAD_REQUEST, "A filter runs with If-Match: *, not versions".to_string()))
+        }
+        (None, Some(filter)) => BulkTarget::Filter(Box::new(filter)),
+        _ => return Err((StatusCode::BAD_REQUEST, "Give either ids or a filter".to_string())),
+    };
+
+    let actor_id = Uuid::parse_str(&claims.sub).ok();
+    let max_products = bulk_max_products();
+    match apply_bulk(&pool, target, &request.operations, versions.as_ref(), request.atomic, actor_id, max_products).await {
+        Ok(summary) => Ok(Json(summary)),
+        Err(BulkError::TooManyProducts(max)) => Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("A bulk operation can change at most {} products; narrow the filter", max),
+        )),
+        Err(BulkError::Database(e)) => {
+            eprintln!("❌ Bulk product update failed: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Bulk update failed".to_string()))
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use axum::{
-    extract::{State, Path},
+    extract::{State, Path, Query},
     Json, Router,
     http::StatusCode,
     routing::{get, post, delete},
@@ -7,13 +7,26 @@ use axum::{
 use sqlx::PgPool;
 use uuid::Uuid;
 use serde_json::json;
-use crate::models::cart::AddToCartRequest;
-use crate::services::cart;
+use crate::api::checkout::user_id_from;
+use crate::api::currencies::currency_error;
+use crate::api::taxes::{country_code, region_name, tax_address};
+use crate::models::cart::{AddToCartRequest, RemoveFromCartQuery};
+use crate::models::currency::CurrencyParams;
+use crate::models::promotion::ApplyCoupon;
+use crate::models::shipping::ShippingAddressParams;
+use crate::models::tax::TaxAddressParams;
+use crate::middleware::auth::AuthMiddleware;
+use crate::services::currency::converter_for;
+use crate::services::shipping_provider::shipping_providers_from_env;
+use crate::services::{cart, promotion, shipping, variant};
 
 pub fn cart_routes() -> Router<PgPool> {
     Router::new()
         .route("/cart", post(add_to_cart).get(get_cart))
         .route("/cart/:product_id", delete(remove_from_cart))
+        .route("/cart/coupons", post(apply_coupon))
+        .route("/cart/coupons/:code", delete(remove_coupon))
+        .route("/cart/shipping-options", get(shipping_options))
 }
 
 
@@ -21,20 +34,41 @@ pub fn cart_routes() -> Router<PgPool> {
 
 async fn add_to_cart(
     State(pool): State<PgPool>,
+    AuthMiddleware(claims): AuthMiddleware,
     Json(payload): Json<AddToCartRequest>,
 ) -> Result<Json<impl serde::Serialize>, (StatusCode, String)> {
-    let user_id = get_user_id(); // Replace with real JWT logic
+    let user_id = user_id_from(&claims.sub)?;
+
+    if payload.quantity <= 0 {
+        return Err((StatusCode::BAD_REQUEST, "Quantity must be at least 1".to_string()));
+    }
+
+    if let Some(variant_id) = payload.variant_id {
+        match variant::variant_belongs_to_product(&pool, variant_id, payload.product_id).await {
+            Ok(true) => {}
+            Ok(false) => return Err((StatusCode::BAD_REQUEST, "Variant does not belong to this product".to_string())),
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to add to cart: {}", e))),
+        }
+    }
+
     match cart::add_to_cart(&pool, user_id, payload).await {
         Ok(item) => Ok(Json(item)),
         Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to add to cart: {}", e))),
     }
 }
 
+// the cart with unit prices, line totals, discounts, tax and totals; `?currency=` converts
+// them and `?country=&region=` is where the tax is worked out for
 async fn get_cart(
     State(pool): State<PgPool>,
+    AuthMiddleware(claims): AuthMiddleware,
+    Query(currency): Query<CurrencyParams>,
+    Query(address): Query<TaxAddressParams>,
 ) -> Result<Json<impl serde::Serialize>, (StatusCode, String)> {
-    let user_id = get_user_id();
-    match cart::get_user_cart(&pool, user_id).await {
+    let user_id = user_id_from(&claims.sub)?;
+    let address = tax_address(&address)?;
+    let converter = converter_for(&pool, currency.currency.as_deref()).await.map_err(currency_error)?;
+    match cart::price_cart(&pool, user_id, &converter, address.as_ref()).await {
         Ok(items) => Ok(Json(items)),
         Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch cart: {}", e))),
     }
@@ -42,15 +76,78 @@ async fn get_cart(
 
 async fn remove_from_cart(
     State(pool): State<PgPool>,
+    AuthMiddleware(claims): AuthMiddleware,
     Path(product_id): Path<Uuid>,
+    Query(query): Query<RemoveFromCartQuery>,
 ) -> Result<Json<impl serde::Serialize>, (StatusCode, String)> {
-    let user_id = get_user_id();
-    match cart::remove_from_cart(&pool, user_id, product_id).await {
+    let user_id = user_id_from(&claims.sub)?;
+    match cart::remove_from_cart(&pool, user_id, product_id, query.variant_id).await {
         Ok(count) => Ok(Json(json!({ "message": "Removed from cart", "deleted": count }))),
         Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to remove: {}", e))),
     }
 }
 
-fn get_user_id() -> Uuid {
-    Uuid::parse_str("103b1059-1626-4b18-89c1-04d392335962").unwrap()
+// the coupon stays on the cart even when it doesn't apply yet; the cart explains why
+async fn apply_coupon(
+    State(pool): State<PgPool>,
+    AuthMiddleware(claims): AuthMiddleware,
+    Query(currency): Query<CurrencyParams>,
+    Query(address): Query<TaxAddressParams>,
+    Json(payload): Json<ApplyCoupon>,
+) -> Result<Json<impl serde::Serialize>, (StatusCode, String)> {
+    let user_id = user_id_from(&claims.sub)?;
+    let address = tax_address(&address)?;
+    let converter = converter_for(&pool, currency.currency.as_deref()).await.map_err(currency_error)?;
+
+    let promotion_id = match promotion::find_coupon(&pool, &payload.code).await {
+        Ok(Some(id)) => id,
+        Ok(None) => return Err((StatusCode::NOT_FOUND, "Unknown coupon code".to_string())),
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to apply coupon: {}", e))),
+    };
+
+    if let Err(e) = promotion::add_cart_coupon(&pool, user_id, promotion_id).await {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to apply coupon: {}", e)));
+    }
+
+    match cart::price_cart(&pool, user_id, &converter, address.as_ref()).await {
+        Ok(summary) => Ok(Json(summary)),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch cart: {}", e))),
+    }
+}
+
+async fn remove_coupon(
+    State(pool): State<PgPool>,
+    AuthMiddleware(claims): AuthMiddleware,
+    Path(code): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let user_id = user_id_from(&claims.sub)?;
+    match promotion::remove_cart_coupon(&pool, user_id, &code).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "That coupon isn't on the cart".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to remove coupon: {}", e))),
+    }
+}
+
+// delivery options for the cart to `?country=&region=`, cheapest first; `?currency=` converts them
+async fn shipping_options(
+    State(pool): State<PgPool>,
+    AuthMiddleware(claims): AuthMiddleware,
+    Query(currency): Query<CurrencyParams>,
+    Query(address): Query<ShippingAddressParams>,
+) -> Result<Json<impl serde::Serialize>, (StatusCode, String)> {
+    let user_id = user_id_from(&claims.sub)?;
+    let country = address
+        .country
+        .as_deref()
+        .ok_or_else(|| "A country is required".to_string())
+        .and_then(country_code)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let region = region_name(address.region.as_deref());
+    let converter = converter_for(&pool, currency.currency.as_deref()).await.map_err(currency_error)?;
+
+    let providers = shipping_providers_from_env(pool.clone());
+    match shipping::quote_cart(&pool, user_id, country, region, &converter, &providers).await {
+        Ok(quote) => Ok(Json(quote)),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to quote shipping: {}", e))),
+    }
 }
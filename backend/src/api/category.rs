@@ -1,19 +1,35 @@
 use crate::services::category::{filter_categories_handler, get_category_by_id_handler, update_category_handler};
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{OriginalUri, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    middleware,
+    response::{IntoResponse, Response},
     routing::{delete, get, patch, post},
     Json, Router
 };
 use sqlx::PgPool;
+use crate::middleware::auth::{require_admin, AuthMiddleware};
+use crate::etag::{if_match, with_etag};
+use crate::models::history::HistoryEntity;
+use crate::services::history::{current_version, set_actor, Versioned};
 use uuid::Uuid;
 use crate::{
-    models::category::CreateCategory,
-    services::category::{create_category, list_categories},
+    models::category::{Category, CategoryNode, CreateCategory, MoveCategory},
+    models::trash::{DeleteCategoryOutcome, DeleteCategoryParams},
+    pagination::{Page, PageParams},
+    services::slug::{is_valid_slug, resolve_old_slug, slug_redirect, SlugOwner, INVALID_SLUG},
+    services::category::{
+        category_breadcrumbs, category_by_slug, category_representation, category_tree, create_category,
+        list_categories, move_category, soft_delete_category, CATEGORY_SORTS,
+    },
 };
 
-pub fn category_routes() -> Router<PgPool> {
+// reshaping the tree is admin-only
+pub fn category_routes(pool: PgPool) -> Router<PgPool> {
+    let admin = Router::new()
+        .route("/categories/:id/move", patch(move_category_handler))
+        .route_layer(middleware::from_fn_with_state(pool.clone(), require_admin));
+
     Router::new()
         .route("/create", post(create_category_handler))
         .route("/:id", get(get_category_by_id_handler))
@@ -22,59 +38,98 @@ pub fn category_routes() -> Router<PgPool> {
         .route("/delete/soft/:id", patch(soft_delete_category_handler))
         .route("/filter", get(filter_categories_handler))
         .route("/delete/hard/:id", delete(hard_delete_category_handler))
+        .route("/categories/tree", get(category_tree_handler))
+        .route("/categories/slug/:slug", get(category_by_slug_handler))
+        .route("/categories/:id/tree", get(category_subtree_handler))
+        .route("/categories/:id/breadcrumbs", get(category_breadcrumbs_handler))
+        .merge(admin)
+        .with_state(pool)
 }
 
 pub async fn create_category_handler(
     State(pool): State<PgPool>,
+    auth: Option<AuthMiddleware>,
     Json(payload): Json<CreateCategory>,
 ) -> Result<Json<impl serde::Serialize>, (StatusCode, String)> {
-    let category = create_category(&pool, payload)
+    if payload.slug.as_deref().is_some_and(|slug| !is_valid_slug(slug)) {
+        return Err((StatusCode::BAD_REQUEST, INVALID_SLUG.to_string()));
+    }
+
+    let category = create_category(&pool, payload, auth.and_then(|a| a.user_id()))
         .await
-        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", err)))?;
+        .map_err(|err| match err {
+            sqlx::Error::Database(e) if e.code().as_deref() == Some("23503") => {
+                (StatusCode::NOT_FOUND, "Parent category not found".to_string())
+            }
+            sqlx::Error::Database(e) if e.code().as_deref() == Some("23505") => {
+                (StatusCode::CONFLICT, "Category name or slug already exists".to_string())
+            }
+            err => (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", err)),
+        })?;
 
     Ok(Json(category))
 }
 
 pub async fn list_categories_handler(
     State(pool): State<PgPool>,
-) -> Result<Json<impl serde::Serialize>, (StatusCode, String)> {
-    let categories = list_categories(&pool)
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<Category>>, (StatusCode, String)> {
+    let request = params
+        .resolve(CATEGORY_SORTS, "created_at")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let categories = list_categories(&pool, &request)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok(Json(categories))
+    Ok(Json(Page::new(categories, &request, &uri)))
 }
 
+// moves the category and its subcategories to the trash; `products=detach|trash` says what
+// happens to products still in them, otherwise the delete is refused while any are left.
+// If-Match must carry the ETag the delete was decided on
 pub async fn soft_delete_category_handler(
     State(pool): State<PgPool>,
     Path(id): Path<Uuid>,
-) -> Result<StatusCode, StatusCode> {
-    let result = sqlx::query!(
-        r#"
-        UPDATE categories
-        SET is_deleted = TRUE, updated_at = NOW()
-        WHERE id = $1
-        "#,
-        id
-    )
-    .execute(&pool)
-    .await;
-
-    match result {
-        Ok(_) => Ok(StatusCode::NO_CONTENT),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    Query(params): Query<DeleteCategoryParams>,
+    auth: Option<AuthMiddleware>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let expected_version = if_match(&headers)?;
+    let actor_id = auth.and_then(|a| a.user_id());
+    match soft_delete_category(&pool, id, params.products.unwrap_or_default(), expected_version, actor_id).await {
+        Ok(DeleteCategoryOutcome::Deleted(deletion)) => Ok(Json(deletion).into_response()),
+        Ok(DeleteCategoryOutcome::VersionMismatch) => stale_category(&pool, id).await,
+        Ok(DeleteCategoryOutcome::HasProducts(count)) => Err((
+            StatusCode::CONFLICT,
+            format!("Category still has {} products; pass products=detach or products=trash", count),
+        )),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Category not found".to_string())),
+        Err(e) => {
+            eprintln!("❌ Failed to delete category: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete category".to_string()))
+        }
     }
 }
 
 pub async fn hard_delete_category_handler(
     Path(id): Path<Uuid>,
     State(pool): State<PgPool>,
+    auth: Option<AuthMiddleware>,
 ) -> impl IntoResponse {
-    let result = sqlx::query!(
-        "DELETE FROM categories WHERE id = $1",
-        id
-    )
-    .execute(&pool)
+    let result = async {
+        let mut tx = pool.begin().await?;
+        set_actor(&mut tx, auth.and_then(|a| a.user_id())).await?;
+        let res = sqlx::query!(
+            "DELETE FROM categories WHERE id = $1",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok::<_, sqlx::Error>(res)
+    }
     .await;
 
     match result {
@@ -100,4 +155,108 @@ pub async fn hard_delete_category_handler(
         }
     }
 }
- 
\ No newline at end of file
+ 
+// the whole category tree, top-level categories first
+pub async fn category_tree_handler(
+    State(pool): State<PgPool>,
+) -> Result<Json<Vec<CategoryNode>>, (StatusCode, String)> {
+    category_tree(&pool, None)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("❌ Failed to load category tree: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })
+}
+
+pub async fn category_subtree_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<CategoryNode>, (StatusCode, String)> {
+    category_tree(&pool, Some(id))
+        .await
+        .map_err(|e| {
+            eprintln!("❌ Failed to load category tree: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })?
+        .pop()
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, "Category not found".to_string()))
+}
+
+// not paged: the path is only as long as the tree is deep, and it has to come back whole, root first
+pub async fn category_breadcrumbs_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<Category>>, (StatusCode, String)> {
+    let path = category_breadcrumbs(&pool, id)
+        .await
+        .map_err(|e| {
+            eprintln!("❌ Failed to load breadcrumbs: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })?;
+
+    if path.is_empty() {
+        return Err((StatusCode::NOT_FOUND, "Category not found".to_string()));
+    }
+
+    Ok(Json(path))
+}
+
+// someone else saved first: send what they saved
+async fn stale_category(pool: &PgPool, id: Uuid) -> Result<Response, (StatusCode, String)> {
+    match category_representation(pool, id).await {
+        Ok((category, version)) => Ok(with_etag(version, StatusCode::PRECONDITION_FAILED, Json(category))),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Category not found".to_string())),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e))),
+    }
+}
+
+// If-Match must carry the ETag the move was decided on
+pub async fn move_category_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    auth: AuthMiddleware,
+    headers: HeaderMap,
+    Json(payload): Json<MoveCategory>,
+) -> Result<Response, (StatusCode, String)> {
+    let expected_version = if_match(&headers)?;
+    match move_category(&pool, id, payload.parent_id, expected_version, auth.user_id()).await {
+        Ok(Versioned::Saved(category, version)) => Ok(with_etag(version, StatusCode::OK, Json(category))),
+        Ok(Versioned::Stale) => stale_category(&pool, id).await,
+        Err(sqlx::Error::RowNotFound) => {
+            Err((StatusCode::NOT_FOUND, "Category or parent not found".to_string()))
+        }
+        Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("23514") => Err((
+            StatusCode::CONFLICT,
+            "A category cannot be moved under itself or one of its descendants".to_string(),
+        )),
+        Err(e) => {
+            eprintln!("❌ Failed to move category: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to move category".to_string()))
+        }
+    }
+}
+
+// category page lookup; an old slug answers 301 with the current location
+pub async fn category_by_slug_handler(
+    State(pool): State<PgPool>,
+    Path(slug): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let db_error = |e: sqlx::Error| {
+        eprintln!("❌ Database error: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    };
+
+    let mut conn = pool.acquire().await.map_err(db_error)?;
+
+    if let Some(category) = category_by_slug(&pool, &slug).await.map_err(db_error)? {
+        let version = current_version(&mut conn, HistoryEntity::Category, category.id).await.map_err(db_error)?;
+        return Ok(with_etag(version, StatusCode::OK, Json(category)));
+    }
+
+    match resolve_old_slug(&mut conn, SlugOwner::Category, &slug).await.map_err(db_error)? {
+        Some((id, current)) => Ok(slug_redirect(format!("/api/categories/slug/{}", current), id, &current)),
+        None => Err((StatusCode::NOT_FOUND, "Category not found".to_string())),
+    }
+}
@@ -0,0 +1,112 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::taxes::tax_address;
+use crate::config::reservation_ttl;
+use crate::middleware::auth::AuthMiddleware;
+use crate::models::inventory::StockMovement;
+use crate::models::reservation::CheckoutReservation;
+use crate::models::tax::{TaxAddressParams, TaxBreakdown};
+use crate::services::reservation::{convert_checkout, release_checkout, reserve_cart, ReservationError};
+use crate::services::tax::checkout_tax;
+
+pub fn checkout_routes() -> Router<PgPool> {
+    Router::new()
+        .route("/checkout/reserve", post(start_checkout_handler))
+        .route("/checkout/:id/release", post(release_checkout_handler))
+        .route("/checkout/:id/complete", post(complete_checkout_handler))
+        .route("/checkout/:id/tax", get(checkout_tax_handler))
+}
+
+fn reservation_error(err: ReservationError) -> (StatusCode, String) {
+    match err {
+        ReservationError::EmptyCart => (StatusCode::BAD_REQUEST, "Cart is empty".to_string()),
+        ReservationError::InvalidQuantity(product_id) => (
+            StatusCode::BAD_REQUEST,
+            format!("Quantity for product {} must be at least 1", product_id),
+        ),
+        ReservationError::NotFound => (StatusCode::NOT_FOUND, "Checkout or product not found".to_string()),
+        ReservationError::Expired => (StatusCode::GONE, "Checkout reservation has expired".to_string()),
+        ReservationError::InsufficientStock(product_id) => (
+            StatusCode::CONFLICT,
+            format!("Not enough stock available for product {}", product_id),
+        ),
+        ReservationError::Database(e) => {
+            eprintln!("❌ Checkout error: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        }
+    }
+}
+
+pub fn user_id_from(sub: &str) -> Result<Uuid, (StatusCode, String)> {
+    Uuid::parse_str(sub).map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid token subject".to_string()))
+}
+
+// start checkout: reserve the whole cart until payment completes or the hold expires
+pub async fn start_checkout_handler(
+    State(pool): State<PgPool>,
+    AuthMiddleware(claims): AuthMiddleware,
+) -> Result<(StatusCode, Json<CheckoutReservation>), (StatusCode, String)> {
+    let user_id = user_id_from(&claims.sub)?;
+
+    reserve_cart(&pool, user_id, reservation_ttl())
+        .await
+        .map(|reservation| (StatusCode::CREATED, Json(reservation)))
+        .map_err(reservation_error)
+}
+
+// payment failed or was cancelled
+pub async fn release_checkout_handler(
+    State(pool): State<PgPool>,
+    AuthMiddleware(claims): AuthMiddleware,
+    Path(checkout_id): Path<Uuid>,
+) -> Result<Json<impl serde::Serialize>, (StatusCode, String)> {
+    let user_id = user_id_from(&claims.sub)?;
+
+    let released = release_checkout(&pool, checkout_id, user_id)
+        .await
+        .map_err(|e| reservation_error(e.into()))?;
+
+    Ok(Json(json!({ "message": "Reservation released", "released": released })))
+}
+
+// payment succeeded; `?country=&region=` is the shipping address the tax is charged for
+pub async fn complete_checkout_handler(
+    State(pool): State<PgPool>,
+    AuthMiddleware(claims): AuthMiddleware,
+    Path(checkout_id): Path<Uuid>,
+    Query(address): Query<TaxAddressParams>,
+) -> Result<Json<Vec<StockMovement>>, (StatusCode, String)> {
+    let user_id = user_id_from(&claims.sub)?;
+    let address = tax_address(&address)?;
+
+    convert_checkout(&pool, checkout_id, user_id, address.as_ref())
+        .await
+        .map(Json)
+        .map_err(reservation_error)
+}
+
+// the tax charged on a completed checkout, line by line, in the base currency
+pub async fn checkout_tax_handler(
+    State(pool): State<PgPool>,
+    AuthMiddleware(claims): AuthMiddleware,
+    Path(checkout_id): Path<Uuid>,
+) -> Result<Json<TaxBreakdown>, (StatusCode, String)> {
+    let user_id = user_id_from(&claims.sub)?;
+
+    match checkout_tax(&pool, checkout_id, user_id).await {
+        Ok(tax) => Ok(Json(tax)),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Completed checkout not found".to_string())),
+        Err(e) => {
+            eprintln!("❌ Failed to load checkout tax: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string()))
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    http::{StatusCode, Uri},
+    middleware,
+    routing::{get, post, put},
+    Json, Router,
+};
+use bigdecimal::{BigDecimal, Zero};
+use serde_json::json;
+use sqlx::PgPool;
+
+use crate::config::store_currency;
+use crate::middleware::auth::{require_admin, AuthMiddleware};
+use crate::models::currency::{Currency, CurrencyRate, ExchangeRate, SetExchangeRate, UpsertCurrency};
+use crate::pagination::{Page, PageParams};
+use crate::services::currency::{list_currencies, set_rate, upsert_currency, CurrencyError, CURRENCY_SORTS};
+use crate::services::rate_provider::{rate_provider_from_env, refresh_rates};
+
+// the storefront lists currencies to pick from; setting them up and their rates is admin-only
+pub fn currency_routes(pool: PgPool) -> Router<PgPool> {
+    let admin = Router::new()
+        .route("/currencies/all", get(list_all_currencies_handler))
+        .route("/currencies/:code", put(upsert_currency_handler))
+        .route("/currencies/:code/rate", put(set_rate_handler))
+        .route("/currencies/rates/refresh", post(refresh_rates_handler))
+        .route_layer(middleware::from_fn_with_state(pool.clone(), require_admin));
+
+    Router::new()
+        .route("/currencies", get(list_currencies_handler))
+        .merge(admin)
+        .with_state(pool)
+}
+
+// for handlers taking `?currency=`
+pub fn currency_error(err: CurrencyError) -> (StatusCode, String) {
+    match err {
+        CurrencyError::Unknown(code) => (StatusCode::BAD_REQUEST, format!("Unsupported currency {}", code)),
+        CurrencyError::NoRate(code) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            format!("No exchange rate for {} yet", code),
+        ),
+        CurrencyError::Database(e) => {
+            eprintln!("❌ Failed to load currency: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        }
+    }
+}
+
+fn currency_code(code: &str) -> Result<String, (StatusCode, String)> {
+    let code = code.trim().to_uppercase();
+    if code.len() != 3 || !code.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err((StatusCode::BAD_REQUEST, "Currency codes are three letters (ISO 4217)".to_string()));
+    }
+    Ok(code)
+}
+
+async fn currencies(
+    pool: &PgPool,
+    include_disabled: bool,
+    uri: &Uri,
+    params: PageParams,
+) -> Result<Json<Page<CurrencyRate>>, (StatusCode, String)> {
+    let request = params
+        .resolve(CURRENCY_SORTS, "code")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    list_currencies(pool, include_disabled, &request)
+        .await
+        .map(|currencies| Json(Page::new(currencies, &request, uri)))
+        .map_err(|e| {
+            eprintln!("❌ Failed to list currencies: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })
+}
+
+pub async fn list_currencies_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<CurrencyRate>>, (StatusCode, String)> {
+    currencies(&pool, false, &uri, params).await
+}
+
+// disabled currencies too
+pub async fn list_all_currencies_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<CurrencyRate>>, (StatusCode, String)> {
+    currencies(&pool, true, &uri, params).await
+}
+
+pub async fn upsert_currency_handler(
+    State(pool): State<PgPool>,
+    Path(code): Path<String>,
+    Json(payload): Json<UpsertCurrency>,
+) -> Result<Json<Currency>, (StatusCode, String)> {
+    let code = currency_code(&code)?;
+    if payload.name.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Name is required".to_string()));
+    }
+    if !(0..=4).contains(&payload.decimals) {
+        return Err((StatusCode::BAD_REQUEST, "Decimals must be between 0 and 4".to_string()));
+    }
+    if payload.rounding_increment.as_ref().is_some_and(|i| *i <= BigDecimal::zero()) {
+        return Err((StatusCode::BAD_REQUEST, "Rounding increment must be positive".to_string()));
+    }
+    if code == store_currency() && payload.enabled == Some(false) {
+        return Err((StatusCode::BAD_REQUEST, "The base currency can't be disabled".to_string()));
+    }
+
+    upsert_currency(&pool, &code, &payload).await.map(Json).map_err(|e| {
+        eprintln!("❌ Failed to save currency: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to save currency".to_string())
+    })
+}
+
+pub async fn set_rate_handler(
+    State(pool): State<PgPool>,
+    Path(code): Path<String>,
+    auth: AuthMiddleware,
+    Json(payload): Json<SetExchangeRate>,
+) -> Result<Json<ExchangeRate>, (StatusCode, String)> {
+    let code = currency_code(&code)?;
+    if code == store_currency() {
+        return Err((StatusCode::BAD_REQUEST, "The base currency's rate is always 1".to_string()));
+    }
+    if payload.rate <= BigDecimal::zero() {
+        return Err((StatusCode::BAD_REQUEST, "Rate must be positive".to_string()));
+    }
+
+    let db_error = |e: sqlx::Error| {
+        eprintln!("❌ Failed to set exchange rate: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to set exchange rate".to_string())
+    };
+
+    let mut conn = pool.acquire().await.map_err(db_error)?;
+    match set_rate(&mut conn, &code, &payload.rate, "admin", auth.user_id()).await {
+        Ok(rate) => Ok(Json(rate)),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Currency not found".to_string())),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+// pulls rates from the configured provider now rather than at its next refresh
+pub async fn refresh_rates_handler(
+    State(pool): State<PgPool>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let provider = rate_provider_from_env().ok_or((
+        StatusCode::CONFLICT,
+        "No rate provider configured; set EXCHANGE_RATES_FILE".to_string(),
+    ))?;
+
+    match refresh_rates(&pool, provider.as_ref()).await {
+        Ok(updated) => Ok(Json(json!({ "updated": updated, "source": provider.name() }))),
+        Err(e) => {
+            eprintln!("❌ Failed to refresh exchange rates: {:?}", e);
+            Err((StatusCode::BAD_GATEWAY, "Failed to refresh exchange rates".to_string()))
+        }
+    }
+}
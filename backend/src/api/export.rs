@@ -0,0 +1,51 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use futures_util::stream;
+use sqlx::PgPool;
+
+use crate::models::export::ExportFormat;
+use crate::models::product::ProductQueryParams;
+use crate::services::export::start_export;
+
+pub fn export_routes(pool: PgPool) -> Router<PgPool> {
+    Router::new()
+        .route("/products/export/:format", get(export_products_handler)) // GET /api/products/export/csv?tags=eco
+        .with_state(pool)
+}
+
+// the whole catalogue (or whatever matches the search filters), streamed as it's read;
+// paging and sort parameters are ignored
+pub async fn export_products_handler(
+    State(pool): State<PgPool>,
+    Path(format): Path<ExportFormat>,
+    Query(params): Query<ProductQueryParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let chunks = start_export(&pool, &params, format).await.map_err(|e| {
+        eprintln!("❌ Failed to start product export: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Export failed".to_string())
+    })?;
+
+    let (content_type, extension) = match format {
+        ExportFormat::Csv => ("text/csv; charset=utf-8", "csv"),
+        ExportFormat::Ndjson => ("application/x-ndjson", "ndjson"),
+        ExportFormat::Xml => ("application/rss+xml; charset=utf-8", "xml"),
+    };
+
+    let body = Body::from_stream(stream::unfold(chunks, |mut chunks| async move {
+        chunks.recv().await.map(|chunk| (chunk, chunks))
+    }));
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"products.{}\"", extension)),
+        ],
+        body,
+    ))
+}
@@ -0,0 +1,100 @@
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    http::{HeaderMap, StatusCode, Uri},
+    response::Response,
+    middleware,
+    routing::{get, post},
+    Json, Router,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::products::{saved_product, stale_product};
+use crate::etag::if_match;
+use crate::middleware::auth::{require_admin, AuthMiddleware};
+use crate::models::history::{ChangeRecord, HistoryEntity};
+use crate::pagination::{Page, PageParams};
+use crate::services::history::{list_history, revert_product, RevertError, HISTORY_SORTS};
+
+// versions recorded for every product and category change, newest first
+pub fn history_routes(pool: PgPool) -> Router<PgPool> {
+    Router::new()
+        .route("/products/:id/history", get(product_history_handler))
+        .route("/products/:id/history/:version/revert", post(revert_product_handler))
+        .route("/categories/:id/history", get(category_history_handler))
+        .route_layer(middleware::from_fn_with_state(pool.clone(), require_admin))
+        .with_state(pool)
+}
+
+async fn history_page(
+    pool: &PgPool,
+    entity: HistoryEntity,
+    id: Uuid,
+    params: PageParams,
+    uri: &Uri,
+) -> Result<Json<Page<ChangeRecord>>, (StatusCode, String)> {
+    let request = params
+        .resolve(HISTORY_SORTS, "version")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let history = list_history(pool, entity, id, &request).await.map_err(|e| {
+        eprintln!("❌ Failed to load change history: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    if history.total == 0 {
+        return Err((StatusCode::NOT_FOUND, "No history for this id".to_string()));
+    }
+
+    Ok(Json(Page::new(history, &request, uri)))
+}
+
+pub async fn product_history_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Path(id): Path<Uuid>,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<ChangeRecord>>, (StatusCode, String)> {
+    history_page(&pool, HistoryEntity::Product, id, params, &uri).await
+}
+
+pub async fn category_history_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Path(id): Path<Uuid>,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<ChangeRecord>>, (StatusCode, String)> {
+    history_page(&pool, HistoryEntity::Category, id, params, &uri).await
+}
+
+// If-Match must carry the ETag of the product the revert was decided on
+pub async fn revert_product_handler(
+    State(pool): State<PgPool>,
+    Path((id, version)): Path<(Uuid, i32)>,
+    auth: AuthMiddleware,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let expected_version = if_match(&headers)?;
+
+    match revert_product(&pool, id, version, expected_version, auth.user_id()).await {
+        Ok((product, new_version)) => saved_product(&pool, product, new_version).await,
+        // someone else saved first: send what they saved
+        Err(RevertError::VersionMismatch) => stale_product(&pool, id).await,
+        Err(RevertError::NotFound) => Err((StatusCode::NOT_FOUND, "Product or version not found".to_string())),
+        Err(RevertError::InTrash) => Err((
+            StatusCode::CONFLICT,
+            "The product is in the trash; restore it first".to_string(),
+        )),
+        Err(RevertError::CategoryNotFound) => Err((
+            StatusCode::CONFLICT,
+            "A category from that version no longer exists".to_string(),
+        )),
+        Err(RevertError::Database(e)) if e.as_database_error().and_then(|e| e.code()).as_deref() == Some("23505") => {
+            Err((StatusCode::CONFLICT, "That version's slug or SKU is now used by another product".to_string()))
+        }
+        Err(RevertError::Database(e)) => {
+            eprintln!("❌ Failed to revert product: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to revert product".to_string()))
+        }
+    }
+}
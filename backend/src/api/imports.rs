@@ -0,0 +1,111 @@
+use axum::{
+    body::Bytes,
+    extract::{DefaultBodyLimit, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware,
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::config::import_max_bytes;
+use crate::middleware::auth::{require_admin, AuthMiddleware};
+use crate::models::import::{ImportFormat, ImportJob, ImportOptions, ImportParams};
+use crate::services::import::{create_job, errors_csv, get_job, job_errors, spawn_import};
+
+pub fn import_routes(pool: PgPool) -> Router<PgPool> {
+    Router::new()
+        .route(
+            "/imports/products",
+            post(start_import_handler).layer(DefaultBodyLimit::max(import_max_bytes())),
+        )
+        .route("/imports/:id", get(import_status_handler))
+        .route("/imports/:id/errors", get(import_errors_handler))
+        .route_layer(middleware::from_fn_with_state(pool.clone(), require_admin))
+        .with_state(pool)
+}
+
+fn format_from_content_type(headers: &HeaderMap) -> Option<ImportFormat> {
+    let content_type = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+    match content_type.split(';').next()?.trim() {
+        "text/csv" => Some(ImportFormat::Csv),
+        "application/x-ndjson" | "application/ndjson" | "application/jsonl" => Some(ImportFormat::Ndjson),
+        _ => None,
+    }
+}
+
+// the file is the raw request body; the import runs in the background and is polled by id
+pub async fn start_import_handler(
+    State(pool): State<PgPool>,
+    AuthMiddleware(claims): AuthMiddleware,
+    Query(params): Query<ImportParams>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Json<ImportJob>), (StatusCode, String)> {
+    let format = params.format.or_else(|| format_from_content_type(&headers)).ok_or((
+        StatusCode::BAD_REQUEST,
+        "Pass format=csv or format=ndjson, or a text/csv or application/x-ndjson body".to_string(),
+    ))?;
+
+    if body.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "The file is empty".to_string()));
+    }
+
+    let options = ImportOptions {
+        format,
+        dry_run: params.dry_run.unwrap_or(false),
+        create_categories: params.create_categories.unwrap_or(false),
+    };
+
+    let job = create_job(&pool, options, Uuid::parse_str(&claims.sub).ok())
+        .await
+        .map_err(|e| {
+            eprintln!("❌ Failed to create import job: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to start import".to_string())
+        })?;
+
+    spawn_import(pool.clone(), job.id, options, body.to_vec());
+
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+pub async fn import_status_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ImportJob>, (StatusCode, String)> {
+    get_job(&pool, id)
+        .await
+        .map_err(|e| {
+            eprintln!("❌ Failed to load import job: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })?
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, "Import not found".to_string()))
+}
+
+// per-row error report as a CSV download
+pub async fn import_errors_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let db_error = |e: sqlx::Error| {
+        eprintln!("❌ Failed to load import errors: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    };
+
+    if get_job(&pool, id).await.map_err(db_error)?.is_none() {
+        return Err((StatusCode::NOT_FOUND, "Import not found".to_string()));
+    }
+
+    let errors = job_errors(&pool, id).await.map_err(db_error)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"import-{}-errors.csv\"", id)),
+        ],
+        errors_csv(&errors),
+    ))
+}
@@ -0,0 +1,243 @@
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
+    middleware,
+    routing::{get, post, put},
+    Json, Router,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::etag::{if_match, with_etag};
+use crate::middleware::auth::{require_admin, AuthMiddleware};
+use crate::models::inventory::{
+    LowStockAlert, LowStockProduct, NewStockMovement, RecordStockMovementRequest, StockDrift, StockMovement,
+    StockMovementKind, UpdateReorderThreshold,
+};
+use crate::models::notification::AdminNotification;
+use crate::pagination::{Page, PageParams};
+use crate::services::history::{product_version, Versioned};
+use crate::services::inventory::{
+    list_stock_drift, reconcile_stock, record_movement, stock_history, InventoryError, DRIFT_SORTS,
+    STOCK_MOVEMENT_SORTS,
+};
+use crate::services::low_stock::{
+    list_low_stock, list_notifications, list_open_alerts, mark_notification_read, reorder_threshold,
+    set_reorder_threshold, ALERT_SORTS, LOW_STOCK_SORTS, NOTIFICATION_SORTS,
+};
+
+pub fn inventory_routes(pool: PgPool) -> Router<PgPool> {
+    Router::new()
+        .route(
+            "/products/:id/stock-movements",
+            get(stock_history_handler).post(record_stock_movement_handler),
+        )
+        .route("/inventory/drift", get(list_drift_handler))
+        .route("/inventory/reconcile", post(reconcile_handler))
+        .route("/products/:id/reorder-threshold", put(set_reorder_threshold_handler))
+        .route("/inventory/low-stock", get(low_stock_handler))
+        .route("/inventory/alerts", get(list_alerts_handler))
+        .route("/notifications", get(list_notifications_handler))
+        .route("/notifications/:id/read", put(mark_notification_read_handler))
+        .route_layer(middleware::from_fn_with_state(pool.clone(), require_admin))
+        .with_state(pool)
+}
+
+pub async fn record_stock_movement_handler(
+    State(pool): State<PgPool>,
+    AuthMiddleware(claims): AuthMiddleware,
+    Path(product_id): Path<Uuid>,
+    Json(payload): Json<RecordStockMovementRequest>,
+) -> Result<(StatusCode, Json<Vec<StockMovement>>), (StatusCode, String)> {
+    if payload.kind == StockMovementKind::Transfer {
+        return Err((StatusCode::BAD_REQUEST, "Use the transfer endpoint to move stock between locations".to_string()));
+    }
+
+    if payload.quantity == 0 {
+        return Err((StatusCode::BAD_REQUEST, "Quantity cannot be zero".to_string()));
+    }
+
+    if payload.kind != StockMovementKind::Adjustment && payload.quantity < 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Quantity must be positive; use an adjustment for signed corrections".to_string(),
+        ));
+    }
+
+    let actor_id = Uuid::parse_str(&claims.sub)
+        .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid token subject".to_string()))?;
+
+    let movement = NewStockMovement {
+        product_id,
+        variant_id: payload.variant_id,
+        warehouse_id: payload.warehouse_id,
+        kind: payload.kind,
+        quantity: payload.kind.signed(payload.quantity),
+        reason: payload.reason,
+        actor_id: Some(actor_id),
+    };
+
+    match record_movement(&pool, movement).await {
+        Ok(movements) => Ok((StatusCode::CREATED, Json(movements))),
+        Err(InventoryError::NotFound) => Err((StatusCode::NOT_FOUND, "Product not found".to_string())),
+        Err(InventoryError::InsufficientStock) => {
+            Err((StatusCode::CONFLICT, "Not enough stock for this movement".to_string()))
+        }
+        Err(InventoryError::Database(e)) => {
+            eprintln!("❌ Failed to record stock movement: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to record stock movement".to_string()))
+        }
+    }
+}
+
+pub async fn stock_history_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Path(product_id): Path<Uuid>,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<StockMovement>>, (StatusCode, String)> {
+    let request = params
+        .resolve(STOCK_MOVEMENT_SORTS, "created_at")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    stock_history(&pool, product_id, &request)
+        .await
+        .map(|movements| Json(Page::new(movements, &request, &uri)))
+        .map_err(|e| {
+            eprintln!("❌ Failed to fetch stock history: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })
+}
+
+pub async fn list_drift_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<StockDrift>>, (StatusCode, String)> {
+    let request = params
+        .resolve(DRIFT_SORTS, "detected_at")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    list_stock_drift(&pool, &request)
+        .await
+        .map(|drift| Json(Page::new(drift, &request, &uri)))
+        .map_err(|e| {
+            eprintln!("❌ Failed to fetch stock drift: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })
+}
+
+// run the reconciliation job now instead of waiting for the next tick. Not paged: this is
+// what the run found, in full; GET /inventory/drift pages through the same flags afterwards
+pub async fn reconcile_handler(
+    State(pool): State<PgPool>,
+) -> Result<Json<Vec<StockDrift>>, (StatusCode, String)> {
+    reconcile_stock(&pool)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("❌ Stock reconciliation failed: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Stock reconciliation failed".to_string())
+        })
+}
+
+// If-Match must carry the product's ETag the edit was based on; the threshold is part of its history
+pub async fn set_reorder_threshold_handler(
+    State(pool): State<PgPool>,
+    Path(product_id): Path<Uuid>,
+    auth: AuthMiddleware,
+    headers: HeaderMap,
+    Json(payload): Json<UpdateReorderThreshold>,
+) -> Result<Response, (StatusCode, String)> {
+    let expected_version = if_match(&headers)?;
+    if payload.reorder_threshold.is_some_and(|t| t < 0) {
+        return Err((StatusCode::BAD_REQUEST, "Reorder threshold cannot be negative".to_string()));
+    }
+
+    let db_error = |e: sqlx::Error| {
+        eprintln!("❌ Failed to set reorder threshold: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Failed to set reorder threshold".to_string())
+    };
+
+    match set_reorder_threshold(&pool, product_id, payload.reorder_threshold, expected_version, auth.user_id()).await {
+        Ok(Versioned::Saved((), version)) => Ok(with_etag(version, StatusCode::OK, Json(payload))),
+        // someone else saved first: send the threshold as they left it
+        Ok(Versioned::Stale) => {
+            let reorder_threshold = reorder_threshold(&pool, product_id).await.map_err(db_error)?;
+            let version = product_version(&pool, product_id).await.map_err(db_error)?;
+            Ok(with_etag(version, StatusCode::PRECONDITION_FAILED, Json(UpdateReorderThreshold { reorder_threshold })))
+        }
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Product not found".to_string())),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+// products at or below their reorder threshold, sorted by days of cover
+pub async fn low_stock_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<LowStockProduct>>, (StatusCode, String)> {
+    let request = params
+        .resolve(LOW_STOCK_SORTS, "days_of_cover")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    list_low_stock(&pool, &request)
+        .await
+        .map(|products| Json(Page::new(products, &request, &uri)))
+        .map_err(|e| {
+            eprintln!("❌ Failed to fetch low stock products: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })
+}
+
+pub async fn list_alerts_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<LowStockAlert>>, (StatusCode, String)> {
+    let request = params
+        .resolve(ALERT_SORTS, "created_at")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    list_open_alerts(&pool, &request)
+        .await
+        .map(|alerts| Json(Page::new(alerts, &request, &uri)))
+        .map_err(|e| {
+            eprintln!("❌ Failed to fetch low stock alerts: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })
+}
+
+pub async fn list_notifications_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<AdminNotification>>, (StatusCode, String)> {
+    let request = params
+        .resolve(NOTIFICATION_SORTS, "created_at")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    list_notifications(&pool, &request)
+        .await
+        .map(|notifications| Json(Page::new(notifications, &request, &uri)))
+        .map_err(|e| {
+            eprintln!("❌ Failed to fetch notifications: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })
+}
+
+pub async fn mark_notification_read_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    match mark_notification_read(&pool, id).await {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Notification not found".to_string())),
+        Err(e) => {
+            eprintln!("❌ Failed to update notification: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update notification".to_string()))
+        }
+    }
+}
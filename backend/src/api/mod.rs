@@ -2,5 +2,21 @@ pub mod auth;
 pub mod user;
 pub mod products;
 pub mod category;
+pub mod variants;
+pub mod inventory;
+pub mod checkout;
+pub mod warehouses;
+pub mod search_analytics;
+pub mod trash;
+pub mod attributes;
+pub mod imports;
 
 pub mod cart;
+pub mod export;
+pub mod bulk;
+pub mod history;
+pub mod pricing;
+pub mod currencies;
+pub mod promotions;
+pub mod taxes;
+pub mod shipping;
@@ -0,0 +1,174 @@
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
+    middleware,
+    routing::{delete, get},
+    Json, Router,
+};
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::etag::{if_match, with_etag};
+use crate::middleware::auth::{require_admin, AuthMiddleware};
+use crate::models::pricing::{PriceHistoryEntry, ScheduledPriceChange, SchedulePriceChange, SetPricing};
+use crate::pagination::{Page, PageParams};
+use crate::services::history::{product_version, Versioned};
+use crate::services::pricing::{
+    cancel_price_change, get_pricing, list_price_history, list_price_schedule, schedule_price_change, set_pricing,
+    PRICE_HISTORY_SORTS, PRICE_SCHEDULE_SORTS,
+};
+
+// sale and compare-at prices, scheduled base price changes and price history
+pub fn pricing_routes(pool: PgPool) -> Router<PgPool> {
+    Router::new()
+        .route("/products/:id/pricing", get(get_pricing_handler).put(set_pricing_handler))
+        .route("/products/:id/price-schedule", get(list_price_schedule_handler).post(schedule_price_change_handler))
+        .route("/price-schedule/:id", delete(cancel_price_change_handler))
+        .route("/products/:id/price-history", get(price_history_handler))
+        .route_layer(middleware::from_fn_with_state(pool.clone(), require_admin))
+        .with_state(pool)
+}
+
+fn validate_pricing(pricing: &SetPricing) -> Result<(), String> {
+    let negative = |price: &Option<BigDecimal>| price.as_ref().is_some_and(|p| *p < BigDecimal::from(0));
+    if negative(&pricing.compare_at_price) || negative(&pricing.sale_price) {
+        return Err("Prices cannot be negative".to_string());
+    }
+    if pricing.sale_price.is_none() && (pricing.sale_starts_at.is_some() || pricing.sale_ends_at.is_some()) {
+        return Err("A sale window needs a sale_price".to_string());
+    }
+    if let (Some(start), Some(end)) = (pricing.sale_starts_at, pricing.sale_ends_at) {
+        if end <= start {
+            return Err("sale_ends_at must be after sale_starts_at".to_string());
+        }
+    }
+    Ok(())
+}
+
+// with the product's ETag, for If-Match on the update
+async fn pricing_response(pool: &PgPool, id: Uuid, status: StatusCode) -> Result<Response, (StatusCode, String)> {
+    let db_error = |e: sqlx::Error| {
+        eprintln!("❌ Failed to load pricing: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    };
+
+    let pricing = match get_pricing(pool, id).await {
+        Ok(pricing) => pricing,
+        Err(sqlx::Error::RowNotFound) => return Err((StatusCode::NOT_FOUND, "Product not found".to_string())),
+        Err(e) => return Err(db_error(e)),
+    };
+    let version = product_version(pool, id).await.map_err(db_error)?;
+
+    Ok(with_etag(version, status, Json(pricing)))
+}
+
+pub async fn get_pricing_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Response, (StatusCode, String)> {
+    pricing_response(&pool, id, StatusCode::OK).await
+}
+
+// If-Match must carry the product's ETag the edit was based on
+pub async fn set_pricing_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    auth: AuthMiddleware,
+    headers: HeaderMap,
+    Json(payload): Json<SetPricing>,
+) -> Result<Response, (StatusCode, String)> {
+    let expected_version = if_match(&headers)?;
+    validate_pricing(&payload).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    match set_pricing(&pool, id, &payload, expected_version, auth.user_id()).await {
+        Ok(Versioned::Saved(pricing, version)) => Ok(with_etag(version, StatusCode::OK, Json(pricing))),
+        // someone else saved first: send what they saved
+        Ok(Versioned::Stale) => pricing_response(&pool, id, StatusCode::PRECONDITION_FAILED).await,
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Product not found".to_string())),
+        Err(e) => {
+            eprintln!("❌ Failed to set pricing: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to set pricing".to_string()))
+        }
+    }
+}
+
+pub async fn schedule_price_change_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    auth: AuthMiddleware,
+    Json(payload): Json<SchedulePriceChange>,
+) -> Result<(StatusCode, Json<ScheduledPriceChange>), (StatusCode, String)> {
+    if payload.price < BigDecimal::from(0) {
+        return Err((StatusCode::BAD_REQUEST, "Price cannot be negative".to_string()));
+    }
+    if payload.apply_at <= Utc::now().naive_utc() {
+        return Err((StatusCode::BAD_REQUEST, "apply_at must be in the future".to_string()));
+    }
+
+    match schedule_price_change(&pool, id, &payload, auth.user_id()).await {
+        Ok(change) => Ok((StatusCode::CREATED, Json(change))),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Product not found".to_string())),
+        Err(e) => {
+            eprintln!("❌ Failed to schedule price change: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to schedule price change".to_string()))
+        }
+    }
+}
+
+pub async fn list_price_schedule_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Path(id): Path<Uuid>,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<ScheduledPriceChange>>, (StatusCode, String)> {
+    let request = params
+        .resolve(PRICE_SCHEDULE_SORTS, "apply_at")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    list_price_schedule(&pool, id, &request)
+        .await
+        .map(|schedule| Json(Page::new(schedule, &request, &uri)))
+        .map_err(|e| {
+            eprintln!("❌ Failed to list scheduled price changes: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })
+}
+
+pub async fn cancel_price_change_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    match cancel_price_change(&pool, id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "No pending price change with that id".to_string())),
+        Err(e) => {
+            eprintln!("❌ Failed to cancel price change: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to cancel price change".to_string()))
+        }
+    }
+}
+
+pub async fn price_history_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Path(id): Path<Uuid>,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<PriceHistoryEntry>>, (StatusCode, String)> {
+    let request = params
+        .resolve(PRICE_HISTORY_SORTS, "changed_at")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let history = list_price_history(&pool, id, &request).await.map_err(|e| {
+        eprintln!("❌ Failed to load price history: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    if history.total == 0 {
+        return Err((StatusCode::NOT_FOUND, "No price history for this product".to_string()));
+    }
+
+    Ok(Json(Page::new(history, &request, &uri)))
+}
@@ -1,20 +1,35 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    extract::{OriginalUri, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
     routing::{delete, get, post, put},
     Json, Router,
 };
-use sqlx::{FromRow, PgPool, QueryBuilder};
+use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::{models::product::{Product, ProductQueryParams, UpdateProduct}, services::product::{create_product, delete_product, soft_delete_product, update_product}};
+use crate::{models::product::{Product, ProductDetails, ProductQueryParams, UpdateProduct}, services::product::{create_product, delete_product, product_by_slug, soft_delete_product, update_product, with_details, ProductError}};
+use crate::services::slug::{is_valid_slug, resolve_old_slug, slug_redirect, SlugOwner, INVALID_SLUG};
+use crate::middleware::auth::AuthMiddleware;
+use crate::etag::{if_match, with_etag};
+use crate::models::history::HistoryEntity;
+use crate::services::history::current_version;
+use crate::pagination::Page;
+use crate::services::search_analytics::{search_filters, spawn_record_search};
+use crate::services::search::{default_product_sort, search_products, suggest, PRODUCT_SORTS};
 use crate::models::product::CreateProduct;
+use crate::api::currencies::currency_error;
+use crate::models::currency::CurrencyParams;
+use crate::services::currency::{converter_for, Converter};
+use crate::models::search::{SearchResults, SuggestParams, Suggestion};
 
 pub fn product_routes(pool: PgPool) -> Router<PgPool> {
     Router::new()
         .route("/", post(create_product_handler))       // POST /api/product
         .route("/", get(list_products))                // GET /api/product
         .route("/search", get(search_products_handler)) // GET /api/product/search
+        .route("/products/suggest", get(suggest_handler)) // GET /api/products/suggest?q=
+        .route("/products/slug/:slug", get(get_product_by_slug)) // GET /api/products/slug/:slug
         .route("/get/:id", get(get_product))               // GET /api/product/:id
         .route("/update/:id", put(update_product_handler))    // PUT /api/product/:id
         .route("/delete/:id", delete(delete_product_handler)) // DELETE /api/product/:id
@@ -25,10 +40,24 @@ pub fn product_routes(pool: PgPool) -> Router<PgPool> {
 // creating new products 
 pub async fn create_product_handler(
     State(pool): State<PgPool>,
+    auth: Option<AuthMiddleware>,
     Json(payload): Json<CreateProduct>,
 ) -> Result<(StatusCode, Json<Product>), (StatusCode, String)> {
-    match create_product(&pool, payload).await {
+    if payload.stock_quantity < 0 {
+        return Err((StatusCode::BAD_REQUEST, "Stock quantity cannot be negative".to_string()));
+    }
+
+    if payload.slug.as_deref().is_some_and(|slug| !is_valid_slug(slug)) {
+        return Err((StatusCode::BAD_REQUEST, INVALID_SLUG.to_string()));
+    }
+
+    match create_product(&pool, payload, auth.and_then(|a| a.user_id())).await {
         Ok(product) => Ok((StatusCode::CREATED, Json(product))),
+        Err(ProductError::CategoryNotFound) => Err((StatusCode::NOT_FOUND, "Category not found".to_string())),
+        Err(ProductError::InvalidAttributes(e)) => Err((StatusCode::BAD_REQUEST, e)),
+        Err(ProductError::Database(e)) if e.as_database_error().and_then(|e| e.code()).as_deref() == Some("23505") => {
+            Err((StatusCode::CONFLICT, "Slug or SKU is already in use".to_string()))
+        }
         Err(err) => {
             eprintln!("❌ Failed to create product: {:?}", err);
             Err((StatusCode::INTERNAL_SERVER_ERROR, "Product creation failed".to_string()))
@@ -36,60 +65,155 @@ pub async fn create_product_handler(
     }
 }
 
+// a live product with its details and version, for reads and 412 responses
+async fn product_representation(
+    pool: &PgPool,
+    product: Product,
+    converter: &Converter,
+) -> Result<(ProductDetails, i32), sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    let version = current_version(&mut conn, HistoryEntity::Product, product.id).await?;
+    let product = with_details(pool, vec![product], converter).await?.remove(0);
+
+    Ok((product, version))
+}
+
+// the response to a saved edit: the product with its details, and the version the edit became
+pub async fn saved_product(pool: &PgPool, product: Product, version: i32) -> Result<Response, (StatusCode, String)> {
+    let converter = converter_for(pool, None).await.map_err(currency_error)?;
+    let product = with_details(pool, vec![product], &converter)
+        .await
+        .map_err(|e| {
+            eprintln!("❌ Database error: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })?
+        .remove(0);
+
+    Ok(with_etag(version, StatusCode::OK, Json(product)))
+}
+
+// a 412 for an edit based on an old version: the product as it is now, to redo the edit on
+pub async fn stale_product(pool: &PgPool, id: Uuid) -> Result<Response, (StatusCode, String)> {
+    let db_error = |e: sqlx::Error| {
+        eprintln!("❌ Database error: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    };
+
+    let product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1")
+        .bind(id)
+        .fetch_one(pool)
+        .await
+        .map_err(db_error)?;
+    let converter = converter_for(pool, None).await.map_err(currency_error)?;
+    let (product, version) = product_representation(pool, product, &converter).await.map_err(db_error)?;
+
+    Ok(with_etag(version, StatusCode::PRECONDITION_FAILED, Json(product)))
+}
+
 // to get product from data base
 pub async fn get_product(
     State(pool): State<PgPool>,
     Path(id): Path<Uuid>,
-) -> Result<Json<Product>, (StatusCode, String)> {
-    let product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1")
+    Query(currency): Query<CurrencyParams>,
+) -> Result<Response, (StatusCode, String)> {
+    let converter = converter_for(&pool, currency.currency.as_deref()).await.map_err(currency_error)?;
+    let db_error = |e: sqlx::Error| {
+        eprintln!("❌ Database error: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    };
+
+    let product = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = $1 AND deleted_at IS NULL")
         .bind(id)
         .fetch_optional(&pool)
         .await
-        .map_err(|e| {
-            eprintln!("❌ Database error: {:?}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
-        })?
+        .map_err(db_error)?
         .ok_or((StatusCode::NOT_FOUND, "Product not found".to_string()))?;
 
-    Ok(Json(product))
+    let (product, version) = product_representation(&pool, product, &converter).await.map_err(db_error)?;
+
+    Ok(with_etag(version, StatusCode::OK, Json(product)))
 }
 
-// list all products available 
-pub async fn list_products(
+// product page lookup; an old slug answers 301 with the current location
+pub async fn get_product_by_slug(
     State(pool): State<PgPool>,
-) -> Result<Json<Vec<Product>>, (StatusCode, String)> {
-    let products = sqlx::query_as::<_, Product>("SELECT * FROM products")
-        .fetch_all(&pool)
-        .await
-        .map_err(|e| {
-            eprintln!("❌ Failed to fetch products: {:?}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
-        })?;
+    Path(slug): Path<String>,
+    Query(currency): Query<CurrencyParams>,
+) -> Result<Response, (StatusCode, String)> {
+    let converter = converter_for(&pool, currency.currency.as_deref()).await.map_err(currency_error)?;
+    let db_error = |e: sqlx::Error| {
+        eprintln!("❌ Database error: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    };
+
+    if let Some(product) = product_by_slug(&pool, &slug).await.map_err(db_error)? {
+        let (product, version) = product_representation(&pool, product, &converter).await.map_err(db_error)?;
+        return Ok(with_etag(version, StatusCode::OK, Json(product)));
+    }
+
+    let mut conn = pool.acquire().await.map_err(db_error)?;
+    match resolve_old_slug(&mut conn, SlugOwner::Product, &slug).await.map_err(db_error)? {
+        Some((id, current)) => Ok(slug_redirect(format!("/api/products/slug/{}", current), id, &current)),
+        None => Err((StatusCode::NOT_FOUND, "Product not found".to_string())),
+    }
+}
 
-    Ok(Json(products))
+// list all products available, paged; accepts the same filters and sorts as search
+pub async fn list_products(
+    state: State<PgPool>,
+    uri: OriginalUri,
+    params: Query<ProductQueryParams>,
+    currency: Query<CurrencyParams>,
+) -> Result<Json<SearchResults>, (StatusCode, String)> {
+    search_products_handler(state, uri, params, currency).await
 }
 
-// update product 
+// update product; If-Match must carry the ETag the edit was based on
 pub async fn update_product_handler(
     Path(id): Path<Uuid>,
     State(pool): State<PgPool>,
+    auth: Option<AuthMiddleware>,
+    headers: HeaderMap,
     Json(update): Json<UpdateProduct>,
-) -> Result<Json<crate::models::product::Product>, (StatusCode, String)> {
-    update_product(&pool, id, update)
-        .await
-        .map(Json)
-        .map_err(|e| {
+) -> Result<Response, (StatusCode, String)> {
+    let expected_version = if_match(&headers)?;
+
+    // stock isn't part of the ETag, so an edit here could overwrite someone else's unnoticed
+    if update.stock_quantity.is_some() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Stock is changed through POST /products/:id/stock-movements".to_string(),
+        ));
+    }
+
+    if update.slug.as_deref().is_some_and(|slug| !is_valid_slug(slug)) {
+        return Err((StatusCode::BAD_REQUEST, INVALID_SLUG.to_string()));
+    }
+
+    match update_product(&pool, id, update, expected_version, auth.and_then(|a| a.user_id())).await {
+        Ok((product, version)) => saved_product(&pool, product, version).await,
+        // someone else saved first: send what they saved, so the edit can be redone on top of it
+        Err(ProductError::VersionMismatch) => stale_product(&pool, id).await,
+        Err(ProductError::NotFound) => Err((StatusCode::NOT_FOUND, "Product not found".to_string())),
+        Err(ProductError::CategoryNotFound) => Err((StatusCode::NOT_FOUND, "Category not found".to_string())),
+        Err(ProductError::InvalidAttributes(e)) => Err((StatusCode::BAD_REQUEST, e)),
+        Err(ProductError::Database(e)) if e.as_database_error().and_then(|e| e.code()).as_deref() == Some("23505") => {
+            Err((StatusCode::CONFLICT, "Slug or SKU is already in use".to_string()))
+        }
+        Err(e) => {
             eprintln!("❌ Failed to update product: {:?}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update product".to_string())
-        })
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update product".to_string()))
+        }
+    }
 }
 
 // delete product 
 pub async fn delete_product_handler(
     State(pool): State<PgPool>,
     Path(id): Path<Uuid>,
+    auth: Option<AuthMiddleware>,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    delete_product(&pool, id)
+    delete_product(&pool, id, auth.and_then(|a| a.user_id()))
         .await
         .map_err(|e| {
             eprintln!("❌ Failed to delete product: {:?}", e);
@@ -103,58 +227,75 @@ pub async fn delete_product_handler(
 pub async fn soft_delete_product_handler(
     State(pool): State<PgPool>,
     Path(id): Path<Uuid>,
+    auth: Option<AuthMiddleware>,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    soft_delete_product(&pool, id).await
-        .map(|_| StatusCode::NO_CONTENT)
-        .map_err(|e| {
+    match soft_delete_product(&pool, id, auth.and_then(|a| a.user_id())).await {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Product not found".into())),
+        Err(e) => {
             eprintln!("❌ Soft delete error: {:?}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Soft delete failed".into())
-        })
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Soft delete failed".into()))
+        }
+    }
 }
 
-// product search 
+// product search: full-text ranked by relevance, with a fuzzy fallback for misspellings.
+// `facets=true` adds facet counts next to the page of hits
 pub async fn search_products_handler(
     State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
     Query(params): Query<ProductQueryParams>,
-) -> Result<Json<Vec<Product>>, (StatusCode, String)> {
-    let mut builder = QueryBuilder::new("SELECT * FROM products WHERE deleted_at IS NULL");
+    Query(currency): Query<CurrencyParams>,
+) -> Result<Json<SearchResults>, (StatusCode, String)> {
+    let converter = converter_for(&pool, currency.currency.as_deref()).await.map_err(currency_error)?;
+    let request = params
+        .page_params()
+        .resolve(PRODUCT_SORTS, default_product_sort(&params))
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
 
-    if let Some(query) = &params.query {
-        builder.push(" AND name ILIKE ").push_bind(format!("%{}%", query));
-    }
-
-    if let Some(category_id) = params.category_id {
-        builder.push(" AND category_id = ").push_bind(category_id);
-    }
-
-    if let Some(min_price) = params.min_price {
-        builder.push(" AND price >= ").push_bind(min_price);
-    }
-
-    if let Some(max_price) = params.max_price {
-        builder.push(" AND price <= ").push_bind(max_price);
-    }
-
-    if let Some(true) = params.in_stock {
-        builder.push(" AND stock_quantity > 0");
-    }
-
-// pagination 
-    let page = params.page.unwrap_or(1);
-    let limit = params.limit.unwrap_or(10);
-    let limit_i32 = limit as i32;
-    let offset_i32 = ((page - 1) * limit) as i32;
-    
-    builder.push(" LIMIT ").push_bind(limit_i32);
-    builder.push(" OFFSET ").push_bind(offset_i32);  
-    let query = builder.build_query_as::<Product>();
-
-    let products = query
-        .fetch_all(&pool)
+    let outcome = search_products(&pool, &params, &request, &converter)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {}", e)))?;
 
-    Ok(Json(products))
+    // only the first page of a text search counts as a search; later pages are the same one
+    let search_id = match params.text() {
+        Some(text) if request.is_first_page() => {
+            let id = Uuid::new_v4();
+            spawn_record_search(
+                pool.clone(),
+                id,
+                text.to_string(),
+                search_filters(&params),
+                outcome.hits.total,
+                outcome.fuzzy,
+            );
+            Some(id)
+        }
+        _ => None,
+    };
+
+    Ok(Json(SearchResults {
+        page: Page::new(outcome.hits, &request, &uri),
+        fuzzy: outcome.fuzzy,
+        facets: outcome.facets,
+        search_id,
+    }))
 }
 
+// autocomplete for the search bar
+pub async fn suggest_handler(
+    State(pool): State<PgPool>,
+    Query(params): Query<SuggestParams>,
+) -> Result<Json<Vec<Suggestion>>, (StatusCode, String)> {
+    let Some(text) = params.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) else {
+        return Ok(Json(Vec::new()));
+    };
 
+    suggest(&pool, text, params.limit.unwrap_or(8).min(20))
+        .await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("❌ Failed to fetch suggestions: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })
+}
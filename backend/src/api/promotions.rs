@@ -0,0 +1,169 @@
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    http::StatusCode,
+    middleware,
+    routing::get,
+    Json, Router,
+};
+use bigdecimal::{BigDecimal, Zero};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::middleware::auth::{require_admin, AuthMiddleware};
+use crate::models::promotion::{Promotion, PromotionDetails, PromotionInput, PromotionKind};
+use crate::pagination::{Page, PageParams};
+use crate::services::promotion::{
+    create_promotion, delete_promotion, get_promotion, list_promotions, update_promotion, PROMOTION_SORTS,
+};
+
+// coupons and automatic promotions; customers enter coupons on their cart
+pub fn promotion_routes(pool: PgPool) -> Router<PgPool> {
+    Router::new()
+        .route("/promotions", get(list_promotions_handler).post(create_promotion_handler))
+        .route(
+            "/promotions/:id",
+            get(get_promotion_handler).put(update_promotion_handler).delete(delete_promotion_handler),
+        )
+        .route_layer(middleware::from_fn_with_state(pool.clone(), require_admin))
+        .with_state(pool)
+}
+
+// trims and upper-cases the code, and checks the fields the kind needs
+fn validate_promotion(input: &mut PromotionInput) -> Result<(), String> {
+    if input.name.trim().is_empty() {
+        return Err("Name is required".to_string());
+    }
+    input.code = input.code.as_deref().map(|c| c.trim().to_uppercase()).filter(|c| !c.is_empty());
+    if input.code.as_deref().is_some_and(|c| c.len() > 50 || c.contains(char::is_whitespace)) {
+        return Err("Codes are at most 50 characters, without spaces".to_string());
+    }
+
+    let hundred = BigDecimal::from(100);
+    let percent = |value: &Option<BigDecimal>| value.as_ref().is_some_and(|v| *v > BigDecimal::zero() && *v <= hundred);
+    match input.kind {
+        PromotionKind::Percentage if !percent(&input.value) => {
+            return Err("Percentage promotions need a value between 0 and 100".to_string())
+        }
+        PromotionKind::FixedAmount if input.value.as_ref().is_none_or(|v| *v <= BigDecimal::zero()) => {
+            return Err("Fixed amount promotions need a positive value".to_string())
+        }
+        PromotionKind::FreeShipping if input.value.is_some() => {
+            return Err("Free shipping promotions don't take a value".to_string())
+        }
+        PromotionKind::BuyXGetY => {
+            if input.buy_quantity.is_none_or(|q| q < 1) || input.get_quantity.is_none_or(|q| q < 1) {
+                return Err("Buy X get Y promotions need buy_quantity and get_quantity of at least 1".to_string());
+            }
+            if input.value.is_some() && !percent(&input.value) {
+                return Err("The discount on the free items is a percentage between 0 and 100".to_string());
+            }
+        }
+        _ => {}
+    }
+    if input.kind != PromotionKind::BuyXGetY && (input.buy_quantity.is_some() || input.get_quantity.is_some()) {
+        return Err("buy_quantity and get_quantity are only for buy X get Y promotions".to_string());
+    }
+
+    if let (Some(start), Some(end)) = (input.starts_at, input.ends_at) {
+        if end <= start {
+            return Err("ends_at must be after starts_at".to_string());
+        }
+    }
+    if input.usage_limit.is_some_and(|l| l < 1) || input.per_user_limit.is_some_and(|l| l < 1) {
+        return Err("Usage limits must be at least 1".to_string());
+    }
+    if input.min_subtotal.as_ref().is_some_and(|m| *m < BigDecimal::zero()) {
+        return Err("Minimum subtotal cannot be negative".to_string());
+    }
+    Ok(())
+}
+
+fn save_error(e: sqlx::Error) -> (StatusCode, String) {
+    match e.as_database_error().and_then(|e| e.code()).as_deref() {
+        Some("23505") => (StatusCode::CONFLICT, "That code is already in use".to_string()),
+        Some("23503") => (StatusCode::NOT_FOUND, "Product or category not found".to_string()),
+        _ => {
+            eprintln!("❌ Failed to save promotion: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to save promotion".to_string())
+        }
+    }
+}
+
+pub async fn list_promotions_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<Promotion>>, (StatusCode, String)> {
+    let request = params
+        .resolve(PROMOTION_SORTS, "created_at")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let promotions = list_promotions(&pool, &request).await.map_err(|e| {
+        eprintln!("❌ Failed to list promotions: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    })?;
+
+    Ok(Json(Page::new(promotions, &request, &uri)))
+}
+
+pub async fn get_promotion_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<PromotionDetails>, (StatusCode, String)> {
+    let db_error = |e: sqlx::Error| {
+        eprintln!("❌ Failed to load promotion: {:?}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    };
+
+    let mut conn = pool.acquire().await.map_err(db_error)?;
+    match get_promotion(&mut conn, id).await {
+        Ok(promotion) => Ok(Json(promotion)),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Promotion not found".to_string())),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+pub async fn create_promotion_handler(
+    State(pool): State<PgPool>,
+    auth: AuthMiddleware,
+    Json(mut payload): Json<PromotionInput>,
+) -> Result<(StatusCode, Json<PromotionDetails>), (StatusCode, String)> {
+    validate_promotion(&mut payload).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    create_promotion(&pool, &payload, auth.user_id())
+        .await
+        .map(|promotion| (StatusCode::CREATED, Json(promotion)))
+        .map_err(save_error)
+}
+
+pub async fn update_promotion_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    Json(mut payload): Json<PromotionInput>,
+) -> Result<Json<PromotionDetails>, (StatusCode, String)> {
+    validate_promotion(&mut payload).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    match update_promotion(&pool, id, &payload).await {
+        Ok(promotion) => Ok(Json(promotion)),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Promotion not found".to_string())),
+        Err(e) => Err(save_error(e)),
+    }
+}
+
+pub async fn delete_promotion_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    match delete_promotion(&pool, id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "Promotion not found".to_string())),
+        Err(e) if e.as_database_error().and_then(|e| e.code()).as_deref() == Some("23503") => Err((
+            StatusCode::CONFLICT,
+            "The promotion has been redeemed; deactivate it instead".to_string(),
+        )),
+        Err(e) => {
+            eprintln!("❌ Failed to delete promotion: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete promotion".to_string()))
+        }
+    }
+}
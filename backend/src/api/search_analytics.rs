@@ -0,0 +1,101 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    middleware,
+    routing::{get, post},
+    Json, Router,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::middleware::auth::require_admin;
+use crate::models::search::{
+    ClickThroughReport, RecordSearchClick, SearchReportParams, TopSearchQuery, ZeroResultQuery,
+};
+use crate::services::search_analytics::{click_through, record_click, top_queries, zero_result_queries};
+
+// clicks come from the storefront; the reports are admin-only
+pub fn search_analytics_routes(pool: PgPool) -> Router<PgPool> {
+    let reports = Router::new()
+        .route("/search/reports/top-queries", get(top_queries_handler))
+        .route("/search/reports/zero-results", get(zero_results_handler))
+        .route("/search/reports/click-through", get(click_through_handler))
+        .route_layer(middleware::from_fn_with_state(pool.clone(), require_admin));
+
+    Router::new()
+        .route("/search/events/:id/click", post(record_click_handler))
+        .merge(reports)
+        .with_state(pool)
+}
+
+pub async fn record_click_handler(
+    State(pool): State<PgPool>,
+    Path(search_id): Path<Uuid>,
+    Json(payload): Json<RecordSearchClick>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    match record_click(&pool, search_id, payload.product_id).await {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Search not found".to_string())),
+        Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("23503") => {
+            Err((StatusCode::NOT_FOUND, "Product not found".to_string()))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to record search click: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to record click".to_string()))
+        }
+    }
+}
+
+fn check_range(params: &SearchReportParams) -> Result<(), (StatusCode, String)> {
+    match (params.from, params.to) {
+        (Some(from), Some(to)) if from > to => {
+            Err((StatusCode::BAD_REQUEST, "from must not be after to".to_string()))
+        }
+        _ => Ok(()),
+    }
+}
+
+pub async fn top_queries_handler(
+    State(pool): State<PgPool>,
+    Query(params): Query<SearchReportParams>,
+) -> Result<Json<Vec<TopSearchQuery>>, (StatusCode, String)> {
+    check_range(&params)?;
+
+    top_queries(&pool, &params)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("❌ Failed to build top queries report: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })
+}
+
+pub async fn zero_results_handler(
+    State(pool): State<PgPool>,
+    Query(params): Query<SearchReportParams>,
+) -> Result<Json<Vec<ZeroResultQuery>>, (StatusCode, String)> {
+    check_range(&params)?;
+
+    zero_result_queries(&pool, &params)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("❌ Failed to build zero-result report: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })
+}
+
+pub async fn click_through_handler(
+    State(pool): State<PgPool>,
+    Query(params): Query<SearchReportParams>,
+) -> Result<Json<ClickThroughReport>, (StatusCode, String)> {
+    check_range(&params)?;
+
+    click_through(&pool, &params)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            eprintln!("❌ Failed to build click-through report: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })
+}
@@ -0,0 +1,295 @@
+use std::collections::HashSet;
+
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
+    middleware,
+    routing::{get, post, put},
+    Json, Router,
+};
+use bigdecimal::{BigDecimal, Zero};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::api::taxes::{country_code, region_name};
+use crate::etag::{if_match, with_etag};
+use crate::middleware::auth::{require_admin, AuthMiddleware};
+use crate::models::shipping::{
+    ProductShipping, ShippingMethodDetails, ShippingMethodInput, ShippingMethodKind, ShippingZoneDetails,
+    ShippingZoneInput,
+};
+use crate::pagination::{Page, PageParams};
+use crate::services::history::{product_version, Versioned};
+use crate::services::shipping::{
+    create_shipping_method, delete_shipping_method, delete_shipping_zone, get_shipping_zone, list_shipping_zones,
+    product_shipping, save_shipping_zone, set_product_shipping, update_shipping_method, SHIPPING_ZONE_SORTS,
+};
+
+// shipping zones and their delivery methods, and product weights and dimensions;
+// customers get their options from GET /cart/shipping-options
+pub fn shipping_routes(pool: PgPool) -> Router<PgPool> {
+    Router::new()
+        .route("/shipping/zones", get(list_shipping_zones_handler).post(create_shipping_zone_handler))
+        .route(
+            "/shipping/zones/:id",
+            get(get_shipping_zone_handler).put(update_shipping_zone_handler).delete(delete_shipping_zone_handler),
+        )
+        .route("/shipping/zones/:id/methods", post(create_shipping_method_handler))
+        .route("/shipping/methods/:id", put(update_shipping_method_handler).delete(delete_shipping_method_handler))
+        .route("/products/:id/shipping", get(get_product_shipping_handler).put(set_product_shipping_handler))
+        .route_layer(middleware::from_fn_with_state(pool.clone(), require_admin))
+        .with_state(pool)
+}
+
+// upper-cases countries and drops blank regions
+fn validate_shipping_zone(input: &mut ShippingZoneInput) -> Result<(), String> {
+    if input.name.trim().is_empty() {
+        return Err("Name is required".to_string());
+    }
+    if input.areas.is_empty() {
+        return Err("A zone needs at least one country".to_string());
+    }
+    let mut seen = HashSet::new();
+    for area in &mut input.areas {
+        area.country = country_code(&area.country)?;
+        area.region = region_name(area.region.as_deref());
+        if !seen.insert((area.country.clone(), area.region.as_deref().map(str::to_uppercase))) {
+            return Err(format!("{} is listed twice", area.region.as_deref().unwrap_or(&area.country)));
+        }
+    }
+    Ok(())
+}
+
+// checks the fields the kind needs
+fn validate_shipping_method(input: &mut ShippingMethodInput) -> Result<(), String> {
+    if input.name.trim().is_empty() {
+        return Err("Name is required".to_string());
+    }
+    if input.price.as_ref().is_some_and(|p| *p < BigDecimal::zero()) {
+        return Err("Price cannot be negative".to_string());
+    }
+    if input.min_days.is_some_and(|d| d < 0) || input.max_days.is_some_and(|d| d < 0) {
+        return Err("Delivery days cannot be negative".to_string());
+    }
+    if let (Some(min), Some(max)) = (input.min_days, input.max_days) {
+        if max < min {
+            return Err("max_days must be at least min_days".to_string());
+        }
+    }
+
+    match input.kind {
+        ShippingMethodKind::FlatRate if input.price.is_none() => {
+            return Err("Flat rate methods need a price".to_string())
+        }
+        ShippingMethodKind::WeightBased => {
+            if input.weight_rates.is_empty() {
+                return Err("Weight based methods need at least one weight bracket".to_string());
+            }
+            input.weight_rates.sort_by_key(|rate| rate.up_to_grams);
+            if input.weight_rates.iter().any(|rate| rate.up_to_grams < 1 || rate.price < BigDecimal::zero()) {
+                return Err("Weight brackets need up_to_grams of at least 1 and a price of 0 or more".to_string());
+            }
+            if input.weight_rates.windows(2).any(|pair| pair[0].up_to_grams == pair[1].up_to_grams) {
+                return Err("Weight brackets need different up_to_grams".to_string());
+            }
+        }
+        ShippingMethodKind::FreeOverThreshold if input.free_threshold.as_ref().is_none_or(|t| *t <= BigDecimal::zero()) => {
+            return Err("Free over threshold methods need a positive free_threshold".to_string())
+        }
+        _ => {}
+    }
+    if input.kind != ShippingMethodKind::WeightBased && !input.weight_rates.is_empty() {
+        return Err("Weight brackets are only for weight based methods".to_string());
+    }
+    if input.kind != ShippingMethodKind::FreeOverThreshold && input.free_threshold.is_some() {
+        return Err("free_threshold is only for free over threshold methods".to_string());
+    }
+    if input.kind != ShippingMethodKind::LocalPickup && input.pickup_warehouse_id.is_some() {
+        return Err("pickup_warehouse_id is only for local pickup methods".to_string());
+    }
+    Ok(())
+}
+
+fn validate_product_shipping(shipping: &ProductShipping) -> Result<(), String> {
+    if shipping.weight_grams.is_some_and(|w| w < 0) {
+        return Err("Weight cannot be negative".to_string());
+    }
+    let dimensions = [shipping.length_mm, shipping.width_mm, shipping.height_mm];
+    if dimensions.iter().any(|d| d.is_some_and(|d| d < 1)) {
+        return Err("Dimensions must be at least 1 mm".to_string());
+    }
+    if dimensions.iter().any(Option::is_some) && !dimensions.iter().all(Option::is_some) {
+        return Err("Give all of length, width and height, or none".to_string());
+    }
+    Ok(())
+}
+
+fn db_error(action: &'static str) -> impl Fn(sqlx::Error) -> (StatusCode, String) {
+    move |e| {
+        eprintln!("❌ Failed to {}: {:?}", action, e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    }
+}
+
+fn error_code(e: &sqlx::Error) -> Option<String> {
+    e.as_database_error().and_then(|e| e.code()).map(|code| code.into_owned())
+}
+
+pub async fn list_shipping_zones_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<ShippingZoneDetails>>, (StatusCode, String)> {
+    let request = params
+        .resolve(SHIPPING_ZONE_SORTS, "name")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    list_shipping_zones(&pool, &request)
+        .await
+        .map(|zones| Json(Page::new(zones, &request, &uri)))
+        .map_err(db_error("list shipping zones"))
+}
+
+pub async fn get_shipping_zone_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ShippingZoneDetails>, (StatusCode, String)> {
+    let mut conn = pool.acquire().await.map_err(db_error("load shipping zone"))?;
+    match get_shipping_zone(&mut conn, id).await {
+        Ok(zone) => Ok(Json(zone)),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Shipping zone not found".to_string())),
+        Err(e) => Err(db_error("load shipping zone")(e)),
+    }
+}
+
+async fn save_zone(
+    pool: &PgPool,
+    id: Option<Uuid>,
+    mut input: ShippingZoneInput,
+) -> Result<ShippingZoneDetails, (StatusCode, String)> {
+    validate_shipping_zone(&mut input).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    match save_shipping_zone(pool, id, &input).await {
+        Ok(zone) => Ok(zone),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Shipping zone not found".to_string())),
+        Err(e) if error_code(&e).as_deref() == Some("23505") => Err((
+            StatusCode::CONFLICT,
+            "One of those countries or regions already belongs to another zone".to_string(),
+        )),
+        Err(e) => Err(db_error("save shipping zone")(e)),
+    }
+}
+
+pub async fn create_shipping_zone_handler(
+    State(pool): State<PgPool>,
+    Json(payload): Json<ShippingZoneInput>,
+) -> Result<(StatusCode, Json<ShippingZoneDetails>), (StatusCode, String)> {
+    save_zone(&pool, None, payload).await.map(|zone| (StatusCode::CREATED, Json(zone)))
+}
+
+pub async fn update_shipping_zone_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<ShippingZoneInput>,
+) -> Result<Json<ShippingZoneDetails>, (StatusCode, String)> {
+    save_zone(&pool, Some(id), payload).await.map(Json)
+}
+
+pub async fn delete_shipping_zone_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    match delete_shipping_zone(&pool, id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "Shipping zone not found".to_string())),
+        Err(e) => Err(db_error("delete shipping zone")(e)),
+    }
+}
+
+fn method_error(e: sqlx::Error) -> (StatusCode, String) {
+    match e {
+        sqlx::Error::RowNotFound => (StatusCode::NOT_FOUND, "Shipping method not found".to_string()),
+        e if error_code(&e).as_deref() == Some("23503") => {
+            (StatusCode::NOT_FOUND, "Shipping zone or pickup warehouse not found".to_string())
+        }
+        e => db_error("save shipping method")(e),
+    }
+}
+
+pub async fn create_shipping_method_handler(
+    State(pool): State<PgPool>,
+    Path(zone_id): Path<Uuid>,
+    Json(mut payload): Json<ShippingMethodInput>,
+) -> Result<(StatusCode, Json<ShippingMethodDetails>), (StatusCode, String)> {
+    validate_shipping_method(&mut payload).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    create_shipping_method(&pool, zone_id, &payload)
+        .await
+        .map(|method| (StatusCode::CREATED, Json(method)))
+        .map_err(method_error)
+}
+
+pub async fn update_shipping_method_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    Json(mut payload): Json<ShippingMethodInput>,
+) -> Result<Json<ShippingMethodDetails>, (StatusCode, String)> {
+    validate_shipping_method(&mut payload).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    update_shipping_method(&pool, id, &payload).await.map(Json).map_err(method_error)
+}
+
+pub async fn delete_shipping_method_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    match delete_shipping_method(&pool, id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "Shipping method not found".to_string())),
+        Err(e) => Err(db_error("delete shipping method")(e)),
+    }
+}
+
+// with the product's ETag, for If-Match on the update
+async fn product_shipping_response(
+    pool: &PgPool,
+    id: Uuid,
+    status: StatusCode,
+) -> Result<Response, (StatusCode, String)> {
+    let shipping = match product_shipping(pool, id).await {
+        Ok(shipping) => shipping,
+        Err(sqlx::Error::RowNotFound) => return Err((StatusCode::NOT_FOUND, "Product not found".to_string())),
+        Err(e) => return Err(db_error("load product shipping")(e)),
+    };
+    let version = product_version(pool, id).await.map_err(db_error("load product shipping"))?;
+
+    Ok(with_etag(version, status, Json(shipping)))
+}
+
+pub async fn get_product_shipping_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Response, (StatusCode, String)> {
+    product_shipping_response(&pool, id, StatusCode::OK).await
+}
+
+// If-Match must carry the product's ETag the edit was based on
+pub async fn set_product_shipping_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    auth: AuthMiddleware,
+    headers: HeaderMap,
+    Json(payload): Json<ProductShipping>,
+) -> Result<Response, (StatusCode, String)> {
+    let expected_version = if_match(&headers)?;
+    validate_product_shipping(&payload).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    match set_product_shipping(&pool, id, &payload, expected_version, auth.user_id()).await {
+        Ok(Versioned::Saved(shipping, version)) => Ok(with_etag(version, StatusCode::OK, Json(shipping))),
+        // someone else saved first: send what they saved
+        Ok(Versioned::Stale) => product_shipping_response(&pool, id, StatusCode::PRECONDITION_FAILED).await,
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Product not found".to_string())),
+        Err(e) => Err(db_error("set product shipping")(e)),
+    }
+}
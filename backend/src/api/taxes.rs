@@ -0,0 +1,304 @@
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
+    middleware,
+    routing::{get, put},
+    Json, Router,
+};
+use bigdecimal::{BigDecimal, Zero};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::config::store_country;
+use crate::etag::{if_match, with_etag};
+use crate::middleware::auth::{require_admin, AuthMiddleware};
+use crate::models::tax::{
+    ProductTaxClass, SetTaxRate, TaxAddress, TaxAddressParams, TaxClass, TaxClassInput, TaxZoneDetails, TaxZoneInput,
+};
+use crate::pagination::{Page, PageParams};
+use crate::services::history::{product_version, Versioned};
+use crate::services::tax::{
+    delete_tax_class, delete_tax_rate, delete_tax_zone, get_tax_class, get_tax_zone, list_tax_classes, list_tax_zones,
+    product_tax_class, save_tax_class, save_tax_zone, set_product_tax_class, set_tax_rate, TAX_CLASS_SORTS,
+    TAX_ZONE_SORTS,
+};
+
+// tax classes, zones and their rates, and which class each product is in
+pub fn tax_routes(pool: PgPool) -> Router<PgPool> {
+    Router::new()
+        .route("/tax/classes", get(list_tax_classes_handler).post(create_tax_class_handler))
+        .route("/tax/classes/:id", put(update_tax_class_handler).delete(delete_tax_class_handler))
+        .route("/tax/zones", get(list_tax_zones_handler).post(create_tax_zone_handler))
+        .route(
+            "/tax/zones/:id",
+            get(get_tax_zone_handler).put(update_tax_zone_handler).delete(delete_tax_zone_handler),
+        )
+        .route("/tax/zones/:id/rates/:class_id", put(set_tax_rate_handler).delete(delete_tax_rate_handler))
+        .route("/products/:id/tax-class", get(get_product_tax_class_handler).put(set_product_tax_class_handler))
+        .route_layer(middleware::from_fn_with_state(pool.clone(), require_admin))
+        .with_state(pool)
+}
+
+// ISO 3166-1 alpha-2, upper-cased; shared with the shipping endpoints
+pub fn country_code(code: &str) -> Result<String, String> {
+    let code = code.trim().to_uppercase();
+    if code.len() != 2 || !code.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err("Country codes are two letters (ISO 3166)".to_string());
+    }
+    Ok(code)
+}
+
+// trimmed, and None when blank
+pub fn region_name(region: Option<&str>) -> Option<String> {
+    region.map(str::trim).filter(|r| !r.is_empty()).map(str::to_string)
+}
+
+// for handlers taking `?country=&region=`; the store's own country when none is given
+pub fn tax_address(params: &TaxAddressParams) -> Result<Option<TaxAddress>, (StatusCode, String)> {
+    let region = region_name(params.region.as_deref());
+    match params.country.as_deref() {
+        Some(country) => {
+            let country = country_code(country).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+            Ok(Some(TaxAddress { country, region }))
+        }
+        None if region.is_some() => Err((StatusCode::BAD_REQUEST, "A region needs a country".to_string())),
+        None => Ok(store_country().map(|country| TaxAddress { country, region: None })),
+    }
+}
+
+fn validate_tax_zone(input: &mut TaxZoneInput) -> Result<(), String> {
+    if input.name.trim().is_empty() {
+        return Err("Name is required".to_string());
+    }
+    input.country = country_code(&input.country)?;
+    input.region = region_name(input.region.as_deref());
+    Ok(())
+}
+
+fn db_error(action: &'static str) -> impl Fn(sqlx::Error) -> (StatusCode, String) {
+    move |e| {
+        eprintln!("❌ Failed to {}: {:?}", action, e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    }
+}
+
+fn is_unique_violation(e: &sqlx::Error) -> bool {
+    e.as_database_error().and_then(|e| e.code()).as_deref() == Some("23505")
+}
+
+fn is_foreign_key_violation(e: &sqlx::Error) -> bool {
+    e.as_database_error().and_then(|e| e.code()).as_deref() == Some("23503")
+}
+
+pub async fn list_tax_classes_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<TaxClass>>, (StatusCode, String)> {
+    let request = params
+        .resolve(TAX_CLASS_SORTS, "name")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    list_tax_classes(&pool, &request)
+        .await
+        .map(|classes| Json(Page::new(classes, &request, &uri)))
+        .map_err(db_error("list tax classes"))
+}
+
+async fn save_class(pool: &PgPool, id: Option<Uuid>, input: &TaxClassInput) -> Result<TaxClass, (StatusCode, String)> {
+    if input.name.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Name is required".to_string()));
+    }
+
+    match save_tax_class(pool, id, input).await {
+        Ok(class) => Ok(class),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Tax class not found".to_string())),
+        Err(e) if is_unique_violation(&e) => {
+            Err((StatusCode::CONFLICT, "A tax class with that name already exists".to_string()))
+        }
+        Err(e) => Err(db_error("save tax class")(e)),
+    }
+}
+
+pub async fn create_tax_class_handler(
+    State(pool): State<PgPool>,
+    Json(payload): Json<TaxClassInput>,
+) -> Result<(StatusCode, Json<TaxClass>), (StatusCode, String)> {
+    save_class(&pool, None, &payload).await.map(|class| (StatusCode::CREATED, Json(class)))
+}
+
+pub async fn update_tax_class_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<TaxClassInput>,
+) -> Result<Json<TaxClass>, (StatusCode, String)> {
+    save_class(&pool, Some(id), &payload).await.map(Json)
+}
+
+// its products are taxed as the default class from then on
+pub async fn delete_tax_class_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let class = get_tax_class(&pool, id)
+        .await
+        .map_err(db_error("load tax class"))?
+        .ok_or((StatusCode::NOT_FOUND, "Tax class not found".to_string()))?;
+    if class.is_default {
+        return Err((
+            StatusCode::CONFLICT,
+            "The default tax class can't be deleted; make another class the default first".to_string(),
+        ));
+    }
+
+    match delete_tax_class(&pool, id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "Tax class not found".to_string())),
+        Err(e) => Err(db_error("delete tax class")(e)),
+    }
+}
+
+pub async fn list_tax_zones_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<TaxZoneDetails>>, (StatusCode, String)> {
+    let request = params
+        .resolve(TAX_ZONE_SORTS, "country")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    list_tax_zones(&pool, &request)
+        .await
+        .map(|zones| Json(Page::new(zones, &request, &uri)))
+        .map_err(db_error("list tax zones"))
+}
+
+pub async fn get_tax_zone_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<TaxZoneDetails>, (StatusCode, String)> {
+    match get_tax_zone(&pool, id).await {
+        Ok(zone) => Ok(Json(zone)),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Tax zone not found".to_string())),
+        Err(e) => Err(db_error("load tax zone")(e)),
+    }
+}
+
+async fn save_zone(
+    pool: &PgPool,
+    id: Option<Uuid>,
+    mut input: TaxZoneInput,
+) -> Result<TaxZoneDetails, (StatusCode, String)> {
+    validate_tax_zone(&mut input).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    match save_tax_zone(pool, id, &input).await {
+        Ok(zone) => Ok(zone),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Tax zone not found".to_string())),
+        Err(e) if is_unique_violation(&e) => {
+            Err((StatusCode::CONFLICT, "There's already a zone for that country and region".to_string()))
+        }
+        Err(e) => Err(db_error("save tax zone")(e)),
+    }
+}
+
+pub async fn create_tax_zone_handler(
+    State(pool): State<PgPool>,
+    Json(payload): Json<TaxZoneInput>,
+) -> Result<(StatusCode, Json<TaxZoneDetails>), (StatusCode, String)> {
+    save_zone(&pool, None, payload).await.map(|zone| (StatusCode::CREATED, Json(zone)))
+}
+
+pub async fn update_tax_zone_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<TaxZoneInput>,
+) -> Result<Json<TaxZoneDetails>, (StatusCode, String)> {
+    save_zone(&pool, Some(id), payload).await.map(Json)
+}
+
+pub async fn delete_tax_zone_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    match delete_tax_zone(&pool, id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "Tax zone not found".to_string())),
+        Err(e) => Err(db_error("delete tax zone")(e)),
+    }
+}
+
+pub async fn set_tax_rate_handler(
+    State(pool): State<PgPool>,
+    Path((zone_id, class_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<SetTaxRate>,
+) -> Result<Json<TaxZoneDetails>, (StatusCode, String)> {
+    if payload.name.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Name is required".to_string()));
+    }
+    if payload.rate < BigDecimal::zero() || payload.rate > BigDecimal::from(100) {
+        return Err((StatusCode::BAD_REQUEST, "Rates are a percentage between 0 and 100".to_string()));
+    }
+
+    match set_tax_rate(&pool, zone_id, class_id, &payload.name, &payload.rate).await {
+        Ok(zone) => Ok(Json(zone)),
+        Err(e) if is_foreign_key_violation(&e) => {
+            Err((StatusCode::NOT_FOUND, "Tax zone or class not found".to_string()))
+        }
+        Err(e) => Err(db_error("set tax rate")(e)),
+    }
+}
+
+pub async fn delete_tax_rate_handler(
+    State(pool): State<PgPool>,
+    Path((zone_id, class_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    match delete_tax_rate(&pool, zone_id, class_id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((StatusCode::NOT_FOUND, "Tax rate not found".to_string())),
+        Err(e) => Err(db_error("delete tax rate")(e)),
+    }
+}
+
+// with the product's ETag, for If-Match on the update
+async fn product_tax_class_response(
+    pool: &PgPool,
+    id: Uuid,
+    status: StatusCode,
+) -> Result<Response, (StatusCode, String)> {
+    let tax_class_id = match product_tax_class(pool, id).await {
+        Ok(tax_class_id) => tax_class_id,
+        Err(sqlx::Error::RowNotFound) => return Err((StatusCode::NOT_FOUND, "Product not found".to_string())),
+        Err(e) => return Err(db_error("load product tax class")(e)),
+    };
+    let version = product_version(pool, id).await.map_err(db_error("load product tax class"))?;
+
+    Ok(with_etag(version, status, Json(ProductTaxClass { tax_class_id })))
+}
+
+pub async fn get_product_tax_class_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Response, (StatusCode, String)> {
+    product_tax_class_response(&pool, id, StatusCode::OK).await
+}
+
+// If-Match must carry the product's ETag the edit was based on
+pub async fn set_product_tax_class_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    auth: AuthMiddleware,
+    headers: HeaderMap,
+    Json(payload): Json<ProductTaxClass>,
+) -> Result<Response, (StatusCode, String)> {
+    let expected_version = if_match(&headers)?;
+
+    match set_product_tax_class(&pool, id, payload.tax_class_id, expected_version, auth.user_id()).await {
+        Ok(Versioned::Saved((), version)) => Ok(with_etag(version, StatusCode::OK, Json(payload))),
+        // someone else saved first: send what they saved
+        Ok(Versioned::Stale) => product_tax_class_response(&pool, id, StatusCode::PRECONDITION_FAILED).await,
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Product not found".to_string())),
+        Err(e) if is_foreign_key_violation(&e) => Err((StatusCode::NOT_FOUND, "Tax class not found".to_string())),
+        Err(e) => Err(db_error("set product tax class")(e)),
+    }
+}
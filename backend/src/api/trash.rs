@@ -0,0 +1,104 @@
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    http::StatusCode,
+    middleware,
+    routing::{get, post},
+    Json, Router,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::config::trash_retention;
+use crate::middleware::auth::{require_admin, AuthMiddleware};
+use crate::models::trash::{CategoryRestore, RestoreOutcome, TrashedCategory, TrashedProduct};
+use crate::pagination::{Page, PageParams};
+use crate::services::trash::{
+    list_trashed_categories, list_trashed_products, restore_category, restore_product, TRASH_SORTS,
+};
+
+// soft-deleted products and categories, restorable until the purge job removes them
+pub fn trash_routes(pool: PgPool) -> Router<PgPool> {
+    Router::new()
+        .route("/trash/products", get(trashed_products_handler))
+        .route("/trash/products/:id/restore", post(restore_product_handler))
+        .route("/trash/categories", get(trashed_categories_handler))
+        .route("/trash/categories/:id/restore", post(restore_category_handler))
+        .route_layer(middleware::from_fn_with_state(pool.clone(), require_admin))
+        .with_state(pool)
+}
+
+pub async fn trashed_products_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<TrashedProduct>>, (StatusCode, String)> {
+    let request = params
+        .resolve(TRASH_SORTS, "deleted_at")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let products = list_trashed_products(&pool, &request, trash_retention())
+        .await
+        .map_err(|e| {
+            eprintln!("❌ Failed to list trashed products: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })?;
+
+    Ok(Json(Page::new(products, &request, &uri)))
+}
+
+pub async fn trashed_categories_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<TrashedCategory>>, (StatusCode, String)> {
+    let request = params
+        .resolve(TRASH_SORTS, "deleted_at")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    let categories = list_trashed_categories(&pool, &request, trash_retention())
+        .await
+        .map_err(|e| {
+            eprintln!("❌ Failed to list trashed categories: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })?;
+
+    Ok(Json(Page::new(categories, &request, &uri)))
+}
+
+pub async fn restore_product_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    auth: AuthMiddleware,
+) -> Result<StatusCode, (StatusCode, String)> {
+    match restore_product(&pool, id, auth.user_id()).await {
+        Ok(RestoreOutcome::Restored(())) => Ok(StatusCode::NO_CONTENT),
+        Ok(RestoreOutcome::ParentInTrash) => Err((
+            StatusCode::CONFLICT,
+            "The product's category is in the trash; restore it first".to_string(),
+        )),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Product not found in trash".to_string())),
+        Err(e) => {
+            eprintln!("❌ Failed to restore product: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to restore product".to_string()))
+        }
+    }
+}
+
+pub async fn restore_category_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    auth: AuthMiddleware,
+) -> Result<Json<CategoryRestore>, (StatusCode, String)> {
+    match restore_category(&pool, id, auth.user_id()).await {
+        Ok(RestoreOutcome::Restored(restored)) => Ok(Json(restored)),
+        Ok(RestoreOutcome::ParentInTrash) => Err((
+            StatusCode::CONFLICT,
+            "The parent category is in the trash; restore it first".to_string(),
+        )),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Category not found in trash".to_string())),
+        Err(e) => {
+            eprintln!("❌ Failed to restore category: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to restore category".to_string()))
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    http::StatusCode,
+    routing::{delete, get, put},
+    Json, Router,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::variant::{
+    CreateProductOption, CreateVariant, ProductOptionWithValues, ProductVariant, UpdateVariant,
+};
+use crate::pagination::{Page, PageParams};
+use crate::services::inventory::InventoryError;
+use crate::services::variant::{
+    create_option, create_variant, list_options, list_variants, option_values_valid_for_product,
+    soft_delete_variant, update_variant, OPTION_SORTS, VARIANT_SORTS,
+};
+
+pub fn variant_routes() -> Router<PgPool> {
+    Router::new()
+        .route("/products/:id/options", get(list_options_handler).post(create_option_handler))
+        .route("/products/:id/variants", get(list_variants_handler).post(create_variant_handler))
+        .route("/variants/update/:id", put(update_variant_handler))
+        .route("/variants/delete/:id", delete(delete_variant_handler))
+}
+
+// map constraint violations to client errors, anything else is a 500
+fn variant_error(err: sqlx::Error, context: &str) -> (StatusCode, String) {
+    match &err {
+        sqlx::Error::RowNotFound => return (StatusCode::NOT_FOUND, "Variant not found".to_string()),
+        sqlx::Error::Database(db_err) => match db_err.code().as_deref() {
+            Some("23505") => return (StatusCode::CONFLICT, "SKU or option value already exists".to_string()),
+            Some("23503") => return (StatusCode::NOT_FOUND, "Product not found".to_string()),
+            _ => {}
+        },
+        _ => {}
+    }
+    eprintln!("❌ {}: {:?}", context, err);
+    (StatusCode::INTERNAL_SERVER_ERROR, context.to_string())
+}
+
+// writes that also move stock
+fn variant_stock_error(err: InventoryError, context: &str) -> (StatusCode, String) {
+    match err {
+        InventoryError::NotFound => (StatusCode::NOT_FOUND, "Variant not found".to_string()),
+        InventoryError::InsufficientStock => {
+            (StatusCode::CONFLICT, "Not enough stock to reach this level".to_string())
+        }
+        InventoryError::Database(err) => variant_error(err, context),
+    }
+}
+
+pub async fn create_option_handler(
+    State(pool): State<PgPool>,
+    Path(product_id): Path<Uuid>,
+    Json(payload): Json<CreateProductOption>,
+) -> Result<(StatusCode, Json<ProductOptionWithValues>), (StatusCode, String)> {
+    if payload.values.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "An option needs at least one value".to_string()));
+    }
+
+    create_option(&pool, product_id, payload)
+        .await
+        .map(|option| (StatusCode::CREATED, Json(option)))
+        .map_err(|e| variant_error(e, "Failed to create option"))
+}
+
+pub async fn list_options_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Path(product_id): Path<Uuid>,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<ProductOptionWithValues>>, (StatusCode, String)> {
+    let request = params
+        .resolve(OPTION_SORTS, "position")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    list_options(&pool, product_id, &request)
+        .await
+        .map(|options| Json(Page::new(options, &request, &uri)))
+        .map_err(|e| variant_error(e, "Failed to fetch options"))
+}
+
+pub async fn create_variant_handler(
+    State(pool): State<PgPool>,
+    Path(product_id): Path<Uuid>,
+    Json(payload): Json<CreateVariant>,
+) -> Result<(StatusCode, Json<ProductVariant>), (StatusCode, String)> {
+    if payload.sku.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "SKU is required".to_string()));
+    }
+
+    if payload.stock_quantity < 0 {
+        return Err((StatusCode::BAD_REQUEST, "Stock quantity cannot be negative".to_string()));
+    }
+
+    let valid = option_values_valid_for_product(&pool, product_id, &payload.option_value_ids)
+        .await
+        .map_err(|e| variant_error(e, "Failed to create variant"))?;
+
+    if !valid {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Option values must belong to this product, one per option".to_string(),
+        ));
+    }
+
+    create_variant(&pool, product_id, payload)
+        .await
+        .map(|variant| (StatusCode::CREATED, Json(variant)))
+        .map_err(|e| variant_stock_error(e, "Failed to create variant"))
+}
+
+pub async fn list_variants_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Path(product_id): Path<Uuid>,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<ProductVariant>>, (StatusCode, String)> {
+    let request = params
+        .resolve(VARIANT_SORTS, "created_at")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    list_variants(&pool, product_id, &request)
+        .await
+        .map(|variants| Json(Page::new(variants, &request, &uri)))
+        .map_err(|e| variant_error(e, "Failed to fetch variants"))
+}
+
+pub async fn update_variant_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    Json(update): Json<UpdateVariant>,
+) -> Result<Json<ProductVariant>, (StatusCode, String)> {
+    if update.stock_quantity.is_some_and(|q| q < 0) {
+        return Err((StatusCode::BAD_REQUEST, "Stock quantity cannot be negative".to_string()));
+    }
+
+    update_variant(&pool, id, update)
+        .await
+        .map(Json)
+        .map_err(|e| variant_stock_error(e, "Failed to update variant"))
+}
+
+pub async fn delete_variant_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    soft_delete_variant(&pool, id)
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(|e| variant_error(e, "Failed to delete variant"))
+}
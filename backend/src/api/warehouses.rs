@@ -0,0 +1,140 @@
+use axum::{
+    extract::{OriginalUri, Path, Query, State},
+    http::StatusCode,
+    middleware,
+    routing::{get, post, put},
+    Json, Router,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::middleware::auth::{require_admin, AuthMiddleware};
+use crate::models::warehouse::{
+    CreateStockTransfer, CreateWarehouse, StockTransfer, UpdateWarehouse, Warehouse, WarehouseStockLevel,
+};
+use crate::pagination::{Page, PageParams};
+use crate::services::inventory::InventoryError;
+use crate::services::warehouse::{
+    create_warehouse, list_warehouses, transfer_stock, update_warehouse, warehouse_stock, WAREHOUSE_SORTS,
+    WAREHOUSE_STOCK_SORTS,
+};
+
+pub fn warehouse_routes(pool: PgPool) -> Router<PgPool> {
+    Router::new()
+        .route("/warehouses", get(list_warehouses_handler).post(create_warehouse_handler))
+        .route("/warehouses/update/:id", put(update_warehouse_handler))
+        .route("/warehouses/:id/stock", get(warehouse_stock_handler))
+        .route("/warehouses/transfers", post(transfer_stock_handler))
+        .route_layer(middleware::from_fn_with_state(pool.clone(), require_admin))
+        .with_state(pool)
+}
+
+pub async fn create_warehouse_handler(
+    State(pool): State<PgPool>,
+    Json(payload): Json<CreateWarehouse>,
+) -> Result<(StatusCode, Json<Warehouse>), (StatusCode, String)> {
+    if payload.name.trim().is_empty() || payload.code.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Name and code are required".to_string()));
+    }
+
+    match create_warehouse(&pool, payload).await {
+        Ok(warehouse) => Ok((StatusCode::CREATED, Json(warehouse))),
+        Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("23505") => {
+            Err((StatusCode::CONFLICT, "Warehouse code already exists".to_string()))
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to create warehouse: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to create warehouse".to_string()))
+        }
+    }
+}
+
+pub async fn list_warehouses_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<Warehouse>>, (StatusCode, String)> {
+    let request = params
+        .resolve(WAREHOUSE_SORTS, "priority")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    list_warehouses(&pool, &request)
+        .await
+        .map(|warehouses| Json(Page::new(warehouses, &request, &uri)))
+        .map_err(|e| {
+            eprintln!("❌ Failed to fetch warehouses: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })
+}
+
+pub async fn update_warehouse_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    Json(update): Json<UpdateWarehouse>,
+) -> Result<Json<Warehouse>, (StatusCode, String)> {
+    if update.is_default == Some(false) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Mark another warehouse as default instead of unsetting this one".to_string(),
+        ));
+    }
+
+    match update_warehouse(&pool, id, update).await {
+        Ok(warehouse) => Ok(Json(warehouse)),
+        Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Warehouse not found".to_string())),
+        Err(e) => {
+            eprintln!("❌ Failed to update warehouse: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update warehouse".to_string()))
+        }
+    }
+}
+
+pub async fn warehouse_stock_handler(
+    State(pool): State<PgPool>,
+    OriginalUri(uri): OriginalUri,
+    Path(id): Path<Uuid>,
+    Query(params): Query<PageParams>,
+) -> Result<Json<Page<WarehouseStockLevel>>, (StatusCode, String)> {
+    let request = params
+        .resolve(WAREHOUSE_STOCK_SORTS, "name")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    warehouse_stock(&pool, id, &request)
+        .await
+        .map(|stock| Json(Page::new(stock, &request, &uri)))
+        .map_err(|e| {
+            eprintln!("❌ Failed to fetch warehouse stock: {:?}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+        })
+}
+
+pub async fn transfer_stock_handler(
+    State(pool): State<PgPool>,
+    AuthMiddleware(claims): AuthMiddleware,
+    Json(payload): Json<CreateStockTransfer>,
+) -> Result<(StatusCode, Json<StockTransfer>), (StatusCode, String)> {
+    if payload.quantity <= 0 {
+        return Err((StatusCode::BAD_REQUEST, "Quantity must be positive".to_string()));
+    }
+
+    if payload.from_warehouse_id == payload.to_warehouse_id {
+        return Err((StatusCode::BAD_REQUEST, "Source and destination must differ".to_string()));
+    }
+
+    let actor_id = Uuid::parse_str(&claims.sub).ok();
+
+    match transfer_stock(&pool, payload, actor_id).await {
+        Ok(transfer) => Ok((StatusCode::CREATED, Json(transfer))),
+        Err(InventoryError::InsufficientStock) => {
+            Err((StatusCode::CONFLICT, "Not enough stock at the source location".to_string()))
+        }
+        Err(InventoryError::NotFound) => Err((StatusCode::NOT_FOUND, "Warehouse or product not found".to_string())),
+        Err(InventoryError::Database(sqlx::Error::Database(e))) if e.code().as_deref() == Some("23503") => {
+            Err((StatusCode::NOT_FOUND, "Warehouse or product not found".to_string()))
+        }
+        Err(InventoryError::Database(e)) => {
+            eprintln!("❌ Failed to transfer stock: {:?}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to transfer stock".to_string()))
+        }
+    }
+}
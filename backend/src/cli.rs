@@ -0,0 +1,81 @@
+// one-off commands run with the server binary, e.g.
+//   easy-buy-backend import products.csv --dry-run --create-categories --errors errors.csv
+use sqlx::PgPool;
+
+use crate::models::import::{ImportFormat, ImportOptions};
+use crate::services::import::{create_job, errors_csv, get_job, job_errors, run_import};
+
+const USAGE: &str = "usage: easy-buy-backend import <file> [--format csv|ndjson] [--dry-run] [--create-categories] [--errors <report.csv>]";
+
+pub async fn run(pool: &PgPool, args: &[String]) -> Result<(), String> {
+    match args.first().map(String::as_str) {
+        Some("import") => import(pool, &args[1..]).await,
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+async fn import(pool: &PgPool, args: &[String]) -> Result<(), String> {
+    let mut path = None;
+    let mut format = None;
+    let mut dry_run = false;
+    let mut create_categories = false;
+    let mut report = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dry-run" => dry_run = true,
+            "--create-categories" => create_categories = true,
+            "--format" => {
+                format = match args.next().map(String::as_str) {
+                    Some("csv") => Some(ImportFormat::Csv),
+                    Some("ndjson") => Some(ImportFormat::Ndjson),
+                    _ => return Err(USAGE.to_string()),
+                }
+            }
+            "--errors" => report = Some(args.next().ok_or(USAGE)?.clone()),
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg.clone()),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+
+    let path = path.ok_or(USAGE)?;
+    let format = format
+        .or_else(|| match path.rsplit('.').next() {
+            Some("csv") => Some(ImportFormat::Csv),
+            Some("ndjson") | Some("jsonl") => Some(ImportFormat::Ndjson),
+            _ => None,
+        })
+        .ok_or("can't tell the format from the file name; pass --format csv|ndjson")?;
+
+    let data = std::fs::read(&path).map_err(|e| format!("can't read {}: {}", path, e))?;
+    let options = ImportOptions { format, dry_run, create_categories };
+
+    let job = create_job(pool, options, None).await.map_err(|e| e.to_string())?;
+    run_import(pool, job.id, options, &data).await.map_err(|e| e.to_string())?;
+
+    let job = get_job(pool, job.id).await.map_err(|e| e.to_string())?.ok_or("import job disappeared")?;
+    if let Some(error) = job.error {
+        return Err(error);
+    }
+
+    println!(
+        "{}{} rows: {} created, {} updated, {} rejected (job {})",
+        if dry_run { "dry run, " } else { "" },
+        job.total_rows,
+        job.created_count,
+        job.updated_count,
+        job.error_count,
+        job.id
+    );
+
+    if job.error_count > 0 {
+        let errors = job_errors(pool, job.id).await.map_err(|e| e.to_string())?;
+        match report {
+            Some(report) => std::fs::write(&report, errors_csv(&errors)).map_err(|e| format!("can't write {}: {}", report, e))?,
+            None => errors.iter().for_each(|e| println!("  row {}: {}", e.row_number, e.message)),
+        }
+    }
+
+    Ok(())
+}
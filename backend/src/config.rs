@@ -1,5 +1,119 @@
 use std::env;
+use std::time::Duration;
 
 pub fn get_database_url() -> String {
     env::var("DATABASE_URL").expect("DATABASE_URL must be set in .env")
 }
+
+// a period in seconds; zero and unparsable values fall back to the default,
+// since tokio::time::interval panics on a zero period
+fn interval_secs(var: &str, default: u64) -> Duration {
+    let secs = env::var(var)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&secs| secs > 0)
+        .unwrap_or(default);
+    Duration::from_secs(secs)
+}
+
+// how often the stock ledger is reconciled against stock_quantity (default: hourly)
+pub fn stock_reconcile_interval() -> Duration {
+    interval_secs("STOCK_RECONCILE_INTERVAL_SECS", 3600)
+}
+
+// how long checkout holds stock before the sweeper releases it (default: 15 minutes);
+// a zero hold would expire before payment could complete
+pub fn reservation_ttl() -> Duration {
+    interval_secs("RESERVATION_TTL_SECS", 900)
+}
+
+// how often products are checked against their reorder thresholds (default: every 15 minutes)
+pub fn low_stock_check_interval() -> Duration {
+    interval_secs("LOW_STOCK_CHECK_INTERVAL_SECS", 900)
+}
+
+// how often expired reservations are swept (default: every minute)
+pub fn reservation_sweep_interval() -> Duration {
+    interval_secs("RESERVATION_SWEEP_INTERVAL_SECS", 60)
+}
+
+// minimum trigram word similarity for the fuzzy search fallback (default: 0.3)
+pub fn search_fuzzy_threshold() -> f32 {
+    env::var("SEARCH_FUZZY_THRESHOLD")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0.3)
+}
+
+// how long soft-deleted products and categories stay restorable (default: 30 days); zero,
+// unparsable and overflowing values fall back to the default, as zero would purge on delete
+pub fn trash_retention() -> Duration {
+    let secs = env::var("TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|&days| days > 0)
+        .and_then(|days| days.checked_mul(24 * 60 * 60))
+        .unwrap_or(30 * 24 * 60 * 60);
+    Duration::from_secs(secs)
+}
+
+// how often the trash is purged of rows past their retention (default: hourly)
+pub fn trash_purge_interval() -> Duration {
+    interval_secs("TRASH_PURGE_INTERVAL_SECS", 3600)
+}
+
+// how often scheduled price changes that have come due are applied (default: every minute)
+pub fn price_scheduler_interval() -> Duration {
+    interval_secs("PRICE_SCHEDULER_INTERVAL_SECS", 60)
+}
+
+// how often exchange rates are pulled from the rate provider, when one is set (default: hourly)
+pub fn exchange_rate_refresh_interval() -> Duration {
+    interval_secs("EXCHANGE_RATE_REFRESH_SECS", 3600)
+}
+
+// largest file the import endpoint accepts (default: 20 MB)
+pub fn import_max_bytes() -> usize {
+    env::var("IMPORT_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(20 * 1024 * 1024)
+}
+
+// storefront address used for product links and relative image paths in feeds
+pub fn store_public_url() -> String {
+    env::var("STORE_PUBLIC_URL")
+        .map(|url| url.trim_end_matches('/').to_string())
+        .unwrap_or_else(|_| "http://localhost:3000".to_string())
+}
+
+// ISO 4217 code catalogue prices are in, the base currency others are converted from (default: USD)
+pub fn store_currency() -> String {
+    env::var("STORE_CURRENCY")
+        .map(|code| code.trim().to_uppercase())
+        .unwrap_or_else(|_| "USD".to_string())
+}
+
+// most products a single bulk operation may touch (default: 5000)
+pub fn bulk_max_products() -> usize {
+    env::var("BULK_MAX_PRODUCTS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(5000)
+}
+
+// whether catalogue prices already include tax, as is usual for VAT, rather than having it
+// added at checkout, as is usual for sales tax (default: false)
+pub fn prices_include_tax() -> bool {
+    env::var("PRICES_INCLUDE_TAX")
+        .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+// ISO 3166 country taxed when the customer hasn't given an address (default: none, no tax)
+pub fn store_country() -> Option<String> {
+    env::var("STORE_COUNTRY")
+        .ok()
+        .map(|code| code.trim().to_uppercase())
+        .filter(|code| !code.is_empty())
+}
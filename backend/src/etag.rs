@@ -0,0 +1,40 @@
+// ETags for products and categories are their latest change-history version, so they
+// move with every recorded edit; stock levels aren't versioned and don't change them.
+// A product's pricing, shipping and tax class share its ETag, as they're all in its history
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+pub fn etag(version: i32) -> String {
+    format!("\"{}\"", version)
+}
+
+// `body` with the ETag for `version`
+pub fn with_etag(version: i32, status: StatusCode, body: impl IntoResponse) -> Response {
+    (status, [(header::ETAG, etag(version))], body).into_response()
+}
+
+// the version an edit was based on; None for `If-Match: *`, which skips the check
+pub fn if_match(headers: &HeaderMap) -> Result<Option<i32>, (StatusCode, String)> {
+    let value = headers
+        .get(header::IF_MATCH)
+        .ok_or((
+            StatusCode::PRECONDITION_REQUIRED,
+            "Send If-Match with the ETag from your last read".to_string(),
+        ))?
+        .to_str()
+        .unwrap_or_default()
+        .trim();
+
+    if value == "*" {
+        return Ok(None);
+    }
+
+    value
+        .trim_start_matches("W/")
+        .trim_matches('"')
+        .parse()
+        .map(Some)
+        .map_err(|_| (StatusCode::BAD_REQUEST, "If-Match must be a single ETag returned by this API".to_string()))
+}
@@ -8,10 +8,13 @@ use std::net::SocketAddr;
 use axum::serve;
 
 mod api;
+mod cli;
 mod config;
 mod db;
+mod etag;
 mod middleware;
 mod models;
+mod pagination;
 mod services;
 
 
@@ -30,13 +33,37 @@ async fn main() {
         .await
         .expect("Failed to connect to database");
 
+    // `easy-buy-backend import <file>` and friends run a command instead of the server
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(e) = cli::run(&pool, &args).await {
+            eprintln!("❌ {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Background jobs
+    services::inventory::spawn_reconciliation_job(pool.clone(), config::stock_reconcile_interval());
+    services::reservation::spawn_reservation_sweeper(pool.clone(), config::reservation_sweep_interval());
+    services::low_stock::spawn_low_stock_monitor(
+        pool.clone(),
+        services::notifier::notifiers_from_env(pool.clone()),
+        config::low_stock_check_interval(),
+    );
+    services::trash::spawn_trash_purger(pool.clone(), config::trash_purge_interval(), config::trash_retention());
+    services::pricing::spawn_price_scheduler(pool.clone(), config::price_scheduler_interval());
+    if let Some(provider) = services::rate_provider::rate_provider_from_env() {
+        services::rate_provider::spawn_rate_refresher(pool.clone(), provider, config::exchange_rate_refresh_interval());
+    }
+
     // Define app routes
     let cors = CorsLayer::new()
         .allow_origin("http://localhost:3000".parse::<axum::http::HeaderValue>().unwrap())
-        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::OPTIONS])
-        .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION])
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE, Method::OPTIONS])
+        .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION, header::IF_MATCH])
         .allow_credentials(true)
-        .expose_headers([header::AUTHORIZATION]);
+        .expose_headers([header::AUTHORIZATION, header::ETAG]);
 
     let app = Router::new()
         .route("/", get(|| async { "Easy Buy API is running 🚀" }))
@@ -44,8 +71,24 @@ async fn main() {
             .nest("/auth", api::auth::auth_routes())
             .merge(api::user::user_routes())
             .merge(api::products::product_routes(pool.clone()))
-            .merge(api::category::category_routes())
+            .merge(api::category::category_routes(pool.clone()))
             .merge(api::cart::cart_routes())
+            .merge(api::variants::variant_routes())
+            .merge(api::inventory::inventory_routes(pool.clone()))
+            .merge(api::checkout::checkout_routes())
+            .merge(api::warehouses::warehouse_routes(pool.clone()))
+            .merge(api::search_analytics::search_analytics_routes(pool.clone()))
+            .merge(api::trash::trash_routes(pool.clone()))
+            .merge(api::attributes::attribute_routes(pool.clone()))
+            .merge(api::imports::import_routes(pool.clone()))
+            .merge(api::export::export_routes(pool.clone()))
+            .merge(api::bulk::bulk_routes(pool.clone()))
+            .merge(api::history::history_routes(pool.clone()))
+            .merge(api::pricing::pricing_routes(pool.clone()))
+            .merge(api::currencies::currency_routes(pool.clone()))
+            .merge(api::promotions::promotion_routes(pool.clone()))
+            .merge(api::taxes::tax_routes(pool.clone()))
+            .merge(api::shipping::shipping_routes(pool.clone()))
         )
         .layer(cors)
         .with_state(pool);
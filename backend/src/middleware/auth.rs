@@ -14,6 +14,13 @@ use crate::models::user::UserRole;
 
 pub struct AuthMiddleware(pub Claims);
 
+impl AuthMiddleware {
+    // the signed-in user's id, as recorded against the changes they make
+    pub fn user_id(&self) -> Option<uuid::Uuid> {
+        uuid::Uuid::parse_str(&self.0.sub).ok()
+    }
+}
+
 #[async_trait]
 impl<S> FromRequestParts<S> for AuthMiddleware
 where
@@ -0,0 +1,54 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Clone, Copy)]
+#[sqlx(type_name = "attribute_kind", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum AttributeKind {
+    Text,
+    Number,
+    Boolean,
+    Enum,
+}
+
+// a typed attribute products in the category (and its subcategories) can carry
+#[derive(Debug, Serialize, FromRow)]
+pub struct AttributeDefinition {
+    pub id: Uuid,
+    pub category_id: Uuid,
+    pub key: String,
+    pub name: String,
+    pub kind: AttributeKind,
+    pub allowed_values: Vec<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl AttributeDefinition {
+    pub fn accepts(&self, value: &Value) -> bool {
+        match (self.kind, value) {
+            (AttributeKind::Text, Value::String(_)) => true,
+            (AttributeKind::Number, Value::Number(_)) => true,
+            (AttributeKind::Boolean, Value::Bool(_)) => true,
+            (AttributeKind::Enum, Value::String(s)) => self.allowed_values.contains(s),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewAttributeDefinition {
+    pub key: String, // the name used in product attributes and filters
+    pub name: String,
+    pub kind: AttributeKind,
+    #[serde(default)]
+    pub allowed_values: Vec<String>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct TagCount {
+    pub tag: String,
+    pub products: i64,
+}
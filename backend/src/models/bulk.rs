@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+use bigdecimal::BigDecimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::product::ProductQueryParams;
+
+// applied to each product in the order given
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BulkOperation {
+    SetPrice { price: BigDecimal },
+    AdjustPrice { percent: Option<BigDecimal>, amount: Option<BigDecimal> }, // one of the two, e.g. percent: -20
+    SetStock { stock_quantity: i32 },
+    SetCategory { category_id: Uuid }, // primary category
+    SoftDelete,
+    Restore,
+}
+
+// body of POST /products/bulk; the products are either listed or matched by search filters
+#[derive(Deserialize)]
+pub struct BulkRequest {
+    pub ids: Option<Vec<Uuid>>,
+    pub filter: Option<ProductQueryParams>, // same fields as the search query string, live products only
+    pub operations: Vec<BulkOperation>,
+    pub versions: Option<HashMap<Uuid, i32>>, // each listed product's ETag from the last read
+    #[serde(default)]
+    pub atomic: bool, // all or nothing; otherwise each product succeeds or fails on its own
+}
+
+#[derive(Serialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkItemStatus {
+    Updated,
+    Failed,
+    RolledBack, // would have succeeded, but another product failed an atomic request
+}
+
+#[derive(Serialize)]
+pub struct BulkItemResult {
+    pub id: Uuid,
+    pub status: BulkItemStatus,
+    pub error: Option<String>,
+    pub version: Option<i32>, // the new ETag, once the update is committed
+}
+
+#[derive(Serialize)]
+pub struct BulkSummary {
+    pub matched: usize,
+    pub updated: usize,
+    pub failed: usize,
+    pub committed: bool,
+    pub results: Vec<BulkItemResult>,
+}
@@ -1,5 +1,9 @@
+use bigdecimal::BigDecimal;
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
+
+use crate::models::promotion::{AppliedDiscount, RejectedCoupon};
+use crate::models::tax::TaxBreakdown;
 use chrono::NaiveDateTime;
 
 #[derive(Serialize, Deserialize, sqlx::FromRow)]
@@ -7,6 +11,7 @@ pub struct CartItem {
     pub id: Uuid,
     pub user_id: Uuid,
     pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
     pub quantity: i32,
     pub created_at: Option<NaiveDateTime>,
     pub updated_at: Option<NaiveDateTime>,
@@ -16,5 +21,42 @@ pub struct CartItem {
 #[derive(Deserialize)]
 pub struct AddToCartRequest {
     pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
     pub quantity: i32,
 }
+
+#[derive(Deserialize)]
+pub struct RemoveFromCartQuery {
+    pub variant_id: Option<Uuid>,
+}
+
+// a cart line priced at what it would cost now
+#[derive(Serialize)]
+pub struct CartLine {
+    #[serde(flatten)]
+    pub item: CartItem,
+    pub unit_price: BigDecimal,
+    pub original_unit_price: Option<BigDecimal>, // null unless the product is discounted
+    pub line_total: BigDecimal,
+}
+
+#[derive(Serialize)]
+pub struct CartSummary {
+    pub currency: String,
+    pub items: Vec<CartLine>,
+    pub subtotal: BigDecimal, // sum of the line totals
+    pub coupons: Vec<String>,
+    pub discounts: Vec<AppliedDiscount>,
+    pub rejected_coupons: Vec<RejectedCoupon>,
+    pub discount_total: BigDecimal,
+    pub free_shipping: bool,
+    pub tax: TaxBreakdown,
+    pub total: BigDecimal, // subtotal less discounts, plus tax unless prices include it
+}
+
+// a cart line at base-currency prices, before any conversion
+pub struct BaseCartLine {
+    pub item: CartItem,
+    pub unit_price: BigDecimal,
+    pub original_unit_price: Option<BigDecimal>,
+}
@@ -6,23 +6,47 @@ use uuid::Uuid;
 pub struct Category {
     pub id: Uuid,
     pub name: String,
+    pub slug: String,
     pub description: Option<String>, 
     pub created_at: Option<chrono::NaiveDateTime>,
     pub updated_at: Option<chrono::NaiveDateTime>,
-    
-    
+    pub parent_id: Option<Uuid>, // None for top-level categories
+}
 
+// the category fields embedded in product responses
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct CategorySummary {
+    pub id: Uuid,
+    pub name: String,
+    pub slug: String,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreateCategory {
     pub name: String,
+    pub slug: Option<String>, // generated from the name when missing
     pub description: Option<String>,
+    pub parent_id: Option<Uuid>,
+}
+
+// a category with its children, for the tree endpoints
+#[derive(Debug, Serialize)]
+pub struct CategoryNode {
+    #[serde(flatten)]
+    pub category: Category,
+    pub children: Vec<CategoryNode>,
+}
+
+// parent_id: null moves the subtree to the top level
+#[derive(Deserialize)]
+pub struct MoveCategory {
+    pub parent_id: Option<Uuid>,
 }
 
 #[derive(Deserialize)]
 pub struct UpdateCategoryRequest {
     pub name: Option<String>,
+    pub slug: Option<String>,
     pub description: Option<String>,
 }
 
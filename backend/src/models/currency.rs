@@ -0,0 +1,70 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Clone, Copy)]
+#[sqlx(type_name = "rounding_mode", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    HalfUp,
+    HalfEven,
+    Up,   // away from zero
+    Down, // towards zero
+}
+
+#[derive(Serialize, FromRow, Clone)]
+pub struct Currency {
+    pub code: String,
+    pub name: String,
+    pub symbol: Option<String>,
+    pub decimals: i16,
+    pub rounding_increment: Option<BigDecimal>, // null: the smallest unit, 10^-decimals
+    pub rounding: RoundingMode,
+    pub enabled: bool,
+}
+
+// body of PUT /currencies/:code
+#[derive(Deserialize)]
+pub struct UpsertCurrency {
+    pub name: String,
+    pub symbol: Option<String>,
+    pub decimals: i16,
+    pub rounding_increment: Option<BigDecimal>,
+    pub rounding: Option<RoundingMode>, // default half_up
+    pub enabled: Option<bool>,          // default true
+}
+
+// a currency with its rate from the base currency
+#[derive(Serialize, FromRow)]
+pub struct CurrencyRate {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub currency: Currency,
+    pub base: bool,
+    pub rate: Option<BigDecimal>, // 1 for the base currency; null until a rate is set
+    pub rate_source: Option<String>,
+    pub rate_updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct ExchangeRate {
+    pub currency: String,
+    pub rate: BigDecimal,
+    pub source: String,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: NaiveDateTime,
+}
+
+// body of PUT /currencies/:code/rate
+#[derive(Deserialize)]
+pub struct SetExchangeRate {
+    pub rate: BigDecimal, // units of this currency per unit of the base currency
+}
+
+// `?currency=XAF` on product and cart reads; prices are in the base currency without it
+#[derive(Deserialize)]
+pub struct CurrencyParams {
+    pub currency: Option<String>,
+}
@@ -0,0 +1,36 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+    Xml, // RSS 2.0 with Google Merchant `g:` fields
+}
+
+// a product as written to exports and feeds
+#[derive(Serialize, FromRow)]
+pub struct ExportRow {
+    pub id: Uuid,
+    pub sku: Option<String>,
+    pub name: String,
+    pub slug: String,
+    pub description: Option<String>,
+    pub price: BigDecimal,
+    pub sale_price: Option<BigDecimal>,
+    pub sale_starts_at: Option<NaiveDateTime>,
+    pub sale_ends_at: Option<NaiveDateTime>,
+    pub stock_quantity: i32,
+    pub category: Option<String>, // primary category name
+    pub image_url: Option<String>,
+    #[sqlx(skip)]
+    pub link: String, // storefront product page
+    pub tags: Vec<String>,
+    pub attributes: Value,
+    pub updated_at: Option<NaiveDateTime>,
+}
@@ -0,0 +1,33 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, sqlx::Type, PartialEq, Clone, Copy)]
+#[sqlx(type_name = "history_entity", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryEntity {
+    Product,
+    Category,
+}
+
+#[derive(Debug, Serialize, sqlx::Type, PartialEq, Clone, Copy)]
+#[sqlx(type_name = "history_action", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryAction {
+    Created,
+    Updated,
+    Deleted,
+}
+
+// one version of a product or category, recorded by the history triggers
+#[derive(Serialize, FromRow)]
+pub struct ChangeRecord {
+    pub version: i32,
+    pub action: HistoryAction,
+    pub changes: Value,          // {"price": {"from": 10.0, "to": 8.0}}
+    pub snapshot: Option<Value>, // state after the change
+    pub actor_id: Option<Uuid>,
+    pub changed_at: NaiveDateTime,
+}
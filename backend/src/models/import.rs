@@ -0,0 +1,82 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Clone, Copy)]
+#[sqlx(type_name = "import_format", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    Csv,
+    Ndjson,
+}
+
+#[derive(Debug, Serialize, sqlx::Type, PartialEq, Clone, Copy)]
+#[sqlx(type_name = "import_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct ImportJob {
+    pub id: Uuid,
+    pub format: ImportFormat,
+    pub dry_run: bool,
+    pub create_categories: bool,
+    pub status: ImportStatus,
+    pub total_rows: i32,
+    pub processed_rows: i32,
+    pub created_count: i32,
+    pub updated_count: i32,
+    pub error_count: i32,
+    pub error: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub started_at: Option<NaiveDateTime>,
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+// format falls back to the request's Content-Type
+#[derive(Deserialize)]
+pub struct ImportParams {
+    pub format: Option<ImportFormat>,
+    pub dry_run: Option<bool>,
+    pub create_categories: Option<bool>, // create unknown categories instead of rejecting the row
+}
+
+#[derive(Clone, Copy)]
+pub struct ImportOptions {
+    pub format: ImportFormat,
+    pub dry_run: bool,
+    pub create_categories: bool,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct ImportRowError {
+    pub row_number: i32,
+    pub sku: Option<String>,
+    pub message: String,
+}
+
+// one product line. CSV columns use the same names, with tags separated by `|`
+// and attributes as `attr:<key>` columns
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImportRow {
+    pub sku: Option<String>,
+    pub name: Option<String>,
+    pub slug: Option<String>,
+    pub description: Option<String>,
+    pub price: Option<BigDecimal>,
+    pub stock_quantity: Option<i32>,
+    pub category: Option<String>, // category name
+    pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub attributes: Map<String, Value>,
+}
@@ -0,0 +1,99 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Clone, Copy)]
+#[sqlx(type_name = "stock_movement_kind", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum StockMovementKind {
+    Receipt,
+    Sale,
+    Return,
+    Adjustment,
+    Damage,
+    Transfer,
+}
+
+impl StockMovementKind {
+    // signed delta for a quantity given by the caller; adjustments are already signed
+    pub fn signed(self, quantity: i32) -> i32 {
+        match self {
+            StockMovementKind::Receipt
+            | StockMovementKind::Return
+            | StockMovementKind::Adjustment
+            | StockMovementKind::Transfer => quantity,
+            StockMovementKind::Sale | StockMovementKind::Damage => -quantity,
+        }
+    }
+}
+
+#[derive(Serialize, FromRow)]
+pub struct StockMovement {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub warehouse_id: Option<Uuid>,
+    pub kind: StockMovementKind,
+    pub quantity: i32,
+    pub reason: Option<String>,
+    pub actor_id: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+}
+
+pub struct NewStockMovement {
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub warehouse_id: Option<Uuid>, // None: default location for increases, allocation strategy for decreases
+    pub kind: StockMovementKind,
+    pub quantity: i32, // signed delta
+    pub reason: Option<String>,
+    pub actor_id: Option<Uuid>,
+}
+
+// body of POST /products/:id/stock-movements
+#[derive(Deserialize)]
+pub struct RecordStockMovementRequest {
+    pub variant_id: Option<Uuid>,
+    pub warehouse_id: Option<Uuid>,
+    pub kind: StockMovementKind,
+    pub quantity: i32, // positive, except for adjustments which are signed
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct StockDrift {
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub ledger_quantity: i64,
+    pub recorded_quantity: i32,
+    pub detected_at: NaiveDateTime,
+    pub checked_at: NaiveDateTime,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct LowStockAlert {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub stock_quantity: i32,
+    pub reorder_threshold: i32,
+    pub created_at: NaiveDateTime,
+    pub notified_at: Option<NaiveDateTime>,
+    pub resolved_at: Option<NaiveDateTime>,
+}
+
+// product at or below its reorder threshold, with how long current stock should last
+#[derive(Serialize, FromRow)]
+pub struct LowStockProduct {
+    pub product_id: Uuid,
+    pub name: String,
+    pub stock_quantity: i32,
+    pub reorder_threshold: i32,
+    pub daily_sales: f64,
+    pub days_of_cover: Option<f64>, // None when nothing sold in the window
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UpdateReorderThreshold {
+    pub reorder_threshold: Option<i32>, // null stops monitoring the product
+}
@@ -2,4 +2,21 @@ pub mod user;
 pub mod product;
 pub mod category;
 pub mod cart;
+pub mod variant;
+pub mod inventory;
+pub mod reservation;
+pub mod notification;
+pub mod warehouse;
+pub mod search;
+pub mod trash;
+pub mod attribute;
+pub mod import;
 
+pub mod export;
+pub mod bulk;
+pub mod history;
+pub mod pricing;
+pub mod currency;
+pub mod promotion;
+pub mod tax;
+pub mod shipping;
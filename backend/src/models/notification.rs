@@ -0,0 +1,13 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Serialize, FromRow)]
+pub struct AdminNotification {
+    pub id: Uuid,
+    pub subject: String,
+    pub body: String,
+    pub created_at: NaiveDateTime,
+    pub read_at: Option<NaiveDateTime>,
+}
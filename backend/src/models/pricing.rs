@@ -0,0 +1,137 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::config::store_currency;
+
+// a product's pricing columns
+#[derive(Serialize, FromRow, Clone)]
+pub struct ProductPricing {
+    pub product_id: Uuid,
+    pub price: BigDecimal, // base price
+    pub compare_at_price: Option<BigDecimal>,
+    pub sale_price: Option<BigDecimal>,
+    pub sale_starts_at: Option<NaiveDateTime>,
+    pub sale_ends_at: Option<NaiveDateTime>,
+}
+
+impl ProductPricing {
+    // no compare-at price and no sale
+    pub fn base_only(product_id: Uuid, price: BigDecimal) -> Self {
+        ProductPricing {
+            product_id,
+            price,
+            compare_at_price: None,
+            sale_price: None,
+            sale_starts_at: None,
+            sale_ends_at: None,
+        }
+    }
+
+    pub fn sale_active(&self, at: NaiveDateTime) -> bool {
+        self.sale_price.is_some()
+            && self.sale_starts_at.is_none_or(|start| start <= at)
+            && self.sale_ends_at.is_none_or(|end| end > at)
+    }
+
+    // what a customer pays at `at`
+    pub fn effective_price(&self, at: NaiveDateTime) -> BigDecimal {
+        match &self.sale_price {
+            Some(sale_price) if self.sale_active(at) => sale_price.clone(),
+            _ => self.price.clone(),
+        }
+    }
+
+    // price shown struck through next to the effective one: the compare-at price when set,
+    // else the base price during a sale; None unless it's higher than what's charged
+    pub fn original_price(&self, at: NaiveDateTime) -> Option<BigDecimal> {
+        let effective = self.effective_price(at);
+        let original = match &self.compare_at_price {
+            Some(compare_at) => compare_at.clone(),
+            None => self.price.clone(),
+        };
+        (original > effective).then_some(original)
+    }
+
+    pub fn display(&self, at: NaiveDateTime) -> PriceDisplay {
+        let on_sale = self.sale_active(at);
+        PriceDisplay {
+            effective_price: self.effective_price(at),
+            original_price: self.original_price(at),
+            sale_ends_at: if on_sale { self.sale_ends_at } else { None },
+            currency: store_currency(),
+        }
+    }
+}
+
+// prices embedded in product responses, as of the request
+#[derive(Serialize)]
+pub struct PriceDisplay {
+    pub effective_price: BigDecimal,
+    pub original_price: Option<BigDecimal>, // null unless discounted
+    pub sale_ends_at: Option<NaiveDateTime>,
+    pub currency: String, // of every price in the response
+}
+
+// body of PUT /products/:id/pricing; replaces the compare-at price and the sale,
+// missing fields clear them. The base price is changed by updating the product
+#[derive(Deserialize)]
+pub struct SetPricing {
+    pub compare_at_price: Option<BigDecimal>,
+    pub sale_price: Option<BigDecimal>,
+    pub sale_starts_at: Option<NaiveDateTime>, // null: starts now
+    pub sale_ends_at: Option<NaiveDateTime>,   // null: runs until removed
+}
+
+// pricing with the effective price right now and the changes still to come
+#[derive(Serialize)]
+pub struct PricingDetails {
+    #[serde(flatten)]
+    pub pricing: ProductPricing,
+    #[serde(flatten)]
+    pub display: PriceDisplay,
+    pub on_sale: bool,
+    pub scheduled_changes: Vec<ScheduledPriceChange>,
+}
+
+#[derive(Debug, Serialize, sqlx::Type, PartialEq, Clone, Copy)]
+#[sqlx(type_name = "price_change_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum PriceChangeStatus {
+    Pending,
+    Applied,
+    Cancelled,
+}
+
+// new base price the price scheduler sets once `apply_at` passes
+#[derive(Serialize, FromRow)]
+pub struct ScheduledPriceChange {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub price: BigDecimal,
+    pub apply_at: NaiveDateTime,
+    pub status: PriceChangeStatus,
+    pub created_by: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub applied_at: Option<NaiveDateTime>,
+}
+
+// body of POST /products/:id/price-schedule
+#[derive(Deserialize)]
+pub struct SchedulePriceChange {
+    pub price: BigDecimal,
+    pub apply_at: NaiveDateTime, // UTC
+}
+
+#[derive(Serialize, FromRow)]
+pub struct PriceHistoryEntry {
+    pub price: BigDecimal,
+    pub compare_at_price: Option<BigDecimal>,
+    pub sale_price: Option<BigDecimal>,
+    pub sale_starts_at: Option<NaiveDateTime>,
+    pub sale_ends_at: Option<NaiveDateTime>,
+    pub actor_id: Option<Uuid>,
+    pub changed_at: NaiveDateTime,
+}
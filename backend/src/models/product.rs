@@ -3,6 +3,13 @@ use serde::{Serialize, Deserialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 use bigdecimal::BigDecimal;
+use serde_json::{Map, Value};
+
+use crate::models::category::CategorySummary;
+use crate::models::pricing::PriceDisplay;
+use crate::models::variant::VariantDetails;
+use crate::models::warehouse::LocationStock;
+use crate::pagination::PageParams;
 
 
 
@@ -10,9 +17,18 @@ use bigdecimal::BigDecimal;
 #[derive(Deserialize)]
 pub struct CreateProduct {
     pub name: String,
+    pub slug: Option<String>, // generated from the name when missing
+    pub sku: Option<String>,
     pub description: Option<String>,
     pub price: BigDecimal,
     pub stock_quantity: i32,
+    pub category_id: Option<Uuid>,
+    #[serde(default)]
+    pub secondary_category_ids: Vec<Uuid>, // also listed under these
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub attributes: Map<String, Value>, // keys defined for the product's categories
 }
 
 
@@ -20,35 +36,104 @@ pub struct CreateProduct {
 pub struct Product {
     pub id: Uuid,
     pub name: String,
+    pub slug: String,
+    pub sku: Option<String>,
     pub description: Option<String>,
     pub price: BigDecimal,
     pub stock_quantity: i32,
+    pub category_id: Option<Uuid>, // primary category
+    pub attributes: Value,
     pub created_at: Option<NaiveDateTime>,
     pub updated_at: Option<NaiveDateTime>,
 }
 
+// product as returned by the read endpoints, with its variants embedded
+#[derive(Serialize)]
+pub struct ProductDetails {
+    #[serde(flatten)]
+    pub product: Product,
+    #[serde(flatten)]
+    pub pricing: PriceDisplay, // `price` is the base price, this is what's charged
+    pub available_quantity: i32, // stock minus active checkout reservations
+    pub category: Option<CategorySummary>,
+    pub secondary_categories: Vec<CategorySummary>,
+    pub tags: Vec<String>,
+    pub locations: Vec<LocationStock>,
+    pub variants: Vec<VariantDetails>,
+}
+
+// search result row: the product with its relevance and highlighted snippet
+#[derive(FromRow)]
+pub struct ProductSearchRow {
+    #[sqlx(flatten)]
+    pub product: Product,
+    pub rank: Option<f32>,
+    pub snippet: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ProductSearchHit {
+    #[serde(flatten)]
+    pub product: ProductDetails,
+    pub rank: Option<f32>,       // null when no search text was given
+    pub snippet: Option<String>, // name/description excerpt with matches wrapped in <mark>
+}
+
 
 
 // dendpoint to udate product content 
 #[derive(Deserialize)]
 pub struct UpdateProduct {
     pub name: Option<String>,
+    pub slug: Option<String>, // the old slug keeps redirecting here
+    pub sku: Option<String>,
     pub description: Option<String>,
     pub price: Option<BigDecimal>,
     pub stock_quantity: Option<i32>,
+    pub category_id: Option<Uuid>,
+    pub secondary_category_ids: Option<Vec<Uuid>>, // replaces the current set
+    pub tags: Option<Vec<String>>,                 // replaces the current tags
+    pub attributes: Option<Map<String, Value>>,    // merged in; null removes a key
     pub deleted_at: Option<chrono::NaiveDateTime>,
 }
 
 // search product 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct ProductQueryParams {
     pub query: Option<String>,
     pub category_id: Option<Uuid>,
-    pub min_price: Option<BigDecimal>,
+    pub include_descendants: Option<bool>, // also match products in child categories
+    pub min_price: Option<BigDecimal>, // in the requested currency, like the prices in the hits
     pub max_price: Option<BigDecimal>,
     pub in_stock: Option<bool>,
+    pub sku: Option<String>,
+    pub options: Option<String>, // "Size:M,Colour:Red", all matched by a single variant
+    pub tags: Option<String>,       // "eco,organic", every tag required
+    pub attributes: Option<String>, // "brand:Acme|Globex,weight:>=2,waterproof:true"
+    pub facets: Option<bool>,           // include total and facet counts in the response
+    pub price_buckets: Option<String>,  // "25,50,100" cut points for the price facet, in the requested currency
     pub page: Option<u32>,
     pub limit: Option<u32>,
+    pub cursor: Option<String>,
+    pub sort: Option<String>,  // relevance | price | created_at | name | popularity
+    pub order: Option<String>,
+}
+
+impl ProductQueryParams {
+    pub fn page_params(&self) -> PageParams {
+        PageParams {
+            page: self.page,
+            limit: self.limit,
+            cursor: self.cursor.clone(),
+            sort: self.sort.clone(),
+            order: self.order.clone(),
+        }
+    }
+
+    // search text, ignoring blank queries
+    pub fn text(&self) -> Option<&str> {
+        self.query.as_deref().map(str::trim).filter(|q| !q.is_empty())
+    }
 }
 
 
@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Clone, Copy)]
+#[sqlx(type_name = "promotion_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum PromotionKind {
+    Percentage,   // `value` percent off the qualifying items
+    FixedAmount,  // `value` off the qualifying items
+    FreeShipping,
+    BuyXGetY, // for every `buy_quantity` qualifying items, `get_quantity` more at `value` percent off
+}
+
+#[derive(Serialize, FromRow, Clone)]
+pub struct Promotion {
+    pub id: Uuid,
+    pub name: String,
+    pub code: Option<String>, // null: applied automatically
+    pub kind: PromotionKind,
+    pub value: Option<BigDecimal>,
+    pub buy_quantity: Option<i32>,
+    pub get_quantity: Option<i32>,
+    pub starts_at: Option<NaiveDateTime>,
+    pub ends_at: Option<NaiveDateTime>,
+    pub usage_limit: Option<i32>,
+    pub per_user_limit: Option<i32>,
+    pub min_subtotal: Option<BigDecimal>,
+    pub stackable: bool,
+    pub priority: i32,
+    pub active: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+// promotion as returned by the admin endpoints
+#[derive(Serialize)]
+pub struct PromotionDetails {
+    #[serde(flatten)]
+    pub promotion: Promotion,
+    pub product_ids: Vec<Uuid>,
+    pub category_ids: Vec<Uuid>,
+    pub redemptions: i64,
+}
+
+// body of POST /promotions and PUT /promotions/:id
+#[derive(Deserialize)]
+pub struct PromotionInput {
+    pub name: String,
+    pub code: Option<String>,
+    pub kind: PromotionKind,
+    pub value: Option<BigDecimal>,
+    pub buy_quantity: Option<i32>,
+    pub get_quantity: Option<i32>,
+    pub starts_at: Option<NaiveDateTime>,
+    pub ends_at: Option<NaiveDateTime>,
+    pub usage_limit: Option<i32>,
+    pub per_user_limit: Option<i32>,
+    pub min_subtotal: Option<BigDecimal>,
+    #[serde(default)]
+    pub stackable: bool,
+    #[serde(default)]
+    pub priority: i32,
+    pub active: Option<bool>, // default true
+    #[serde(default)]
+    pub product_ids: Vec<Uuid>,
+    #[serde(default)]
+    pub category_ids: Vec<Uuid>,
+}
+
+// body of POST /cart/coupons
+#[derive(Deserialize)]
+pub struct ApplyCoupon {
+    pub code: String,
+}
+
+// promotion engine input: a promotion with what it's limited to and how often it's been used
+pub struct PromotionCandidate {
+    pub promotion: Promotion,
+    pub product_ids: HashSet<Uuid>,
+    pub category_ids: HashSet<Uuid>, // descendants included
+    pub redemptions: i64,
+    pub user_redemptions: i64,
+}
+
+// promotion engine input: one cart line
+pub struct DiscountLine {
+    pub product_id: Uuid,
+    pub category_ids: Vec<Uuid>, // primary and secondary
+    pub unit_price: BigDecimal,
+    pub quantity: i32,
+}
+
+#[derive(Serialize, Clone)]
+pub struct AppliedDiscount {
+    pub promotion_id: Uuid,
+    pub name: String,
+    pub code: Option<String>,
+    pub kind: PromotionKind,
+    pub amount: BigDecimal,
+}
+
+// a coupon on the cart that isn't taking effect, and why
+#[derive(Serialize)]
+pub struct RejectedCoupon {
+    pub code: String,
+    pub reason: String,
+}
+
+#[derive(Serialize)]
+pub struct DiscountOutcome {
+    pub applied: Vec<AppliedDiscount>,
+    pub rejected: Vec<RejectedCoupon>,
+    pub discount_total: BigDecimal,
+    pub free_shipping: bool,
+    #[serde(skip)]
+    pub line_discounts: Vec<BigDecimal>, // each line's part of discount_total, in the order given
+}
@@ -0,0 +1,36 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, sqlx::Type, PartialEq, Clone, Copy)]
+#[sqlx(type_name = "reservation_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ReservationStatus {
+    Active,
+    Released,
+    Expired,
+    Converted,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct StockReservation {
+    pub id: Uuid,
+    pub checkout_id: Uuid,
+    pub user_id: Uuid,
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub quantity: i32,
+    pub status: ReservationStatus,
+    pub expires_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+// result of starting a checkout: every cart line held until expires_at
+#[derive(Serialize)]
+pub struct CheckoutReservation {
+    pub checkout_id: Uuid,
+    pub expires_at: NaiveDateTime,
+    pub reservations: Vec<StockReservation>,
+}
@@ -0,0 +1,115 @@
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::product::ProductSearchHit;
+use crate::pagination::{Page, Paged};
+
+// a page of hits, plus facet counts when `facets=true`
+#[derive(Serialize)]
+pub struct SearchResults {
+    #[serde(flatten)]
+    pub page: Page<ProductSearchHit>,
+    pub fuzzy: bool, // true when the text only matched through the misspelling fallback
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facets: Option<SearchFacets>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search_id: Option<Uuid>, // report clicks against this id
+}
+
+pub struct SearchOutcome {
+    pub hits: Paged<ProductSearchHit>,
+    pub fuzzy: bool,
+    pub facets: Option<SearchFacets>,
+}
+
+// each facet ignores its own filter so the other choices stay visible
+#[derive(Serialize)]
+pub struct SearchFacets {
+    pub categories: Vec<CategoryFacet>,
+    pub price_ranges: Vec<PriceRangeFacet>,
+    pub availability: Vec<AvailabilityFacet>,
+    pub options: Vec<OptionFacet>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct CategoryFacet {
+    pub category_id: Uuid,
+    pub name: String,
+    pub count: i64,
+}
+
+// min inclusive, max exclusive; the last range has no max
+#[derive(Serialize)]
+pub struct PriceRangeFacet {
+    pub min: BigDecimal,
+    pub max: Option<BigDecimal>,
+    pub count: i64,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct AvailabilityFacet {
+    pub in_stock: bool,
+    pub count: i64,
+}
+
+// variant option values, counted once per product
+#[derive(Serialize, FromRow)]
+pub struct OptionFacet {
+    pub name: String,
+    pub value: String,
+    pub count: i64,
+}
+
+#[derive(Deserialize)]
+pub struct SuggestParams {
+    pub q: Option<String>,
+    pub limit: Option<u32>,
+}
+
+// an autocomplete entry; id is the product or category it completes to
+#[derive(Serialize, FromRow)]
+pub struct Suggestion {
+    pub text: String,
+    pub kind: String, // product | category
+    pub id: Uuid,
+}
+
+#[derive(Deserialize)]
+pub struct RecordSearchClick {
+    pub product_id: Uuid,
+}
+
+// inclusive date range, defaulting to the last 30 days
+#[derive(Deserialize)]
+pub struct SearchReportParams {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct TopSearchQuery {
+    pub query: String,
+    pub searches: i64,
+    pub avg_results: f64,
+    pub clicks: i64,
+    pub click_through_rate: f64,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct ZeroResultQuery {
+    pub query: String,
+    pub searches: i64,
+    pub last_searched_at: NaiveDateTime,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct ClickThroughReport {
+    pub searches: i64,
+    pub clicked_searches: i64,
+    pub zero_result_searches: i64,
+    pub click_through_rate: f64,
+}
@@ -0,0 +1,139 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Clone, Copy)]
+#[sqlx(type_name = "shipping_method_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ShippingMethodKind {
+    FlatRate,          // `price` per order
+    WeightBased,       // by the weight bracket the parcel fits in
+    FreeOverThreshold, // `price`, or free once the order reaches `free_threshold`
+    LocalPickup,       // collected from `pickup_warehouse_id`, usually free
+}
+
+#[derive(Serialize, Deserialize, FromRow, Clone)]
+pub struct ShippingArea {
+    pub country: String,
+    pub region: Option<String>, // null: the whole country
+}
+
+#[derive(Serialize, Deserialize, FromRow, Clone)]
+pub struct WeightRate {
+    pub up_to_grams: i32,
+    pub price: BigDecimal,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct ShippingMethod {
+    pub id: Uuid,
+    pub zone_id: Uuid,
+    pub name: String,
+    pub kind: ShippingMethodKind,
+    pub price: BigDecimal,
+    pub free_threshold: Option<BigDecimal>,
+    pub pickup_warehouse_id: Option<Uuid>,
+    pub min_days: Option<i32>,
+    pub max_days: Option<i32>,
+    pub position: i32,
+    pub enabled: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+// method as returned by the admin endpoints
+#[derive(Serialize)]
+pub struct ShippingMethodDetails {
+    #[serde(flatten)]
+    pub method: ShippingMethod,
+    pub weight_rates: Vec<WeightRate>, // lightest bracket first
+}
+
+// body of POST /shipping/zones/:id/methods and PUT /shipping/methods/:id
+#[derive(Deserialize)]
+pub struct ShippingMethodInput {
+    pub name: String,
+    pub kind: ShippingMethodKind,
+    pub price: Option<BigDecimal>, // default 0
+    pub free_threshold: Option<BigDecimal>,
+    pub pickup_warehouse_id: Option<Uuid>,
+    pub min_days: Option<i32>,
+    pub max_days: Option<i32>,
+    #[serde(default)]
+    pub position: i32,
+    pub enabled: Option<bool>, // default true
+    #[serde(default)]
+    pub weight_rates: Vec<WeightRate>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct ShippingZone {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+// zone as returned by the admin endpoints
+#[derive(Serialize)]
+pub struct ShippingZoneDetails {
+    #[serde(flatten)]
+    pub zone: ShippingZone,
+    pub areas: Vec<ShippingArea>,
+    pub methods: Vec<ShippingMethodDetails>,
+}
+
+// body of POST /shipping/zones and PUT /shipping/zones/:id
+#[derive(Deserialize)]
+pub struct ShippingZoneInput {
+    pub name: String,
+    pub areas: Vec<ShippingArea>,
+}
+
+// body of PUT /products/:id/shipping, and what it returns
+#[derive(Serialize, Deserialize, FromRow)]
+pub struct ProductShipping {
+    pub weight_grams: Option<i32>,
+    pub length_mm: Option<i32>,
+    pub width_mm: Option<i32>,
+    pub height_mm: Option<i32>,
+}
+
+// `?country=CM&region=Littoral` on GET /cart/shipping-options
+#[derive(Deserialize)]
+pub struct ShippingAddressParams {
+    pub country: Option<String>,
+    pub region: Option<String>,
+}
+
+// rate provider input: where the cart is going and what it weighs. Amounts are in the base currency
+pub struct ShippingRequest {
+    pub country: String, // ISO 3166-1 alpha-2, upper case
+    pub region: Option<String>,
+    pub weight_grams: i64,            // products without a weight count as weightless
+    pub volumetric_weight_grams: i64, // from the dimensions, for carriers that charge by size
+    pub subtotal: BigDecimal,         // after discounts
+}
+
+#[derive(Serialize, Clone)]
+pub struct ShippingOption {
+    pub id: String, // pass back to pick this option: "<provider>:<method>"
+    pub provider: String,
+    pub name: String,
+    pub kind: Option<ShippingMethodKind>, // null for carrier services
+    pub price: BigDecimal,
+    pub original_price: Option<BigDecimal>, // null unless a promotion made it free
+    pub min_days: Option<i32>,
+    pub max_days: Option<i32>,
+    pub pickup_warehouse_id: Option<Uuid>,
+}
+
+#[derive(Serialize)]
+pub struct ShippingQuote {
+    pub currency: String,
+    pub weight_grams: i64,
+    pub free_shipping: bool, // a promotion on the cart makes delivery free
+    pub options: Vec<ShippingOption>, // cheapest first
+}
@@ -0,0 +1,115 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Serialize, FromRow)]
+pub struct TaxClass {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub is_default: bool, // products without a class are taxed as this one
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+// body of POST /tax/classes and PUT /tax/classes/:id
+#[derive(Deserialize)]
+pub struct TaxClassInput {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub is_default: bool,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct TaxZone {
+    pub id: Uuid,
+    pub name: String,
+    pub country: String,
+    pub region: Option<String>, // null: the whole country
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct TaxRate {
+    pub zone_id: Uuid,
+    pub tax_class_id: Uuid,
+    pub tax_class: String,
+    pub name: String,
+    pub rate: BigDecimal, // percent
+    pub updated_at: NaiveDateTime,
+}
+
+// zone as returned by the admin endpoints
+#[derive(Serialize)]
+pub struct TaxZoneDetails {
+    #[serde(flatten)]
+    pub zone: TaxZone,
+    pub rates: Vec<TaxRate>,
+}
+
+// body of POST /tax/zones and PUT /tax/zones/:id
+#[derive(Deserialize)]
+pub struct TaxZoneInput {
+    pub name: String,
+    pub country: String,
+    pub region: Option<String>,
+}
+
+// body of PUT /tax/zones/:id/rates/:class_id
+#[derive(Deserialize)]
+pub struct SetTaxRate {
+    pub name: String,
+    pub rate: BigDecimal,
+}
+
+// body of PUT /products/:id/tax-class
+#[derive(Deserialize, Serialize)]
+pub struct ProductTaxClass {
+    pub tax_class_id: Option<Uuid>, // null: the default class
+}
+
+// `?country=CM&region=Littoral` on cart reads and checkout completion
+#[derive(Deserialize)]
+pub struct TaxAddressParams {
+    pub country: Option<String>,
+    pub region: Option<String>,
+}
+
+// where the goods are going, which decides the zone
+#[derive(Clone)]
+pub struct TaxAddress {
+    pub country: String, // ISO 3166-1 alpha-2, upper case
+    pub region: Option<String>,
+}
+
+// tax calculator input: one cart line, after its share of the discounts
+pub struct TaxableLine {
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub quantity: i32,
+    pub amount: BigDecimal, // what the customer pays for the line, before tax unless prices include it
+}
+
+#[derive(Serialize, FromRow, Clone)]
+pub struct TaxLine {
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub quantity: i32,
+    pub tax_class: Option<String>,
+    pub zone: Option<String>, // null: no zone covers the address, so no tax
+    pub rate_name: Option<String>,
+    pub rate: BigDecimal,
+    pub taxable_amount: BigDecimal, // after discounts, before tax
+    pub tax_amount: BigDecimal,
+}
+
+#[derive(Serialize)]
+pub struct TaxBreakdown {
+    pub prices_include_tax: bool, // the tax is already part of the prices and isn't added to the total
+    pub lines: Vec<TaxLine>,
+    pub tax_total: BigDecimal,
+}
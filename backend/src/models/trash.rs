@@ -0,0 +1,66 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use crate::models::category::Category;
+use crate::models::product::Product;
+
+#[derive(Serialize, FromRow)]
+pub struct TrashedProduct {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub product: Product,
+    pub deleted_at: NaiveDateTime,
+    pub purge_at: NaiveDateTime, // removed for good after this
+}
+
+#[derive(Serialize, FromRow)]
+pub struct TrashedCategory {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub category: Category,
+    pub deleted_at: NaiveDateTime,
+    pub purge_at: NaiveDateTime,
+}
+
+// what to do with live products left in a category subtree being deleted
+#[derive(Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrphanedProducts {
+    #[default]
+    Reject, // refuse while the subtree still has products
+    Detach, // move them to the deleted category's parent (or uncategorised)
+    Trash,  // delete them with the categories; restoring the category brings them back
+}
+
+#[derive(Deserialize)]
+pub struct DeleteCategoryParams {
+    pub products: Option<OrphanedProducts>,
+}
+
+// a category is deleted together with its live subcategories
+#[derive(Serialize)]
+pub struct CategoryDeletion {
+    pub deleted_at: NaiveDateTime,
+    pub categories: u64,
+    pub products_trashed: u64,
+    pub products_detached: u64,
+}
+
+pub enum DeleteCategoryOutcome {
+    Deleted(CategoryDeletion),
+    HasProducts(i64),
+    VersionMismatch, // edited since the version the caller read
+}
+
+// restoring a category brings back everything deleted along with it
+#[derive(Serialize)]
+pub struct CategoryRestore {
+    pub categories: u64,
+    pub products: u64,
+}
+
+pub enum RestoreOutcome<T> {
+    Restored(T),
+    ParentInTrash, // the parent category has to be restored first
+}
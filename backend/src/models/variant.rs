@@ -0,0 +1,90 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::models::warehouse::LocationStock;
+
+#[derive(Serialize, FromRow)]
+pub struct ProductOption {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub name: String,
+    pub position: i32,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct ProductOptionValue {
+    pub id: Uuid,
+    pub option_id: Uuid,
+    pub value: String,
+    pub position: i32,
+}
+
+// option type with its values, as returned by the API
+#[derive(Serialize)]
+pub struct ProductOptionWithValues {
+    #[serde(flatten)]
+    pub option: ProductOption,
+    pub values: Vec<ProductOptionValue>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateProductOption {
+    pub name: String,
+    pub position: Option<i32>,
+    pub values: Vec<String>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct ProductVariant {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub sku: String,
+    pub price: Option<BigDecimal>,
+    pub stock_quantity: i32,
+    pub barcode: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateVariant {
+    pub sku: String,
+    pub price: Option<BigDecimal>,
+    pub stock_quantity: i32,
+    pub barcode: Option<String>,
+    pub option_value_ids: Vec<Uuid>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateVariant {
+    pub sku: Option<String>,
+    pub price: Option<BigDecimal>,
+    pub stock_quantity: Option<i32>,
+    pub barcode: Option<String>,
+}
+
+// "Size: M" pair attached to a variant
+#[derive(Serialize, FromRow)]
+pub struct VariantOptionValue {
+    #[serde(skip)]
+    pub variant_id: Uuid,
+    pub option: String,
+    pub value: String,
+}
+
+// variant as embedded in product responses
+#[derive(Serialize)]
+pub struct VariantDetails {
+    pub id: Uuid,
+    pub sku: String,
+    pub price: BigDecimal,
+    pub stock_quantity: i32,
+    pub barcode: Option<String>,
+    pub available_quantity: i32,
+    pub available: bool,
+    pub locations: Vec<LocationStock>,
+    pub options: Vec<VariantOptionValue>,
+}
@@ -0,0 +1,92 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Serialize, FromRow)]
+pub struct Warehouse {
+    pub id: Uuid,
+    pub name: String,
+    pub code: String,
+    pub address: Option<String>,
+    pub priority: i32,
+    pub is_default: bool,
+    pub is_active: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct CreateWarehouse {
+    pub name: String,
+    pub code: String,
+    pub address: Option<String>,
+    pub priority: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateWarehouse {
+    pub name: Option<String>,
+    pub address: Option<String>,
+    pub priority: Option<i32>,
+    pub is_default: Option<bool>,
+    pub is_active: Option<bool>,
+}
+
+// stock of one product (or variant) at one location
+#[derive(Serialize, FromRow)]
+pub struct LocationStock {
+    #[serde(skip)]
+    pub product_id: Uuid,
+    #[serde(skip)]
+    pub variant_id: Option<Uuid>,
+    pub warehouse_id: Uuid,
+    pub warehouse_code: String,
+    pub quantity: i32,
+}
+
+// stock of every product at one location
+#[derive(Serialize, FromRow)]
+pub struct WarehouseStockLevel {
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub name: String,
+    pub quantity: i32,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct StockTransfer {
+    pub id: Uuid,
+    pub from_warehouse_id: Uuid,
+    pub to_warehouse_id: Uuid,
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub quantity: i32,
+    pub reason: Option<String>,
+    pub actor_id: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Deserialize)]
+pub struct CreateStockTransfer {
+    pub from_warehouse_id: Uuid,
+    pub to_warehouse_id: Uuid,
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub quantity: i32,
+    pub reason: Option<String>,
+}
+
+// input to an allocation strategy, one per location in priority order
+#[derive(FromRow)]
+pub struct WarehouseLevel {
+    pub warehouse_id: Uuid,
+    pub quantity: i32,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Allocation {
+    pub warehouse_id: Uuid,
+    pub quantity: i32,
+}
@@ -0,0 +1,373 @@
+// shared offset and keyset (cursor) pagination for the list endpoints
+use axum::http::Uri;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgRow;
+use sqlx::{FromRow, Postgres, QueryBuilder, Row};
+use uuid::Uuid;
+
+pub const DEFAULT_LIMIT: u32 = 20;
+pub const MAX_LIMIT: u32 = 100;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PageParams {
+    pub page: Option<u32>,
+    pub limit: Option<u32>,
+    pub cursor: Option<String>,
+    pub sort: Option<String>,
+    pub order: Option<String>, // asc | desc, defaults per sort key
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+impl SortDirection {
+    fn sql(self) -> &'static str {
+        match self {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        }
+    }
+
+    fn comparison(self) -> &'static str {
+        match self {
+            SortDirection::Asc => ">",
+            SortDirection::Desc => "<",
+        }
+    }
+}
+
+// a whitelisted sort: the public name, a non-null SQL expression and the type
+// its text form is cast back to when resuming from a cursor
+pub struct SortKey {
+    pub name: &'static str,
+    pub expr: &'static str,
+    pub sql_type: &'static str,
+    pub direction: SortDirection,
+}
+
+// position after the last row of a page
+#[derive(Serialize, Deserialize)]
+struct Cursor {
+    sort: String,
+    order: SortDirection,
+    value: String,
+    id: Uuid,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    fn decode(raw: &str) -> Option<Cursor> {
+        let bytes = URL_SAFE_NO_PAD.decode(raw).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+}
+
+pub struct PageRequest<'a> {
+    pub page: u32,
+    pub limit: u32,
+    pub sort: &'a SortKey,
+    pub direction: SortDirection,
+    cursor: Option<Cursor>,
+}
+
+impl PageParams {
+    // validate against the endpoint's sort keys; errors are meant for a 400 response
+    pub fn resolve<'a>(&self, keys: &'a [SortKey], default_sort: &str) -> Result<PageRequest<'a>, String> {
+        let page = self.page.unwrap_or(1);
+        if page == 0 {
+            return Err("page starts at 1".to_string());
+        }
+
+        let limit = self.limit.unwrap_or(DEFAULT_LIMIT);
+        if limit == 0 {
+            return Err("limit must be at least 1".to_string());
+        }
+
+        let sort_name = self.sort.as_deref().unwrap_or(default_sort);
+        let sort = keys.iter().find(|k| k.name == sort_name).ok_or_else(|| {
+            let allowed: Vec<&str> = keys.iter().map(|k| k.name).collect();
+            format!("sort must be one of: {}", allowed.join(", "))
+        })?;
+
+        let direction = match self.order.as_deref() {
+            None => sort.direction,
+            Some("asc") => SortDirection::Asc,
+            Some("desc") => SortDirection::Desc,
+            Some(_) => return Err("order must be asc or desc".to_string()),
+        };
+
+        let cursor = match self.cursor.as_deref() {
+            None => None,
+            Some(_) if self.page.is_some() => return Err("use either page or cursor, not both".to_string()),
+            Some(raw) => {
+                let cursor = Cursor::decode(raw).ok_or("invalid cursor")?;
+                if cursor.sort != sort.name || cursor.order != direction {
+                    return Err("cursor was issued for a different sort".to_string());
+                }
+                Some(cursor)
+            }
+        };
+
+        Ok(PageRequest { page, limit: limit.min(MAX_LIMIT), sort, direction, cursor })
+    }
+}
+
+impl PageRequest<'_> {
+    pub fn is_first_page(&self) -> bool {
+        self.cursor.is_none() && self.page == 1
+    }
+
+    // `, <sort value> AS sort_value, <id> AS sort_id` for the select list, read back through Keyed
+    pub fn push_sort_columns(&self, builder: &mut QueryBuilder<'_, Postgres>, id_column: &str) {
+        builder.push(format!(", ({})::text AS sort_value, {} AS sort_id", self.sort.expr, id_column));
+    }
+
+    // ` AND (<sort>, <id>) > (<cursor>)` when resuming from a cursor
+    pub fn push_cursor_filter(&self, builder: &mut QueryBuilder<'_, Postgres>, id_column: &str) {
+        if let Some(cursor) = &self.cursor {
+            builder
+                .push(format!(" AND ({}, {}) {} (CAST(", self.sort.expr, id_column, self.direction.comparison()))
+                .push_bind(cursor.value.clone())
+                .push(format!(" AS {}), ", self.sort.sql_type))
+                .push_bind(cursor.id)
+                .push(")");
+        }
+    }
+
+    // ORDER BY and LIMIT/OFFSET; fetches one extra row to tell whether there is a next page
+    pub fn push_order_and_limit(&self, builder: &mut QueryBuilder<'_, Postgres>, id_column: &str) {
+        let direction = self.direction.sql();
+        builder.push(format!(" ORDER BY {} {}, {} {}", self.sort.expr, direction, id_column, direction));
+        builder.push(" LIMIT ").push_bind(self.limit as i64 + 1);
+        if self.cursor.is_none() {
+            builder.push(" OFFSET ").push_bind((self.page as i64 - 1) * self.limit as i64);
+        }
+    }
+
+    // drop the look-ahead row and turn the last kept row into the next cursor
+    pub fn finish<T>(&self, mut rows: Vec<Keyed<T>>, total: i64) -> Paged<T> {
+        let has_more = rows.len() > self.limit as usize;
+        rows.truncate(self.limit as usize);
+
+        let next_cursor = rows.last().filter(|_| has_more).map(|last| {
+            Cursor {
+                sort: self.sort.name.to_string(),
+                order: self.direction,
+                value: last.sort_value.clone(),
+                id: last.sort_id,
+            }
+            .encode()
+        });
+
+        Paged { items: rows.into_iter().map(|r| r.row).collect(), total, next_cursor }
+    }
+}
+
+// a row plus the sort columns added by push_sort_columns
+pub struct Keyed<T> {
+    pub row: T,
+    pub sort_value: String,
+    pub sort_id: Uuid,
+}
+
+// written out because the derive doesn't handle a generic flattened field
+impl<'r, T: FromRow<'r, PgRow>> FromRow<'r, PgRow> for Keyed<T> {
+    fn from_row(row: &'r PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Keyed {
+            row: T::from_row(row)?,
+            sort_value: row.try_get("sort_value")?,
+            sort_id: row.try_get("sort_id")?,
+        })
+    }
+}
+
+pub struct Paged<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PageLinks {
+    #[serde(rename = "self")]
+    pub current: String,
+    pub next: Option<String>,
+    pub prev: Option<String>,
+}
+
+// response envelope shared by every list endpoint
+#[derive(Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub page: Option<u32>, // null when paging by cursor
+    pub limit: u32,
+    pub next_cursor: Option<String>,
+    pub links: PageLinks,
+}
+
+impl<T> Page<T> {
+    pub fn new(paged: Paged<T>, request: &PageRequest, uri: &Uri) -> Page<T> {
+        let by_cursor = request.cursor.is_some();
+
+        let next = match &paged.next_cursor {
+            Some(cursor) if by_cursor => Some(link(uri, "cursor", cursor)),
+            Some(_) => Some(link(uri, "page", &(request.page + 1).to_string())),
+            None => None,
+        };
+        let prev = (!by_cursor && request.page > 1).then(|| link(uri, "page", &(request.page - 1).to_string()));
+
+        Page {
+            items: paged.items,
+            total: paged.total,
+            page: (!by_cursor).then_some(request.page),
+            limit: request.limit,
+            next_cursor: paged.next_cursor,
+            links: PageLinks { current: uri.to_string(), next, prev },
+        }
+    }
+}
+
+// the request uri with page/cursor replaced by the given one
+fn link(uri: &Uri, key: &str, value: &str) -> String {
+    let mut pairs: Vec<&str> = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| !pair.is_empty() && !pair.starts_with("page=") && !pair.starts_with("cursor="))
+        .collect();
+
+    let replacement = format!("{}={}", key, value);
+    pairs.push(&replacement);
+
+    format!("{}?{}", uri.path(), pairs.join("&"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SORTS: &[SortKey] = &[
+        SortKey { name: "created_at", expr: "created_at", sql_type: "timestamp", direction: SortDirection::Desc },
+        SortKey { name: "name", expr: "name", sql_type: "text", direction: SortDirection::Asc },
+    ];
+
+    fn cursor(sort: &str, order: SortDirection) -> String {
+        Cursor { sort: sort.to_string(), order, value: "Lamp".to_string(), id: Uuid::nil() }.encode()
+    }
+
+    #[test]
+    fn cursors_round_trip() {
+        let encoded = cursor("name", SortDirection::Asc);
+        let decoded = Cursor::decode(&encoded).unwrap();
+
+        assert_eq!(decoded.sort, "name");
+        assert!(decoded.order == SortDirection::Asc);
+        assert_eq!(decoded.value, "Lamp");
+        assert_eq!(decoded.id, Uuid::nil());
+    }
+
+    #[test]
+    fn a_cursor_resumes_its_own_sort() {
+        let params = PageParams {
+            cursor: Some(cursor("name", SortDirection::Asc)),
+            sort: Some("name".to_string()),
+            ..Default::default()
+        };
+
+        let request = params.resolve(SORTS, "created_at").unwrap();
+
+        assert_eq!(request.sort.name, "name");
+        assert!(!request.is_first_page());
+    }
+
+    #[test]
+    fn malformed_cursors_are_rejected() {
+        for raw in ["not base64!", "bm90IGpzb24"] {
+            let params = PageParams { cursor: Some(raw.to_string()), ..Default::default() };
+            assert_eq!(params.resolve(SORTS, "created_at").err().as_deref(), Some("invalid cursor"));
+        }
+    }
+
+    #[test]
+    fn cursors_from_another_sort_are_rejected() {
+        let params = PageParams { cursor: Some(cursor("name", SortDirection::Asc)), ..Default::default() };
+        assert!(params.resolve(SORTS, "created_at").is_err());
+
+        let params = PageParams {
+            cursor: Some(cursor("name", SortDirection::Asc)),
+            sort: Some("name".to_string()),
+            order: Some("desc".to_string()),
+            ..Default::default()
+        };
+        assert!(params.resolve(SORTS, "created_at").is_err());
+    }
+
+    #[test]
+    fn unknown_sorts_are_rejected() {
+        let params = PageParams { sort: Some("price".to_string()), ..Default::default() };
+
+        assert_eq!(
+            params.resolve(SORTS, "created_at").err().as_deref(),
+            Some("sort must be one of: created_at, name"),
+        );
+    }
+
+    #[test]
+    fn pages_start_at_one() {
+        let params = PageParams { page: Some(0), ..Default::default() };
+        assert!(params.resolve(SORTS, "created_at").is_err());
+
+        let request = PageParams::default().resolve(SORTS, "created_at").unwrap();
+        assert_eq!(request.page, 1);
+        assert_eq!(request.limit, DEFAULT_LIMIT);
+        assert!(request.direction == SortDirection::Desc);
+    }
+
+    #[test]
+    fn limits_are_capped() {
+        let params = PageParams { limit: Some(MAX_LIMIT + 1), ..Default::default() };
+        assert_eq!(params.resolve(SORTS, "created_at").unwrap().limit, MAX_LIMIT);
+
+        let params = PageParams { limit: Some(0), ..Default::default() };
+        assert!(params.resolve(SORTS, "created_at").is_err());
+    }
+
+    #[test]
+    fn page_and_cursor_are_exclusive() {
+        let params = PageParams {
+            page: Some(2),
+            cursor: Some(cursor("created_at", SortDirection::Desc)),
+            ..Default::default()
+        };
+        assert!(params.resolve(SORTS, "created_at").is_err());
+    }
+
+    #[test]
+    fn finish_keeps_a_cursor_only_when_there_is_more() {
+        let params = PageParams { limit: Some(2), sort: Some("name".to_string()), ..Default::default() };
+        let request = params.resolve(SORTS, "created_at").unwrap();
+        let rows = |n: usize| -> Vec<Keyed<usize>> {
+            (0..n)
+                .map(|i| Keyed { row: i, sort_value: format!("row {}", i), sort_id: Uuid::from_u128(i as u128) })
+                .collect()
+        };
+
+        let paged = request.finish(rows(3), 3);
+        assert_eq!(paged.items, vec![0, 1]);
+        let next = Cursor::decode(&paged.next_cursor.unwrap()).unwrap();
+        assert_eq!(next.value, "row 1");
+        assert_eq!(next.id, Uuid::from_u128(1));
+
+        assert!(request.finish(rows(2), 2).next_cursor.is_none());
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use serde_json::{Map, Value};
+use sqlx::{PgConnection, PgPool, QueryBuilder};
+use uuid::Uuid;
+
+use crate::models::attribute::{AttributeDefinition, NewAttributeDefinition, TagCount};
+use crate::pagination::{Keyed, PageRequest, Paged, SortDirection, SortKey};
+use crate::services::slug::slugify;
+
+pub async fn create_definition(
+    pool: &PgPool,
+    category_id: Uuid,
+    new: NewAttributeDefinition,
+) -> Result<AttributeDefinition, sqlx::Error> {
+    sqlx::query_as::<_, AttributeDefinition>(
+        r#"
+        INSERT INTO attribute_definitions (id, category_id, key, name, kind, allowed_values, created_at)
+        SELECT $1, id, $3, $4, $5, $6, $7 FROM categories WHERE id = $2 AND deleted_at IS NULL
+        RETURNING id, category_id, key, name, kind, allowed_values, created_at
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(category_id)
+    .bind(new.key)
+    .bind(new.name)
+    .bind(new.kind)
+    .bind(new.allowed_values)
+    .bind(Utc::now().naive_utc())
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn delete_definition(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+    let result = sqlx::query("DELETE FROM attribute_definitions WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+
+    Ok(())
+}
+
+// definitions that apply in these categories: their own and those inherited from ancestors
+pub async fn applicable_definitions(
+    conn: &mut PgConnection,
+    category_ids: &[Uuid],
+) -> Result<Vec<AttributeDefinition>, sqlx::Error> {
+    sqlx::query_as::<_, AttributeDefinition>(
+        r#"
+        WITH RECURSIVE ancestors AS (
+            SELECT id, parent_id FROM categories WHERE id = ANY($1)
+            UNION
+            SELECT c.id, c.parent_id FROM categories c JOIN ancestors a ON c.id = a.parent_id
+        )
+        SELECT d.id, d.category_id, d.key, d.name, d.kind, d.allowed_values, d.created_at
+        FROM attribute_definitions d
+        WHERE d.category_id IN (SELECT id FROM ancestors)
+        ORDER BY d.key, d.created_at
+        "#,
+    )
+    .bind(category_ids)
+    .fetch_all(conn)
+    .await
+}
+
+pub const DEFINITION_SORTS: &[SortKey] = &[
+    SortKey { name: "key", expr: "d.key", sql_type: "text", direction: SortDirection::Asc },
+    SortKey { name: "created_at", expr: "d.created_at", sql_type: "timestamp", direction: SortDirection::Desc },
+];
+
+// applicable_definitions for one category, a page at a time
+pub async fn category_definitions(
+    pool: &PgPool,
+    category_id: Uuid,
+    page: &PageRequest<'_>,
+) -> Result<Paged<AttributeDefinition>, sqlx::Error> {
+    let mut builder = QueryBuilder::new("WITH RECURSIVE ancestors AS (SELECT id, parent_id FROM categories WHERE id = ");
+    builder
+        .push_bind(category_id)
+        .push(" UNION SELECT c.id, c.parent_id FROM categories c JOIN ancestors a ON c.id = a.parent_id)")
+        .push(" SELECT d.id, d.category_id, d.key, d.name, d.kind, d.allowed_values, d.created_at");
+    page.push_sort_columns(&mut builder, "d.id");
+    builder.push(" FROM attribute_definitions d WHERE d.category_id IN (SELECT id FROM ancestors)");
+    page.push_cursor_filter(&mut builder, "d.id");
+    page.push_order_and_limit(&mut builder, "d.id");
+
+    let rows = builder.build_query_as::<Keyed<AttributeDefinition>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar(
+        r#"
+        WITH RECURSIVE ancestors AS (
+            SELECT id, parent_id FROM categories WHERE id = $1
+            UNION
+            SELECT c.id, c.parent_id FROM categories c JOIN ancestors a ON c.id = a.parent_id
+        )
+        SELECT COUNT(*) FROM attribute_definitions WHERE category_id IN (SELECT id FROM ancestors)
+        "#,
+    )
+    .bind(category_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(page.finish(rows, total))
+}
+
+// every key must be defined for one of the product's categories and the value must suit
+// the definition; errors are meant for a 400 response
+pub fn validate_attributes(definitions: &[AttributeDefinition], attributes: &Map<String, Value>) -> Result<(), String> {
+    for (key, value) in attributes {
+        let mut matching = definitions.iter().filter(|d| &d.key == key).peekable();
+        if matching.peek().is_none() {
+            return Err(format!("attribute {} is not defined for the product's categories", key));
+        }
+        if !matching.any(|d| d.accepts(value)) {
+            return Err(format!("invalid value for attribute {}", key));
+        }
+    }
+
+    Ok(())
+}
+
+// tags are stored slugified so "Eco Friendly" and "eco-friendly" are one tag
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut tags: Vec<String> = tags.iter().map(|t| slugify(t)).filter(|t| !t.is_empty()).collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+pub async fn set_tags(conn: &mut PgConnection, product_id: Uuid, tags: &[String]) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM product_tags WHERE product_id = $1")
+        .bind(product_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query("INSERT INTO product_tags (product_id, tag) SELECT $1, UNNEST($2::text[])")
+        .bind(product_id)
+        .bind(normalize_tags(tags))
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn tags_for_products(pool: &PgPool, product_ids: &[Uuid]) -> Result<HashMap<Uuid, Vec<String>>, sqlx::Error> {
+    let rows: Vec<(Uuid, String)> =
+        sqlx::query_as("SELECT product_id, tag FROM product_tags WHERE product_id = ANY($1) ORDER BY tag")
+            .bind(product_ids)
+            .fetch_all(pool)
+            .await?;
+
+    let mut tags: HashMap<Uuid, Vec<String>> = HashMap::new();
+    for (product_id, tag) in rows {
+        tags.entry(product_id).or_default().push(tag);
+    }
+
+    Ok(tags)
+}
+
+pub const TAG_SORTS: &[SortKey] = &[
+    SortKey { name: "products", expr: "products", sql_type: "bigint", direction: SortDirection::Desc },
+    SortKey { name: "tag", expr: "tag", sql_type: "text", direction: SortDirection::Asc },
+];
+
+// tags have no id of their own; a uuid made from the tag breaks ties between equal sort values
+const TAG_ID: &str = "md5(tag)::uuid";
+
+// tags in use on live products, most used first
+pub async fn list_tags(pool: &PgPool, page: &PageRequest<'_>) -> Result<Paged<TagCount>, sqlx::Error> {
+    let mut builder = QueryBuilder::new("SELECT tag, products");
+    page.push_sort_columns(&mut builder, TAG_ID);
+    builder.push(
+        r#"
+        FROM (
+            SELECT t.tag, COUNT(*) AS products
+            FROM product_tags t
+            JOIN products p ON p.id = t.product_id AND p.deleted_at IS NULL
+            GROUP BY t.tag
+        ) tags
+        WHERE TRUE"#,
+    );
+    page.push_cursor_filter(&mut builder, TAG_ID);
+    page.push_order_and_limit(&mut builder, TAG_ID);
+
+    let rows = builder.build_query_as::<Keyed<TagCount>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar(
+        "SELECT COUNT(DISTINCT t.tag) FROM product_tags t JOIN products p ON p.id = t.product_id AND p.deleted_at IS NULL",
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(page.finish(rows, total))
+}
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::Utc;
+use sqlx::{Connection, PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::models::bulk::{BulkItemResult, BulkItemStatus, BulkOperation, BulkSummary};
+use crate::models::history::HistoryEntity;
+use crate::models::product::ProductQueryParams;
+use crate::models::trash::RestoreOutcome;
+use crate::services::history::{current_version, saved_version, set_actor};
+use crate::services::inventory::{set_stock_level, InventoryError};
+use crate::services::product::{check_categories, ProductError};
+use crate::services::search::export_query;
+use crate::services::trash::restore_product_in;
+
+// which products a bulk request applies to
+pub enum BulkTarget {
+    Ids(Vec<Uuid>),
+    Filter(Box<ProductQueryParams>),
+}
+
+// the request is rejected before anything runs
+pub enum BulkError {
+    TooManyProducts(usize),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for BulkError {
+    fn from(err: sqlx::Error) -> Self {
+        BulkError::Database(err)
+    }
+}
+
+const NOT_FOUND: &str = "Product not found";
+
+// every product gets its own savepoint inside one transaction, so a failure only undoes
+// that product; an atomic request rolls the whole transaction back instead. With `versions`,
+// a product edited since the version given for it fails
+pub async fn apply_bulk(
+    pool: &PgPool,
+    target: BulkTarget,
+    operations: &[BulkOperation],
+    versions: Option<&HashMap<Uuid, i32>>,
+    atomic: bool,
+    actor_id: Option<Uuid>,
+    max_products: usize,
+) -> Result<BulkSummary, BulkError> {
+    let mut tx = pool.begin().await?;
+    set_actor(&mut tx, actor_id).await?;
+
+    let ids = match target {
+        BulkTarget::Ids(mut ids) => {
+            let mut seen = std::collections::HashSet::new();
+            ids.retain(|id| seen.insert(*id));
+            ids
+        }
+        BulkTarget::Filter(params) => {
+            let mut query = export_query(&mut tx, &params, "id").await?;
+            query.push(" LIMIT ").push_bind(max_products as i64 + 1);
+            query.build_query_scalar().fetch_all(&mut *tx).await?
+        }
+    };
+
+    if ids.len() > max_products {
+        return Err(BulkError::TooManyProducts(max_products));
+    }
+
+    let mut results = Vec::with_capacity(ids.len());
+    for id in ids {
+        let expected_version = versions.and_then(|versions| versions.get(&id).copied());
+        let mut savepoint = tx.begin().await?;
+        match apply_to_product(&mut savepoint, id, expected_version, operations, actor_id).await? {
+            Ok(()) => {
+                savepoint.commit().await?;
+                let version = Some(saved_version(&mut tx, HistoryEntity::Product, id).await?);
+                results.push(BulkItemResult { id, status: BulkItemStatus::Updated, error: None, version });
+            }
+            Err(error) => {
+                savepoint.rollback().await?;
+                results.push(BulkItemResult { id, status: BulkItemStatus::Failed, error: Some(error), version: None });
+            }
+        }
+    }
+
+    let failed = results.iter().filter(|r| r.status == BulkItemStatus::Failed).count();
+    let committed = !(atomic && failed > 0);
+
+    if committed {
+        tx.commit().await?;
+    } else {
+        tx.rollback().await?;
+        for result in results.iter_mut().filter(|r| r.status == BulkItemStatus::Updated) {
+            result.status = BulkItemStatus::RolledBack;
+            result.version = None;
+        }
+    }
+
+    Ok(BulkSummary {
+        matched: results.len(),
+        updated: results.iter().filter(|r| r.status == BulkItemStatus::Updated).count(),
+        failed,
+        committed,
+        results,
+    })
+}
+
+// the inner error is the product's failure message; database errors abort the whole request
+async fn apply_to_product(
+    conn: &mut PgConnection,
+    id: Uuid,
+    expected_version: Option<i32>,
+    operations: &[BulkOperation],
+    actor_id: Option<Uuid>,
+) -> Result<Result<(), String>, sqlx::Error> {
+    if let Some(expected) = expected_version {
+        // trashed products too, so they can be restored; the lock holds off concurrent edits
+        let found: Option<Uuid> = sqlx::query_scalar("SELECT id FROM products WHERE id = $1 FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await?;
+        if found.is_none() {
+            return Ok(Err(NOT_FOUND.to_string()));
+        }
+
+        let current = current_version(conn, HistoryEntity::Product, id).await?;
+        if current != expected {
+            return Ok(Err(format!("Changed since version {}; it is now at version {}", expected, current)));
+        }
+    }
+
+    for operation in operations {
+        if let Err(error) = apply_operation(conn, id, operation, actor_id).await? {
+            return Ok(Err(error));
+        }
+    }
+
+    sqlx::query("UPDATE products SET updated_at = $1 WHERE id = $2")
+        .bind(Utc::now().naive_utc())
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(Ok(()))
+}
+
+async fn apply_operation(
+    conn: &mut PgConnection,
+    id: Uuid,
+    operation: &BulkOperation,
+    actor_id: Option<Uuid>,
+) -> Result<Result<(), String>, sqlx::Error> {
+    match operation {
+        BulkOperation::SetPrice { price } => {
+            let updated = sqlx::query("UPDATE products SET price = $1 WHERE id = $2 AND deleted_at IS NULL")
+                .bind(price)
+                .bind(id)
+                .execute(&mut *conn)
+                .await?;
+            if updated.rows_affected() == 0 {
+                return Ok(Err(NOT_FOUND.to_string()));
+            }
+        }
+        BulkOperation::AdjustPrice { percent, amount } => {
+            let price: Option<BigDecimal> = sqlx::query_scalar(
+                r#"
+                UPDATE products
+                SET price = ROUND(COALESCE(price * (100 + $1) / 100, price + $2), 2)
+                WHERE id = $3 AND deleted_at IS NULL
+                RETURNING price
+                "#,
+            )
+            .bind(percent)
+            .bind(amount)
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+            match price {
+                None => return Ok(Err(NOT_FOUND.to_string())),
+                Some(price) if price < BigDecimal::zero() => {
+                    return Ok(Err("The adjusted price would be negative".to_string()))
+                }
+                Some(_) => {}
+            }
+        }
+        BulkOperation::SetStock { stock_quantity } => {
+            let live: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM products WHERE id = $1 AND deleted_at IS NULL)")
+                .bind(id)
+                .fetch_one(&mut *conn)
+                .await?;
+            if !live {
+                return Ok(Err(NOT_FOUND.to_string()));
+            }
+
+            match set_stock_level(conn, id, None, *stock_quantity, actor_id, "bulk update").await {
+                Ok(_) => {}
+                // a decrease the default allocation can't take from any location
+                Err(InventoryError::InsufficientStock) => return Ok(Err("Not enough stock to reach this level".to_string())),
+                Err(InventoryError::NotFound) => return Ok(Err(NOT_FOUND.to_string())),
+                Err(InventoryError::Database(e)) => return Err(e),
+            }
+        }
+        BulkOperation::SetCategory { category_id } => {
+            match check_categories(conn, &[*category_id]).await {
+                Ok(()) => {}
+                Err(ProductError::Database(e)) => return Err(e),
+                Err(_) => return Ok(Err("Category not found".to_string())),
+            }
+
+            let updated = sqlx::query("UPDATE products SET category_id = $1 WHERE id = $2 AND deleted_at IS NULL")
+                .bind(category_id)
+                .bind(id)
+                .execute(&mut *conn)
+                .await?;
+            if updated.rows_affected() == 0 {
+                return Ok(Err(NOT_FOUND.to_string()));
+            }
+
+            // the new primary category stops being a secondary one
+            sqlx::query("DELETE FROM product_categories WHERE product_id = $1 AND category_id = $2")
+                .bind(id)
+                .bind(category_id)
+                .execute(&mut *conn)
+                .await?;
+        }
+        BulkOperation::SoftDelete => {
+            let updated = sqlx::query("UPDATE products SET deleted_at = $1 WHERE id = $2 AND deleted_at IS NULL")
+                .bind(Utc::now().naive_utc())
+                .bind(id)
+                .execute(&mut *conn)
+                .await?;
+            if updated.rows_affected() == 0 {
+                return Ok(Err(NOT_FOUND.to_string()));
+            }
+        }
+        BulkOperation::Restore => match restore_product_in(conn, id).await {
+            Ok(RestoreOutcome::Restored(())) => {}
+            Ok(RestoreOutcome::ParentInTrash) => {
+                return Ok(Err("The product's category is in the trash; restore it first".to_string()))
+            }
+            Err(sqlx::Error::RowNotFound) => return Ok(Err("Product is not in the trash".to_string())),
+            Err(e) => return Err(e),
+        },
+    }
+
+    Ok(Ok(()))
+}
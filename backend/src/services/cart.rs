@@ -1,5 +1,15 @@
-use crate::models::cart::{CartItem, AddToCartRequest};
-use sqlx::PgPool;
+use std::collections::HashMap;
+
+use crate::models::cart::{CartItem, AddToCartRequest, BaseCartLine, CartLine, CartSummary};
+use crate::models::promotion::AppliedDiscount;
+use crate::models::tax::{TaxAddress, TaxBreakdown, TaxLine};
+use crate::services::currency::{base_currency, Converter};
+use crate::services::pricing::pricing_for_products;
+use crate::services::promotion::discounts_for_cart;
+use crate::services::tax::{tax_calculator_from_env, taxable_lines};
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDateTime, Utc};
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 
 pub async fn add_to_cart(
@@ -10,14 +20,16 @@ pub async fn add_to_cart(
     let cart_item = sqlx::query_as!( 
         CartItem, 
         r#"
-        INSERT INTO cart_items (user_id, product_id, quantity)
-        VALUES ($1, $2, $3)
-        ON CONFLICT (user_id, product_id)
+        INSERT INTO cart_items (id, user_id, product_id, variant_id, quantity)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT ON CONSTRAINT cart_items_user_product_variant_key
         DO UPDATE SET quantity = cart_items.quantity + EXCLUDED.quantity, updated_at = now()
-        RETURNING *
+        RETURNING id, user_id, product_id, variant_id, quantity, created_at, updated_at
         "#,
+        Uuid::new_v4(),
         user_id, 
         req.product_id,
+        req.variant_id,
         req.quantity,
     )
     .fetch_one(pool)
@@ -26,30 +38,151 @@ pub async fn add_to_cart(
     Ok(cart_item)
 }
 
-pub async fn get_user_cart(
+// the user's cart at base-currency prices as of `at`: a variant's own price, else the
+// product's effective one
+pub async fn base_priced_lines(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    at: NaiveDateTime,
+) -> Result<Vec<BaseCartLine>, sqlx::Error> {
+    let items = sqlx::query_as::<_, CartItem>(
+        r#"
+        SELECT id, user_id, product_id, variant_id, quantity, created_at, updated_at
+        FROM cart_items
+        WHERE user_id = $1
+        ORDER BY created_at, id
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    price_cart_items(conn, items, at).await
+}
+
+// base-currency prices as of `at` for lines that needn't be in the cart any more, such as a
+// checkout's reserved ones
+pub async fn price_cart_items(
+    conn: &mut PgConnection,
+    items: Vec<CartItem>,
+    at: NaiveDateTime,
+) -> Result<Vec<BaseCartLine>, sqlx::Error> {
+    let product_ids: Vec<Uuid> = items.iter().map(|i| i.product_id).collect();
+    let variant_ids: Vec<Uuid> = items.iter().filter_map(|i| i.variant_id).collect();
+    let pricing = pricing_for_products(&mut *conn, &product_ids).await?;
+    let variant_prices: HashMap<Uuid, BigDecimal> = sqlx::query_as::<_, (Uuid, BigDecimal)>(
+        "SELECT id, price FROM product_variants WHERE id = ANY($1) AND price IS NOT NULL",
+    )
+    .bind(&variant_ids)
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .collect();
+
+    Ok(items
+        .into_iter()
+        .filter_map(|item| {
+            let pricing = pricing.get(&item.product_id)?;
+            let (unit_price, original_unit_price) = match item.variant_id.and_then(|id| variant_prices.get(&id)) {
+                Some(price) => (price.clone(), None),
+                None => (pricing.effective_price(at), pricing.original_price(at)),
+            };
+            Some(BaseCartLine { item, unit_price, original_unit_price })
+        })
+        .collect())
+}
+
+// the cart at today's prices with its promotions and the tax for `address` applied, in the
+// converter's currency. Unit prices, discounts and taxes are converted and rounded one by one
+// and then added up, so the lines match the prices on the product pages and the totals match
+// the lines
+pub async fn price_cart(
     pool: &PgPool,
     user_id: Uuid,
-) -> Result<Vec<CartItem>, sqlx::Error> {
-    let items = sqlx::query_as!(
-        CartItem,
-        "SELECT * FROM cart_items WHERE user_id = $1",
-        user_id
+    converter: &Converter,
+    address: Option<&TaxAddress>,
+) -> Result<CartSummary, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    let now = Utc::now().naive_utc();
+    let lines = base_priced_lines(&mut conn, user_id, now).await?;
+    let base = base_currency(&mut conn).await?;
+    let outcome = discounts_for_cart(&mut conn, user_id, &lines, now, &base, false).await?;
+    let taxable = taxable_lines(&lines, &outcome.line_discounts);
+    let tax = tax_calculator_from_env().calculate(&mut conn, address, &taxable, &base).await?;
+    let coupons: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT p.code FROM cart_coupons cc JOIN promotions p ON p.id = cc.promotion_id
+        WHERE cc.user_id = $1
+        ORDER BY cc.added_at
+        "#,
     )
-    .fetch_all(pool)
+    .bind(user_id)
+    .fetch_all(&mut *conn)
     .await?;
 
-    Ok(items)
+    let mut items = Vec::with_capacity(lines.len());
+    let mut subtotal = BigDecimal::from(0);
+    for line in lines {
+        let unit_price = converter.convert(&line.unit_price);
+        let original_unit_price =
+            line.original_unit_price.map(|p| converter.convert(&p)).filter(|p| *p > unit_price);
+        let line_total = &unit_price * BigDecimal::from(line.item.quantity);
+        subtotal += &line_total;
+        items.push(CartLine { item: line.item, unit_price, original_unit_price, line_total });
+    }
+
+    let mut discount_total = BigDecimal::from(0);
+    let discounts: Vec<AppliedDiscount> = outcome
+        .applied
+        .into_iter()
+        .map(|discount| {
+            let amount = converter.convert(&discount.amount);
+            discount_total += &amount;
+            AppliedDiscount { amount, ..discount }
+        })
+        .collect();
+
+    let mut tax_total = BigDecimal::from(0);
+    let tax_lines: Vec<TaxLine> = tax
+        .lines
+        .into_iter()
+        .map(|line| {
+            let tax_amount = converter.convert(&line.tax_amount);
+            tax_total += &tax_amount;
+            TaxLine { taxable_amount: converter.convert(&line.taxable_amount), tax_amount, ..line }
+        })
+        .collect();
+    let mut total = (&subtotal - &discount_total).max(BigDecimal::from(0));
+    if !tax.prices_include_tax {
+        total += &tax_total;
+    }
+
+    Ok(CartSummary {
+        currency: converter.code().to_string(),
+        items,
+        subtotal,
+        coupons,
+        discounts,
+        rejected_coupons: outcome.rejected,
+        discount_total,
+        free_shipping: outcome.free_shipping,
+        tax: TaxBreakdown { prices_include_tax: tax.prices_include_tax, lines: tax_lines, tax_total },
+        total,
+    })
 }
 
 pub async fn remove_from_cart(
     pool: &PgPool,
     user_id: Uuid,
     product_id: Uuid,
+    variant_id: Option<Uuid>,
 ) -> Result<u64, sqlx::Error> {
+    // without a variant every line of the product is removed
     let result = sqlx::query!(
-        "DELETE FROM cart_items WHERE user_id = $1 AND product_id = $2",
+        "DELETE FROM cart_items WHERE user_id = $1 AND product_id = $2 AND ($3::uuid IS NULL OR variant_id = $3)",
         user_id,
-        product_id
+        product_id,
+        variant_id
     )
     .execute(pool)
     .await?;
@@ -1,154 +1,485 @@
-use crate::models::category::{Category, CategoryFilter, CreateCategory, UpdateCategoryRequest};
-use axum::{extract::{Path, Query, State}, http::StatusCode, response::IntoResponse, Json};
-use sqlx::PgPool;
+use std::collections::HashMap;
+
+use crate::models::category::{Category, CategoryFilter, CategoryNode, CreateCategory, UpdateCategoryRequest};
+use crate::models::trash::{CategoryDeletion, DeleteCategoryOutcome, OrphanedProducts};
+use axum::{extract::{OriginalUri, Path, Query, State}, http::{HeaderMap, StatusCode}, response::Response, Json};
+use crate::middleware::auth::AuthMiddleware;
+use crate::etag::{if_match, with_etag};
+use crate::models::history::HistoryEntity;
+use crate::services::history::{current_version, saved_version, set_actor, Versioned};
+use crate::pagination::{Keyed, Page, PageParams, PageRequest, Paged, SortDirection, SortKey};
+use crate::services::slug::{is_valid_slug, record_slug_change, release_slug, unique_slug, SlugOwner, INVALID_SLUG};
+use sqlx::{PgPool, QueryBuilder};
 use uuid::Uuid;
 use chrono::Utc;
  
 
  // creating new category
-pub async fn create_category(pool: &PgPool, data: CreateCategory) -> Result<Category, sqlx::Error> {
+pub async fn create_category(pool: &PgPool, data: CreateCategory, actor_id: Option<Uuid>) -> Result<Category, sqlx::Error> {
     let now = Utc::now().naive_utc();
     let id = Uuid::new_v4();
+    let mut tx = pool.begin().await?;
+    set_actor(&mut tx, actor_id).await?;
+
+    let slug = match data.slug {
+        Some(slug) => {
+            release_slug(&mut tx, SlugOwner::Category, &slug).await?;
+            slug
+        }
+        None => unique_slug(&mut tx, SlugOwner::Category, &data.name).await?,
+    };
 
     let rec = sqlx::query_as!(
         Category, 
         r#"
-        INSERT INTO categories (id, name, description, created_at, updated_at)
-        VALUES ($1, $2, $3, $4, $5)
-        RETURNING id, name, description, created_at, updated_at
+        INSERT INTO categories (id, name, description, created_at, updated_at, parent_id, slug)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, name, description, created_at, updated_at, parent_id, slug
         "#,
         id,
         data.name, 
         data.description,
         now,
-        now
+        now,
+        data.parent_id,
+        slug
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await?;
 
+    tx.commit().await?;
+
     Ok(rec)
 }
 
 // listing categories
 
 
-pub async fn list_categories(pool: &PgPool) -> Result<Vec<Category>, sqlx::Error> {
-    let categories = sqlx::query_as!(
-        Category,
+pub const CATEGORY_SORTS: &[SortKey] = &[
+    SortKey { name: "name", expr: "name", sql_type: "text", direction: SortDirection::Asc },
+    SortKey {
+        name: "created_at",
+        expr: "COALESCE(created_at, 'epoch'::timestamp)",
+        sql_type: "timestamp",
+        direction: SortDirection::Desc,
+    },
+];
+
+pub async fn list_categories(pool: &PgPool, page: &PageRequest<'_>) -> Result<Paged<Category>, sqlx::Error> {
+    let mut builder = QueryBuilder::new("SELECT id, name, description, created_at, updated_at, parent_id, slug");
+    page.push_sort_columns(&mut builder, "id");
+    builder.push(" FROM categories WHERE deleted_at IS NULL");
+    page.push_cursor_filter(&mut builder, "id");
+    page.push_order_and_limit(&mut builder, "id");
+
+    let rows = builder.build_query_as::<Keyed<Category>>().fetch_all(pool).await?;
+
+    let total = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM categories WHERE deleted_at IS NULL"#)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(page.finish(rows, total))
+}
+
+// nest a flat list of categories under their parents, children sorted by name
+fn build_tree(categories: Vec<Category>, roots: impl Fn(&Category) -> bool) -> Vec<CategoryNode> {
+    let mut top = Vec::new();
+    let mut by_parent: HashMap<Uuid, Vec<Category>> = HashMap::new();
+    for category in categories {
+        match category.parent_id {
+            _ if roots(&category) => top.push(category),
+            Some(parent_id) => by_parent.entry(parent_id).or_default().push(category),
+            None => {}
+        }
+    }
+
+    fn attach(category: Category, by_parent: &mut HashMap<Uuid, Vec<Category>>) -> CategoryNode {
+        let children = by_parent.remove(&category.id).unwrap_or_default();
+        CategoryNode {
+            children: children.into_iter().map(|child| attach(child, by_parent)).collect(),
+            category,
+        }
+    }
+
+    top.into_iter().map(|category| attach(category, &mut by_parent)).collect()
+}
+
+// every live category reachable from the top level (or from `root`), as a tree;
+// the subtree of a deleted category is left out
+pub async fn category_tree(pool: &PgPool, root: Option<Uuid>) -> Result<Vec<CategoryNode>, sqlx::Error> {
+    let categories = sqlx::query_as::<_, Category>(
         r#"
-        SELECT id, name, description, created_at, updated_at
-        FROM categories
-        WHERE deleted_at IS NULL
-        ORDER BY created_at DESC 
-        "#
+        WITH RECURSIVE tree AS (
+            SELECT id, name, description, created_at, updated_at, parent_id, slug
+            FROM categories
+            WHERE deleted_at IS NULL
+              AND CASE WHEN $1::uuid IS NULL THEN parent_id IS NULL ELSE id = $1 END
+            UNION ALL
+            SELECT c.id, c.name, c.description, c.created_at, c.updated_at, c.parent_id, c.slug
+            FROM categories c
+            JOIN tree t ON c.parent_id = t.id
+            WHERE c.deleted_at IS NULL
+        )
+        SELECT id, name, description, created_at, updated_at, parent_id, slug
+        FROM tree
+        ORDER BY name
+        "#,
     )
+    .bind(root)
     .fetch_all(pool)
     .await?;
 
-    Ok(categories)
+    Ok(build_tree(categories, |category| match root {
+        Some(root) => category.id == root,
+        None => category.parent_id.is_none(),
+    }))
+}
+
+// ancestors of a category from the top level down, ending with the category itself
+pub async fn category_breadcrumbs(pool: &PgPool, id: Uuid) -> Result<Vec<Category>, sqlx::Error> {
+    sqlx::query_as::<_, Category>(
+        r#"
+        WITH RECURSIVE path AS (
+            SELECT id, name, description, created_at, updated_at, parent_id, slug, 0 AS depth
+            FROM categories
+            WHERE id = $1 AND deleted_at IS NULL
+            UNION ALL
+            SELECT c.id, c.name, c.description, c.created_at, c.updated_at, c.parent_id, c.slug, p.depth + 1
+            FROM categories c
+            JOIN path p ON c.id = p.parent_id
+        )
+        SELECT id, name, description, created_at, updated_at, parent_id, slug
+        FROM path
+        ORDER BY depth DESC
+        "#,
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await
 }
 
-//delete category 
-pub async fn soft_delete_category(pool: &PgPool, category_id: Uuid) -> Result<(), sqlx::Error> {
-    let now = Utc::now();
-    let query = "
+// re-parent a category together with its subtree; the database trigger rejects cycles
+pub async fn move_category(
+    pool: &PgPool,
+    id: Uuid,
+    parent_id: Option<Uuid>,
+    expected_version: Option<i32>,
+    actor_id: Option<Uuid>,
+) -> Result<Versioned<Category>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    set_actor(&mut tx, actor_id).await?;
+
+    // the cycle trigger's lock before the row's, in the same order as a delete takes them
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext('categories_tree'))")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("SELECT id FROM categories WHERE id = $1 AND deleted_at IS NULL FOR UPDATE")
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if let Some(expected) = expected_version {
+        if current_version(&mut tx, HistoryEntity::Category, id).await? != expected {
+            return Ok(Versioned::Stale);
+        }
+    }
+
+    let category = sqlx::query_as::<_, Category>(
+        r#"
         UPDATE categories
-        SET deleted_at = $1
-        WHERE id = $2 AND deleted_at IS NULL
-    ";
+        SET parent_id = $1, updated_at = $2
+        WHERE id = $3
+          AND ($1::uuid IS NULL OR EXISTS (SELECT 1 FROM categories WHERE id = $1 AND deleted_at IS NULL))
+        RETURNING id, name, description, created_at, updated_at, parent_id, slug
+        "#,
+    )
+    .bind(parent_id)
+    .bind(Utc::now().naive_utc())
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+    let version = saved_version(&mut tx, HistoryEntity::Category, id).await?;
 
-    let result = sqlx::query(query)
-        .bind(now)
-        .bind(category_id)
-        .execute(pool)
+    tx.commit().await?;
+
+    Ok(Versioned::Saved(category, version))
+}
+
+//delete category: moves it and its live subcategories to the trash under one timestamp,
+// so restoring it brings back exactly what went with it
+pub async fn soft_delete_category(
+    pool: &PgPool,
+    category_id: Uuid,
+    products: OrphanedProducts,
+    expected_version: Option<i32>,
+    actor_id: Option<Uuid>,
+) -> Result<DeleteCategoryOutcome, sqlx::Error> {
+    let now = Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+    set_actor(&mut tx, actor_id).await?;
+
+    // same lock the cycle trigger takes, so nothing is moved into the subtree meanwhile
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext('categories_tree'))")
+        .execute(&mut *tx)
         .await?;
 
-    if result.rows_affected() == 0 {
-        return Err(sqlx::Error::RowNotFound);
+    let parent_id: Option<Uuid> =
+        sqlx::query_scalar("SELECT parent_id FROM categories WHERE id = $1 AND deleted_at IS NULL FOR UPDATE")
+            .bind(category_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)?;
+    if let Some(expected) = expected_version {
+        if current_version(&mut tx, HistoryEntity::Category, category_id).await? != expected {
+            return Ok(DeleteCategoryOutcome::VersionMismatch);
+        }
     }
 
-    Ok(())
+    let subtree: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        WITH RECURSIVE subtree AS (
+            SELECT id FROM categories WHERE id = $1
+            UNION ALL
+            SELECT c.id FROM categories c JOIN subtree s ON c.parent_id = s.id
+            WHERE c.deleted_at IS NULL
+        )
+        SELECT id FROM subtree
+        "#,
+    )
+    .bind(category_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut deletion = CategoryDeletion { deleted_at: now, categories: 0, products_trashed: 0, products_detached: 0 };
+
+    match products {
+        OrphanedProducts::Reject => {
+            let count: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM products WHERE category_id = ANY($1) AND deleted_at IS NULL",
+            )
+            .bind(&subtree)
+            .fetch_one(&mut *tx)
+            .await?;
+            if count > 0 {
+                return Ok(DeleteCategoryOutcome::HasProducts(count));
+            }
+        }
+        OrphanedProducts::Detach => {
+            deletion.products_detached = sqlx::query(
+                "UPDATE products SET category_id = $1, updated_at = $2 WHERE category_id = ANY($3) AND deleted_at IS NULL",
+            )
+            .bind(parent_id)
+            .bind(now)
+            .bind(&subtree)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+        OrphanedProducts::Trash => {
+            deletion.products_trashed = sqlx::query(
+                "UPDATE products SET deleted_at = $1 WHERE category_id = ANY($2) AND deleted_at IS NULL",
+            )
+            .bind(now)
+            .bind(&subtree)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+    }
+
+    deletion.categories = sqlx::query("UPDATE categories SET deleted_at = $1 WHERE id = ANY($2) AND deleted_at IS NULL")
+        .bind(now)
+        .bind(&subtree)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    tx.commit().await?;
+
+    Ok(DeleteCategoryOutcome::Deleted(deletion))
 }
 
 
-// get category by id 
-pub async fn get_category_by_id_handler(
-    State(pool): State<PgPool>,
-    Path(id): Path<Uuid>,
-) -> Result<Json<Category>, (StatusCode, String)> {
-    let result = sqlx::query_as!(
+// a live category with its version, for reads and 412 responses
+pub async fn category_representation(pool: &PgPool, id: Uuid) -> Result<(Category, i32), sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    let category = sqlx::query_as!(
         Category,
-        r#" 
-        SELECT id, name, description, created_at, updated_at
+        r#"
+        SELECT id, name, description, created_at, updated_at, parent_id, slug
         FROM categories
-        WHERE id = $1
+        WHERE id = $1 AND deleted_at IS NULL
         "#,
         id
     )
-    .fetch_one(&pool)
-    .await;
+    .fetch_one(&mut *conn)
+    .await?;
+    let version = current_version(&mut conn, HistoryEntity::Category, id).await?;
 
-    match result {
-        Ok(category) => Ok(Json(category)),
+    Ok((category, version))
+}
+
+// get category by id 
+pub async fn get_category_by_id_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Response, (StatusCode, String)> {
+    match category_representation(&pool, id).await {
+        Ok((category, version)) => Ok(with_etag(version, StatusCode::OK, Json(category))),
         Err(sqlx::Error::RowNotFound) => Err((StatusCode::NOT_FOUND, "Category not found".to_string())),
         Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e))),
     }
 }
 
-// update category info 
+// update category info; If-Match must carry the ETag the edit was based on
 pub async fn update_category_handler(
     Path(id): Path<Uuid>,
     State(pool): State<PgPool>,
+    auth: Option<AuthMiddleware>,
+    headers: HeaderMap,
     Json(payload): Json<UpdateCategoryRequest>,
-) -> Result<Json<String>, (StatusCode, String)> {
-    let result = sqlx::query!(
+) -> Result<Response, (StatusCode, String)> {
+    if payload.slug.as_deref().is_some_and(|slug| !is_valid_slug(slug)) {
+        return Err((StatusCode::BAD_REQUEST, INVALID_SLUG.into()));
+    }
+
+    let expected_version = if_match(&headers)?;
+    let result = update_category(&pool, id, payload, expected_version, auth.and_then(|a| a.user_id())).await;
+
+    match result {
+        Ok(CategoryUpdate::Updated(version)) => {
+            Ok(with_etag(version, StatusCode::OK, Json("Category updated successfully".to_string())))
+        }
+        // someone else saved first: send what they saved
+        Ok(CategoryUpdate::VersionMismatch) => {
+            let (category, version) = category_representation(&pool, id)
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e)))?;
+            Ok(with_etag(version, StatusCode::PRECONDITION_FAILED, Json(category)))
+        }
+        Ok(CategoryUpdate::NotFound) => Err((StatusCode::NOT_FOUND, "Category not found".into())),
+        Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("23505") => {
+            Err((StatusCode::CONFLICT, "Slug or name is already in use".into()))
+        }
+        Err(err) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update: {}", err))),
+    }
+}
+
+enum CategoryUpdate {
+    Updated(i32), // with the version the edit became
+    NotFound,
+    VersionMismatch, // edited since the version the caller read
+}
+
+// a changed slug leaves the old one redirecting
+async fn update_category(
+    pool: &PgPool,
+    id: Uuid,
+    payload: UpdateCategoryRequest,
+    expected_version: Option<i32>,
+    actor_id: Option<Uuid>,
+) -> Result<CategoryUpdate, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    set_actor(&mut tx, actor_id).await?;
+
+    let current: Option<String> =
+        sqlx::query_scalar("SELECT slug FROM categories WHERE id = $1 AND deleted_at IS NULL FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?;
+    let Some(current) = current else {
+        return Ok(CategoryUpdate::NotFound);
+    };
+
+    // checked under the row lock, so a concurrent edit has committed its version by now
+    if let Some(expected) = expected_version {
+        if current_version(&mut tx, HistoryEntity::Category, id).await? != expected {
+            return Ok(CategoryUpdate::VersionMismatch);
+        }
+    }
+
+    if let Some(slug) = &payload.slug {
+        record_slug_change(&mut tx, SlugOwner::Category, id, &current, slug).await?;
+    }
+
+    sqlx::query!(
         r#"
         UPDATE categories
         SET name = COALESCE($1, name), 
             description = COALESCE($2, description),
+            slug = COALESCE($4, slug),
             updated_at = NOW()
-        WHERE id = $3 AND deleted_at IS NULL
+        WHERE id = $3
         "#,
         payload.name,
         payload.description,
-        id
+        id,
+        payload.slug
     )
-    .execute(&pool)
-    .await;
+    .execute(&mut *tx)
+    .await?;
+    let version = saved_version(&mut tx, HistoryEntity::Category, id).await?;
 
-    match result {
-        Ok(res) if res.rows_affected() == 1 => Ok(Json("Category updated successfully".to_string())),
-        Ok(_) => Err((StatusCode::NOT_FOUND, "Category not found".into())),
-        Err(err) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update: {}", err))),
-    }
+    tx.commit().await?;
+
+    Ok(CategoryUpdate::Updated(version))
 }
 
 // search category by name 
 pub async fn filter_categories_handler(
     Query(filter): Query<CategoryFilter>,
+    Query(params): Query<PageParams>,
+    OriginalUri(uri): OriginalUri,
     State(pool): State<PgPool>, // ✅ wrap the pool in State
-) -> Result<Json<Vec<Category>>, (StatusCode, String)> {
-    let name_filter = filter.name.unwrap_or_default();
+) -> Result<Json<Page<Category>>, (StatusCode, String)> {
+    let request = params
+        .resolve(CATEGORY_SORTS, "name")
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let name_filter = format!("%{}%", filter.name.unwrap_or_default());
 
-    let categories = sqlx::query_as!( 
-        Category,
-        r#"
-        SELECT id, name, description, created_at, updated_at
-        FROM categories
-        WHERE is_deleted = false AND name ILIKE $1
-        "#,
-        format!("%{}%", name_filter) 
+    let db_error = |err: sqlx::Error| {
+        eprintln!("DB error: {:?}", err);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+    };
+
+    let mut builder = QueryBuilder::new("SELECT id, name, description, created_at, updated_at, parent_id, slug");
+    request.push_sort_columns(&mut builder, "id");
+    builder
+        .push(" FROM categories WHERE deleted_at IS NULL AND name ILIKE ")
+        .push_bind(name_filter.clone());
+    request.push_cursor_filter(&mut builder, "id");
+    request.push_order_and_limit(&mut builder, "id");
+
+    let rows = builder
+        .build_query_as::<Keyed<Category>>()
+        .fetch_all(&pool)
+        .await
+        .map_err(db_error)?;
+
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM categories WHERE deleted_at IS NULL AND name ILIKE $1"#,
+        name_filter
     )
-    .fetch_all(&pool) 
+    .fetch_one(&pool)
     .await
-    .map_err(|err| {
-        eprintln!("DB error: {:?}", err);
-        (StatusCode::INTERNAL_SERVER_ERROR, "Database error".into())
-    })?;
+    .map_err(db_error)?;
 
-    if categories.is_empty() {
+    if total == 0 {
         return Err((StatusCode::NOT_FOUND, "No categories found".into()));
     }
 
-    Ok(Json(categories))
+    Ok(Json(Page::new(request.finish(rows, total), &request, &uri)))
+}
+
+// a live category by its current slug
+pub async fn category_by_slug(pool: &PgPool, slug: &str) -> Result<Option<Category>, sqlx::Error> {
+    sqlx::query_as::<_, Category>(
+        r#"
+        SELECT id, name, description, created_at, updated_at, parent_id, slug
+        FROM categories
+        WHERE slug = $1 AND deleted_at IS NULL
+        "#,
+    )
+    .bind(slug)
+    .fetch_optional(pool)
+    .await
 }
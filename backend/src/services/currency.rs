@@ -0,0 +1,235 @@
+use bigdecimal::{BigDecimal, One, Signed, Zero};
+use sqlx::{PgConnection, PgPool, QueryBuilder};
+use uuid::Uuid;
+
+use crate::config::store_currency;
+use crate::models::currency::{Currency, CurrencyRate, ExchangeRate, RoundingMode, UpsertCurrency};
+use crate::pagination::{Keyed, PageRequest, Paged, SortDirection, SortKey};
+
+#[derive(Debug)]
+pub enum CurrencyError {
+    Unknown(String), // not set up, or disabled
+    NoRate(String),  // set up, but no exchange rate yet
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for CurrencyError {
+    fn from(err: sqlx::Error) -> Self {
+        CurrencyError::Database(err)
+    }
+}
+
+// turns base-currency amounts into one currency, rounded by that currency's rules;
+// every price in a response goes through the same converter so totals add up
+#[derive(Clone)]
+pub struct Converter {
+    pub currency: Currency,
+    rate: BigDecimal,
+}
+
+impl Converter {
+    pub fn code(&self) -> &str {
+        &self.currency.code
+    }
+
+    pub fn convert(&self, amount: &BigDecimal) -> BigDecimal {
+        round(&(amount * &self.rate), &self.currency)
+    }
+
+    // the base-currency amount that converts to `amount`, unrounded so it can be compared
+    // with stored prices; rates are always positive
+    pub fn to_base(&self, amount: &BigDecimal) -> BigDecimal {
+        amount / &self.rate
+    }
+}
+
+// rounds to the currency's increment, or its smallest unit when it has none
+pub fn round(amount: &BigDecimal, currency: &Currency) -> BigDecimal {
+    let decimals = i64::from(currency.decimals);
+    let increment = match &currency.rounding_increment {
+        Some(increment) => increment.clone(),
+        None => BigDecimal::new(1.into(), decimals),
+    };
+
+    let steps = amount.abs() / &increment;
+    let whole = steps.with_scale(0); // truncated
+    let fraction = &steps - &whole;
+    let half = BigDecimal::new(5.into(), 1);
+    let round_up = match currency.rounding {
+        RoundingMode::Down => false,
+        RoundingMode::Up => !fraction.is_zero(),
+        RoundingMode::HalfUp => fraction >= half,
+        RoundingMode::HalfEven => fraction > half || (fraction == half && &whole % BigDecimal::from(2) != BigDecimal::zero()),
+    };
+    let whole = if round_up { whole + BigDecimal::one() } else { whole };
+
+    let rounded = (whole * increment).with_scale(decimals);
+    if amount.is_negative() {
+        -rounded
+    } else {
+        rounded
+    }
+}
+
+// rules for the base currency when it hasn't been set up in the currencies table
+fn default_currency(code: &str) -> Currency {
+    Currency {
+        code: code.to_string(),
+        name: code.to_string(),
+        symbol: None,
+        decimals: 2,
+        rounding_increment: None,
+        rounding: RoundingMode::HalfUp,
+        enabled: true,
+    }
+}
+
+// the base currency's rounding rules
+pub async fn base_currency(conn: &mut PgConnection) -> Result<Currency, sqlx::Error> {
+    let base = store_currency();
+    let currency = sqlx::query_as::<_, Currency>(
+        "SELECT code, name, symbol, decimals, rounding_increment, rounding, enabled FROM currencies WHERE code = $1",
+    )
+    .bind(&base)
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(currency.unwrap_or_else(|| default_currency(&base)))
+}
+
+// converter for `code`, or for the base currency when it's None
+pub async fn converter_for(pool: &PgPool, code: Option<&str>) -> Result<Converter, CurrencyError> {
+    let base = store_currency();
+    let code = code.map(|c| c.trim().to_uppercase()).unwrap_or_else(|| base.clone());
+
+    let row: Option<CurrencyRate> = sqlx::query_as(
+        r#"
+        SELECT c.code, c.name, c.symbol, c.decimals, c.rounding_increment, c.rounding, c.enabled,
+               c.code = $2 AS base, r.rate, r.source AS rate_source, r.updated_at AS rate_updated_at
+        FROM currencies c
+        LEFT JOIN exchange_rates r ON r.currency = c.code
+        WHERE c.code = $1
+        "#,
+    )
+    .bind(&code)
+    .bind(&base)
+    .fetch_optional(pool)
+    .await?;
+
+    if code == base {
+        let currency = row.map(|row| row.currency).unwrap_or_else(|| default_currency(&base));
+        return Ok(Converter { currency, rate: BigDecimal::one() });
+    }
+
+    match row {
+        Some(row) if row.currency.enabled => match row.rate {
+            Some(rate) => Ok(Converter { currency: row.currency, rate }),
+            None => Err(CurrencyError::NoRate(code)),
+        },
+        _ => Err(CurrencyError::Unknown(code)),
+    }
+}
+
+pub const CURRENCY_SORTS: &[SortKey] = &[
+    // the base currency first, then by code
+    SortKey {
+        name: "code",
+        expr: "CASE WHEN base THEN '' ELSE code END",
+        sql_type: "text",
+        direction: SortDirection::Asc,
+    },
+    SortKey { name: "name", expr: "name", sql_type: "text", direction: SortDirection::Asc },
+];
+
+const CURRENCY_ID: &str = "md5(code)::uuid";
+
+pub async fn list_currencies(
+    pool: &PgPool,
+    include_disabled: bool,
+    page: &PageRequest<'_>,
+) -> Result<Paged<CurrencyRate>, sqlx::Error> {
+    let mut builder = QueryBuilder::new(
+        "SELECT code, name, symbol, decimals, rounding_increment, rounding, enabled, base, \
+         CASE WHEN base THEN 1 ELSE rate END AS rate, rate_source, rate_updated_at",
+    );
+    page.push_sort_columns(&mut builder, CURRENCY_ID);
+    builder
+        .push(
+            r#"
+        FROM (
+            SELECT c.code, c.name, c.symbol, c.decimals, c.rounding_increment, c.rounding, c.enabled,
+                   c.code = "#,
+        )
+        .push_bind(store_currency())
+        .push(
+            r#" AS base,
+                   r.rate, r.source AS rate_source, r.updated_at AS rate_updated_at
+            FROM currencies c
+            LEFT JOIN exchange_rates r ON r.currency = c.code
+            WHERE c.enabled OR "#,
+        )
+        .push_bind(include_disabled)
+        .push(
+            r#"
+        ) cur
+        WHERE TRUE"#,
+        );
+    page.push_cursor_filter(&mut builder, CURRENCY_ID);
+    page.push_order_and_limit(&mut builder, CURRENCY_ID);
+
+    let rows = builder.build_query_as::<Keyed<CurrencyRate>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM currencies WHERE enabled OR $1")
+        .bind(include_disabled)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(page.finish(rows, total))
+}
+
+pub async fn upsert_currency(pool: &PgPool, code: &str, currency: &UpsertCurrency) -> Result<Currency, sqlx::Error> {
+    sqlx::query_as::<_, Currency>(
+        r#"
+        INSERT INTO currencies (code, name, symbol, decimals, rounding_increment, rounding, enabled)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (code) DO UPDATE
+        SET name = EXCLUDED.name, symbol = EXCLUDED.symbol, decimals = EXCLUDED.decimals,
+            rounding_increment = EXCLUDED.rounding_increment, rounding = EXCLUDED.rounding,
+            enabled = EXCLUDED.enabled, updated_at = now()
+        RETURNING code, name, symbol, decimals, rounding_increment, rounding, enabled
+        "#,
+    )
+    .bind(code)
+    .bind(&currency.name)
+    .bind(&currency.symbol)
+    .bind(currency.decimals)
+    .bind(&currency.rounding_increment)
+    .bind(currency.rounding.unwrap_or(RoundingMode::HalfUp))
+    .bind(currency.enabled.unwrap_or(true))
+    .fetch_one(pool)
+    .await
+}
+
+// RowNotFound when the currency isn't set up
+pub async fn set_rate(
+    conn: &mut PgConnection,
+    code: &str,
+    rate: &BigDecimal,
+    source: &str,
+    updated_by: Option<Uuid>,
+) -> Result<ExchangeRate, sqlx::Error> {
+    sqlx::query_as::<_, ExchangeRate>(
+        r#"
+        INSERT INTO exchange_rates (currency, rate, source, updated_by, updated_at)
+        SELECT code, $2, $3, $4, now() FROM currencies WHERE code = $1
+        ON CONFLICT (currency) DO UPDATE
+        SET rate = EXCLUDED.rate, source = EXCLUDED.source, updated_by = EXCLUDED.updated_by, updated_at = EXCLUDED.updated_at
+        RETURNING currency, rate, source, updated_by, updated_at
+        "#,
+    )
+    .bind(code)
+    .bind(rate)
+    .bind(source)
+    .bind(updated_by)
+    .fetch_one(conn)
+    .await
+}
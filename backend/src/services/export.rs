@@ -0,0 +1,221 @@
+use std::io;
+
+use axum::body::Bytes;
+use chrono::Utc;
+use futures_util::TryStreamExt;
+use sqlx::{PgPool, Postgres, QueryBuilder, Transaction};
+use tokio::sync::mpsc;
+
+use crate::config::{store_currency, store_public_url};
+use crate::models::export::{ExportFormat, ExportRow};
+use crate::models::product::ProductQueryParams;
+use crate::services::search::export_query;
+
+// flush to the client once this much has been written
+const CHUNK_SIZE: usize = 16 * 1024;
+
+const EXPORT_COLUMNS: &str = "id, sku, name, slug, description, price, sale_price, sale_starts_at, sale_ends_at, stock_quantity, \
+    (SELECT c.name FROM categories c WHERE c.id = products.category_id AND c.deleted_at IS NULL) AS category, \
+    image AS image_url, \
+    ARRAY(SELECT t.tag FROM product_tags t WHERE t.product_id = products.id ORDER BY t.tag) AS tags, \
+    attributes, updated_at";
+
+const CSV_HEADER: [&str; 14] = [
+    "id", "sku", "name", "slug", "description", "price", "currency", "stock_quantity", "category", "image_url",
+    "link", "tags", "attributes", "updated_at",
+];
+
+pub type ExportChunks = mpsc::Receiver<Result<Bytes, io::Error>>;
+
+// resolves the filters up front so a bad database is a 500 rather than a truncated file,
+// then streams the rows from a cursor in the background, a chunk at a time
+pub async fn start_export(
+    pool: &PgPool,
+    params: &ProductQueryParams,
+    format: ExportFormat,
+) -> Result<ExportChunks, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let query = export_query(&mut tx, params, EXPORT_COLUMNS).await?;
+
+    // a couple of chunks in flight; a slow client holds the cursor rather than filling memory
+    let (sender, receiver) = mpsc::channel(2);
+
+    tokio::spawn(async move {
+        if let Err(e) = write_export(tx, query, format, &sender).await {
+            eprintln!("❌ Product export failed: {:?}", e);
+            let _ = sender.send(Err(io::Error::other("export failed"))).await;
+        }
+    });
+
+    Ok(receiver)
+}
+
+async fn write_export(
+    mut tx: Transaction<'static, Postgres>,
+    mut query: QueryBuilder<'static, Postgres>,
+    format: ExportFormat,
+    sender: &mpsc::Sender<Result<Bytes, io::Error>>,
+) -> Result<(), sqlx::Error> {
+    let base_url = store_public_url();
+    let currency = store_currency();
+    let mut buf = Vec::with_capacity(CHUNK_SIZE * 2);
+
+    match format {
+        ExportFormat::Csv => push_csv_record(&mut buf, &CSV_HEADER),
+        ExportFormat::Ndjson => {}
+        ExportFormat::Xml => push_feed_header(&mut buf, &base_url),
+    }
+
+    let mut rows = query.build_query_as::<ExportRow>().fetch(&mut *tx);
+
+    while let Some(mut row) = rows.try_next().await? {
+        row.link = format!("{}/products/{}", base_url, row.slug);
+        row.image_url = row.image_url.filter(|url| !url.is_empty()).map(|url| absolute_url(&base_url, url));
+
+        match format {
+            ExportFormat::Csv => push_csv_row(&mut buf, &row, &currency),
+            ExportFormat::Ndjson => {
+                serde_json::to_writer(&mut buf, &row).expect("export rows always serialize");
+                buf.push(b'\n');
+            }
+            ExportFormat::Xml => push_feed_item(&mut buf, &row, &currency),
+        }
+
+        if buf.len() >= CHUNK_SIZE && !send_chunk(sender, &mut buf).await {
+            return Ok(()); // client went away
+        }
+    }
+
+    if let ExportFormat::Xml = format {
+        buf.extend_from_slice(b"</channel>\n</rss>\n");
+    }
+
+    send_chunk(sender, &mut buf).await;
+    Ok(())
+}
+
+async fn send_chunk(sender: &mpsc::Sender<Result<Bytes, io::Error>>, buf: &mut Vec<u8>) -> bool {
+    if buf.is_empty() {
+        return true;
+    }
+
+    let chunk = Bytes::from(std::mem::replace(buf, Vec::with_capacity(CHUNK_SIZE * 2)));
+    sender.send(Ok(chunk)).await.is_ok()
+}
+
+// images may be stored as paths relative to the storefront
+fn absolute_url(base_url: &str, url: String) -> String {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        url
+    } else {
+        format!("{}/{}", base_url, url.trim_start_matches('/'))
+    }
+}
+
+fn push_csv_record(buf: &mut Vec<u8>, fields: &[&str]) {
+    let mut writer = csv::Writer::from_writer(buf);
+    writer.write_record(fields).expect("writing to memory can't fail");
+    writer.flush().expect("writing to memory can't fail");
+}
+
+// tags are `|`-separated as in imports; attributes are written as a JSON object
+fn push_csv_row(buf: &mut Vec<u8>, row: &ExportRow, currency: &str) {
+    let price = row.price.with_scale(2).to_string();
+    let stock = row.stock_quantity.to_string();
+    let tags = row.tags.join("|");
+    let attributes = row.attributes.to_string();
+    let updated_at = row.updated_at.map(|t| t.to_string()).unwrap_or_default();
+    let id = row.id.to_string();
+
+    push_csv_record(
+        buf,
+        &[
+            &id,
+            row.sku.as_deref().unwrap_or_default(),
+            &row.name,
+            &row.slug,
+            row.description.as_deref().unwrap_or_default(),
+            &price,
+            currency,
+            &stock,
+            row.category.as_deref().unwrap_or_default(),
+            row.image_url.as_deref().unwrap_or_default(),
+            &row.link,
+            &tags,
+            &attributes,
+            &updated_at,
+        ],
+    );
+}
+
+fn push_feed_header(buf: &mut Vec<u8>, base_url: &str) {
+    buf.extend_from_slice(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    buf.extend_from_slice(b"<rss version=\"2.0\" xmlns:g=\"http://base.google.com/ns/1.0\">\n<channel>\n");
+    buf.extend_from_slice(b"<title>Easy Buy</title>\n");
+    push_element(buf, "link", base_url);
+    buf.extend_from_slice(b"<description>Easy Buy product feed</description>\n");
+}
+
+// one <item> with Google Merchant Center fields
+fn push_feed_item(buf: &mut Vec<u8>, row: &ExportRow, currency: &str) {
+    buf.extend_from_slice(b"<item>\n");
+    push_element(buf, "g:id", row.sku.as_deref().unwrap_or(&row.id.to_string()));
+    push_element(buf, "title", &row.name);
+    push_element(buf, "description", row.description.as_deref().unwrap_or(&row.name));
+    push_element(buf, "link", &row.link);
+    if let Some(image_url) = &row.image_url {
+        push_element(buf, "g:image_link", image_url);
+    }
+    push_element(buf, "g:price", &format!("{} {}", row.price.with_scale(2), currency));
+    push_feed_sale(buf, row, currency);
+    push_element(buf, "g:availability", if row.stock_quantity > 0 { "in_stock" } else { "out_of_stock" });
+    if let Some(category) = &row.category {
+        push_element(buf, "g:product_type", category);
+    }
+    if let Some(brand) = row.attributes.get("brand").and_then(|b| b.as_str()) {
+        push_element(buf, "g:brand", brand);
+    }
+    push_element(buf, "g:condition", "new");
+    buf.extend_from_slice(b"</item>\n");
+}
+
+// a sale that's on now, or a bounded one still to come; without dates Merchant Center
+// treats the sale price as current, so an open-ended future sale waits until it starts
+fn push_feed_sale(buf: &mut Vec<u8>, row: &ExportRow, currency: &str) {
+    let Some(sale_price) = &row.sale_price else { return };
+    let now = Utc::now().naive_utc();
+    if row.sale_ends_at.is_some_and(|end| end <= now) {
+        return;
+    }
+
+    match (row.sale_starts_at, row.sale_ends_at) {
+        (Some(start), Some(end)) => {
+            push_element(buf, "g:sale_price", &format!("{} {}", sale_price.with_scale(2), currency));
+            let period = format!("{}Z/{}Z", start.format("%Y-%m-%dT%H:%M:%S"), end.format("%Y-%m-%dT%H:%M:%S"));
+            push_element(buf, "g:sale_price_effective_date", &period);
+        }
+        (Some(start), None) if start > now => {}
+        _ => push_element(buf, "g:sale_price", &format!("{} {}", sale_price.with_scale(2), currency)),
+    }
+}
+
+fn push_element(buf: &mut Vec<u8>, name: &str, text: &str) {
+    buf.extend_from_slice(format!("<{}>{}</{}>\n", name, xml_escape(text), name).as_bytes());
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // control characters aren't allowed in XML 1.0
+            c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,197 @@
+use chrono::Utc;
+use serde_json::Value;
+use sqlx::{PgConnection, PgPool, QueryBuilder};
+use uuid::Uuid;
+
+use crate::models::history::{ChangeRecord, HistoryEntity};
+use crate::models::product::Product;
+use crate::pagination::{Keyed, PageRequest, Paged, SortDirection, SortKey};
+use crate::services::attribute::set_tags;
+use crate::services::product::{check_categories, set_secondary_categories, ProductError};
+use crate::services::slug::{record_slug_change, SlugOwner};
+
+pub const HISTORY_SORTS: &[SortKey] =
+    &[SortKey { name: "version", expr: "version", sql_type: "integer", direction: SortDirection::Desc }];
+
+// an edit made against the version the caller read
+pub enum Versioned<T> {
+    Saved(T, i32), // with the version the edit became
+    Stale,         // edited since that version; nothing was saved
+}
+
+#[derive(Debug)]
+pub enum RevertError {
+    NotFound, // the product or the version
+    InTrash,
+    VersionMismatch, // edited since the version the caller read
+    CategoryNotFound, // the version's categories have been deleted since
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for RevertError {
+    fn from(err: sqlx::Error) -> Self {
+        match err {
+            sqlx::Error::RowNotFound => RevertError::NotFound,
+            err => RevertError::Database(err),
+        }
+    }
+}
+
+// who the history triggers credit with the changes made in this transaction
+pub async fn set_actor(conn: &mut PgConnection, actor_id: Option<Uuid>) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT set_config('easy_buy.actor_id', $1, true)")
+        .bind(actor_id.map(|id| id.to_string()).unwrap_or_default())
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+// latest version of a product or category, 0 before anything is recorded
+pub async fn current_version(conn: &mut PgConnection, entity: HistoryEntity, id: Uuid) -> Result<i32, sqlx::Error> {
+    sqlx::query_scalar("SELECT COALESCE(MAX(version), 0) FROM change_history WHERE entity = $1 AND entity_id = $2")
+        .bind(entity)
+        .bind(id)
+        .fetch_one(conn)
+        .await
+}
+
+// the version this transaction's edits become, for the ETag of a write. The history triggers
+// normally run at commit; firing them now and deferring them again keeps later statements
+// in the transaction to the same single version
+pub async fn saved_version(conn: &mut PgConnection, entity: HistoryEntity, id: Uuid) -> Result<i32, sqlx::Error> {
+    const HISTORY_TRIGGERS: &str = "products_history, product_tags_history, product_categories_history, categories_history";
+
+    sqlx::query(&format!("SET CONSTRAINTS {} IMMEDIATE", HISTORY_TRIGGERS)).execute(&mut *conn).await?;
+    sqlx::query(&format!("SET CONSTRAINTS {} DEFERRED", HISTORY_TRIGGERS)).execute(&mut *conn).await?;
+    current_version(conn, entity, id).await
+}
+
+// the product's ETag version, for reads of the parts edited through their own endpoints
+pub async fn product_version(pool: &PgPool, id: Uuid) -> Result<i32, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    current_version(&mut conn, HistoryEntity::Product, id).await
+}
+
+// locks a live product for an edit; false when it has moved on from `expected`, the version
+// the caller read. RowNotFound for missing and trashed products
+pub async fn lock_product_at(conn: &mut PgConnection, id: Uuid, expected: Option<i32>) -> Result<bool, sqlx::Error> {
+    sqlx::query("SELECT id FROM products WHERE id = $1 AND deleted_at IS NULL FOR UPDATE")
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    // checked under the row lock, so a concurrent edit has committed its version by now
+    match expected {
+        Some(expected) => Ok(current_version(conn, HistoryEntity::Product, id).await? == expected),
+        None => Ok(true),
+    }
+}
+
+pub async fn list_history(
+    pool: &PgPool,
+    entity: HistoryEntity,
+    entity_id: Uuid,
+    page: &PageRequest<'_>,
+) -> Result<Paged<ChangeRecord>, sqlx::Error> {
+    let mut builder = QueryBuilder::new("SELECT version, action, changes, snapshot, actor_id, changed_at");
+    page.push_sort_columns(&mut builder, "id");
+    builder
+        .push(" FROM change_history WHERE entity = ")
+        .push_bind(entity)
+        .push(" AND entity_id = ")
+        .push_bind(entity_id);
+    page.push_cursor_filter(&mut builder, "id");
+    page.push_order_and_limit(&mut builder, "id");
+
+    let rows = builder.build_query_as::<Keyed<ChangeRecord>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM change_history WHERE entity = $1 AND entity_id = $2")
+        .bind(entity)
+        .bind(entity_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(page.finish(rows, total))
+}
+
+// puts the product's fields, tags and categories back as they were at `version`;
+// stock isn't versioned and stays as it is. The revert is itself a new version
+pub async fn revert_product(
+    pool: &PgPool,
+    id: Uuid,
+    version: i32,
+    expected_version: Option<i32>,
+    actor_id: Option<Uuid>,
+) -> Result<(Product, i32), RevertError> {
+    let mut tx = pool.begin().await?;
+    set_actor(&mut tx, actor_id).await?;
+
+    let snapshot: Value = sqlx::query_scalar::<_, Option<Value>>(
+        "SELECT snapshot FROM change_history WHERE entity = 'product' AND entity_id = $1 AND version = $2",
+    )
+    .bind(id)
+    .bind(version)
+    .fetch_optional(&mut *tx)
+    .await?
+    .flatten()
+    .ok_or(RevertError::NotFound)?;
+
+    let (current_slug, in_trash): (String, bool) =
+        sqlx::query_as("SELECT slug, deleted_at IS NOT NULL FROM products WHERE id = $1 FOR UPDATE")
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+    if in_trash {
+        return Err(RevertError::InTrash);
+    }
+    if let Some(expected) = expected_version {
+        if current_version(&mut tx, HistoryEntity::Product, id).await? != expected {
+            return Err(RevertError::VersionMismatch);
+        }
+    }
+
+    let slug = snapshot["slug"].as_str().unwrap_or(&current_slug).to_string();
+    record_slug_change(&mut tx, SlugOwner::Product, id, &current_slug, &slug).await?;
+
+    let category_id: Option<Uuid> = serde_json::from_value(snapshot["category_id"].clone()).unwrap_or_default();
+    let secondary: Vec<Uuid> = serde_json::from_value(snapshot["secondary_category_ids"].clone()).unwrap_or_default();
+    let tags: Vec<String> = serde_json::from_value(snapshot["tags"].clone()).unwrap_or_default();
+
+    let categories: Vec<Uuid> = category_id.iter().chain(&secondary).copied().collect();
+    match check_categories(&mut tx, &categories).await {
+        Ok(()) => {}
+        Err(ProductError::Database(e)) => return Err(RevertError::Database(e)),
+        Err(_) => return Err(RevertError::CategoryNotFound),
+    }
+
+    let product = sqlx::query_as::<_, Product>(
+        r#"
+        UPDATE products p
+        SET (name, slug, sku, description, price, image, category_id, attributes, reorder_threshold,
+             compare_at_price, sale_price, sale_starts_at, sale_ends_at, tax_class_id,
+             weight_grams, length_mm, width_mm, height_mm) =
+                (r.name, r.slug, r.sku, r.description, r.price, r.image, r.category_id, r.attributes, r.reorder_threshold,
+                 r.compare_at_price, r.sale_price, r.sale_starts_at, r.sale_ends_at,
+                 (SELECT id FROM tax_classes WHERE id = r.tax_class_id), -- a deleted class means the default
+                 r.weight_grams, r.length_mm, r.width_mm, r.height_mm),
+            updated_at = $3
+        -- columns added after the snapshot was taken keep their current values
+        FROM jsonb_populate_record(NULL::products, product_history_snapshot($2) || $1) r
+        WHERE p.id = $2
+        RETURNING p.id, p.name, p.slug, p.sku, p.description, p.price, p.stock_quantity, p.category_id, p.attributes, p.created_at, p.updated_at
+        "#,
+    )
+    .bind(&snapshot)
+    .bind(id)
+    .bind(Utc::now().naive_utc())
+    .fetch_one(&mut *tx)
+    .await?;
+
+    set_secondary_categories(&mut tx, id, category_id, &secondary).await?;
+    set_tags(&mut tx, id, &tags).await?;
+    let new_version = saved_version(&mut tx, HistoryEntity::Product, id).await?;
+
+    tx.commit().await?;
+
+    Ok((product, new_version))
+}
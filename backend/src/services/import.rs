@@ -0,0 +1,420 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use serde_json::{Map, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::attribute::{AttributeDefinition, AttributeKind};
+use crate::models::category::CreateCategory;
+use crate::models::import::{ImportFormat, ImportJob, ImportOptions, ImportRow, ImportRowError, ImportStatus};
+use crate::models::product::{CreateProduct, UpdateProduct};
+use crate::services::attribute::{applicable_definitions, validate_attributes};
+use crate::services::category::create_category;
+use crate::services::product::{create_product, update_product, ProductError};
+use crate::services::slug::{is_valid_slug, INVALID_SLUG};
+
+// progress is written back to the job every this many rows
+const PROGRESS_EVERY: usize = 50;
+
+const CSV_COLUMNS: [&str; 8] = ["sku", "name", "slug", "description", "price", "stock_quantity", "category", "tags"];
+
+// a line of the file: its line number, the sku if one could be read, and the row
+// or why it couldn't be read
+type ParsedRow = (i32, Option<String>, Result<ImportRow, String>);
+
+// errors are for the whole file, e.g. an unknown CSV column
+pub fn parse_rows(format: ImportFormat, data: &[u8]) -> Result<Vec<ParsedRow>, String> {
+    match format {
+        ImportFormat::Csv => parse_csv(data),
+        ImportFormat::Ndjson => parse_ndjson(data),
+    }
+}
+
+fn parse_csv(data: &[u8]) -> Result<Vec<ParsedRow>, String> {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(data);
+    let headers = reader.headers().map_err(|e| format!("unreadable CSV header: {}", e))?.clone();
+
+    if let Some(unknown) = headers.iter().find(|h| !CSV_COLUMNS.contains(h) && !h.starts_with("attr:")) {
+        return Err(format!("unknown column {}", unknown));
+    }
+
+    Ok(reader
+        .records()
+        .map(|record| match record {
+            Ok(record) => {
+                let line = record.position().map(|p| p.line() as i32).unwrap_or_default();
+                let sku = headers.iter().position(|h| h == "sku").and_then(|i| record.get(i)).map(str::to_string);
+                (line, sku.filter(|s| !s.is_empty()), csv_row(&headers, &record))
+            }
+            Err(e) => {
+                let line = e.position().map(|p| p.line() as i32).unwrap_or_default();
+                (line, None, Err(format!("unreadable row: {}", e)))
+            }
+        })
+        .collect())
+}
+
+// empty cells are left out, so an update only touches the columns that have values
+fn csv_row(headers: &csv::StringRecord, record: &csv::StringRecord) -> Result<ImportRow, String> {
+    let mut row = ImportRow::default();
+
+    for (header, value) in headers.iter().zip(record.iter()).filter(|(_, v)| !v.is_empty()) {
+        match header {
+            "sku" => row.sku = Some(value.to_string()),
+            "name" => row.name = Some(value.to_string()),
+            "slug" => row.slug = Some(value.to_string()),
+            "description" => row.description = Some(value.to_string()),
+            "price" => row.price = Some(BigDecimal::from_str(value).map_err(|_| "price is not a number")?),
+            "stock_quantity" => {
+                row.stock_quantity = Some(value.parse().map_err(|_| "stock_quantity is not a whole number")?)
+            }
+            "category" => row.category = Some(value.to_string()),
+            "tags" => row.tags = Some(value.split('|').map(|t| t.trim().to_string()).collect()),
+            attribute => {
+                let key = attribute.trim_start_matches("attr:");
+                row.attributes.insert(key.to_string(), Value::String(value.to_string()));
+            }
+        }
+    }
+
+    Ok(row)
+}
+
+fn parse_ndjson(data: &[u8]) -> Result<Vec<ParsedRow>, String> {
+    let text = std::str::from_utf8(data).map_err(|_| "the file is not valid UTF-8".to_string())?;
+
+    Ok(text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let row = serde_json::from_str::<ImportRow>(line).map_err(|e| format!("invalid JSON: {}", e));
+            let sku = serde_json::from_str::<Value>(line)
+                .ok()
+                .and_then(|v| v.get("sku").and_then(Value::as_str).map(str::to_string));
+            (i as i32 + 1, sku, row)
+        })
+        .collect())
+}
+
+// CSV cells are text; numbers and booleans are read as such where the definition asks for them
+fn coerce_attributes(definitions: &[AttributeDefinition], attributes: Map<String, Value>) -> Map<String, Value> {
+    attributes
+        .into_iter()
+        .map(|(key, value)| {
+            let kinds: Vec<AttributeKind> = definitions.iter().filter(|d| d.key == key).map(|d| d.kind).collect();
+            let value = match value {
+                Value::String(s) if kinds.contains(&AttributeKind::Number) => serde_json::from_str::<serde_json::Number>(&s)
+                    .map(Value::Number)
+                    .unwrap_or(Value::String(s)),
+                Value::String(s) if kinds.contains(&AttributeKind::Boolean) => match s.to_lowercase().as_str() {
+                    "true" => Value::Bool(true),
+                    "false" => Value::Bool(false),
+                    _ => Value::String(s),
+                },
+                value => value,
+            };
+            (key, value)
+        })
+        .collect()
+}
+
+fn product_error(e: ProductError) -> String {
+    match e {
+        ProductError::NotFound => "product not found".to_string(),
+        ProductError::CategoryNotFound => "category not found".to_string(),
+        ProductError::InvalidAttributes(message) => message,
+        ProductError::VersionMismatch => "product was changed during the import".to_string(),
+        ProductError::InsufficientStock => "not enough stock to reach this level".to_string(),
+        ProductError::Database(e) if e.as_database_error().and_then(|e| e.code()).as_deref() == Some("23505") => {
+            "slug or sku is already in use".to_string()
+        }
+        ProductError::Database(e) => format!("database error: {}", e),
+    }
+}
+
+enum RowOutcome {
+    Created,
+    Updated,
+}
+
+// state carried across the rows of one file
+struct Importer<'a> {
+    pool: &'a PgPool,
+    options: ImportOptions,
+    seen_skus: HashSet<String>,
+    categories: HashMap<String, Option<Uuid>>, // by lower-cased name; None = created only in a dry run
+    actor_id: Option<Uuid>,                    // who started the import
+}
+
+impl Importer<'_> {
+    // Ok(Err(..)) is a rejected row; Err(..) stops the whole import
+    async fn import_row(&mut self, row: ImportRow) -> Result<Result<RowOutcome, String>, sqlx::Error> {
+        let Some(sku) = row.sku.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string) else {
+            return Ok(Err("sku is required".to_string()));
+        };
+        if !self.seen_skus.insert(sku.clone()) {
+            return Ok(Err("sku appears more than once in the file".to_string()));
+        }
+        if row.price.as_ref().is_some_and(|p| p < &BigDecimal::from(0)) {
+            return Ok(Err("price cannot be negative".to_string()));
+        }
+        if row.stock_quantity.is_some_and(|q| q < 0) {
+            return Ok(Err("stock_quantity cannot be negative".to_string()));
+        }
+        if row.slug.as_deref().is_some_and(|slug| !is_valid_slug(slug)) {
+            return Ok(Err(INVALID_SLUG.to_string()));
+        }
+
+        let existing: Option<(Uuid, bool)> = sqlx::query_as("SELECT id, deleted_at IS NOT NULL FROM products WHERE sku = $1")
+            .bind(&sku)
+            .fetch_optional(self.pool)
+            .await?;
+        let existing = match existing {
+            Some((_, true)) => return Ok(Err("sku belongs to a product in the trash".to_string())),
+            Some((id, false)) => Some(id),
+            None if row.name.is_none() || row.price.is_none() => {
+                return Ok(Err("name and price are required for a new product".to_string()))
+            }
+            None => None,
+        };
+
+        let category_id = match &row.category {
+            Some(name) => match self.resolve_category(name).await? {
+                Ok(id) => id,
+                Err(message) => return Ok(Err(message)),
+            },
+            None => None,
+        };
+
+        // attributes are checked against the row's category, or the product's current ones
+        let mut attributes = row.attributes;
+        if !attributes.is_empty() {
+            let categories: Vec<Uuid> = match (category_id, existing) {
+                (Some(id), _) => vec![id],
+                (None, Some(id)) => sqlx::query_scalar(
+                    "SELECT category_id FROM products WHERE id = $1 AND category_id IS NOT NULL UNION SELECT category_id FROM product_categories WHERE product_id = $1",
+                )
+                .bind(id)
+                .fetch_all(self.pool)
+                .await?,
+                (None, None) => Vec::new(),
+            };
+            let mut conn = self.pool.acquire().await?;
+            let definitions = applicable_definitions(&mut conn, &categories).await?;
+            attributes = coerce_attributes(&definitions, attributes);
+            if let Err(message) = validate_attributes(&definitions, &attributes) {
+                return Ok(Err(message));
+            }
+        }
+
+        if self.options.dry_run {
+            return Ok(Ok(if existing.is_some() { RowOutcome::Updated } else { RowOutcome::Created }));
+        }
+
+        let result = match existing {
+            Some(id) => update_product(
+                self.pool,
+                id,
+                UpdateProduct {
+                    name: row.name,
+                    slug: row.slug,
+                    sku: None,
+                    description: row.description,
+                    price: row.price,
+                    stock_quantity: row.stock_quantity,
+                    category_id,
+                    secondary_category_ids: None,
+                    tags: row.tags,
+                    attributes: Some(attributes).filter(|a| !a.is_empty()),
+                    deleted_at: None,
+                },
+                None,
+                self.actor_id,
+            )
+            .await
+            .map(|_| RowOutcome::Updated),
+            None => create_product(
+                self.pool,
+                CreateProduct {
+                    name: row.name.unwrap_or_default(),
+                    slug: row.slug,
+                    sku: Some(sku),
+                    description: row.description,
+                    price: row.price.unwrap_or_default(),
+                    stock_quantity: row.stock_quantity.unwrap_or(0),
+                    category_id,
+                    secondary_category_ids: Vec::new(),
+                    tags: row.tags.unwrap_or_default(),
+                    attributes,
+                },
+                self.actor_id,
+            )
+            .await
+            .map(|_| RowOutcome::Created),
+        };
+
+        Ok(result.map_err(product_error))
+    }
+
+    // Ok(None) is a category that only a real run would create
+    async fn resolve_category(&mut self, name: &str) -> Result<Result<Option<Uuid>, String>, sqlx::Error> {
+        let key = name.trim().to_lowercase();
+        if let Some(id) = self.categories.get(&key) {
+            return Ok(Ok(*id));
+        }
+
+        let found: Option<(Uuid, bool)> = sqlx::query_as(
+            "SELECT id, deleted_at IS NOT NULL FROM categories WHERE lower(name) = $1 ORDER BY deleted_at NULLS FIRST LIMIT 1",
+        )
+        .bind(&key)
+        .fetch_optional(self.pool)
+        .await?;
+
+        let id = match found {
+            Some((_, true)) => return Ok(Err(format!("category {} is in the trash", name))),
+            Some((id, false)) => Some(id),
+            None if !self.options.create_categories => return Ok(Err(format!("unknown category {}", name))),
+            None if self.options.dry_run => None,
+            None => {
+                let new = CreateCategory { name: name.trim().to_string(), slug: None, description: None, parent_id: None };
+                Some(create_category(self.pool, new, self.actor_id).await?.id)
+            }
+        };
+
+        self.categories.insert(key, id);
+        Ok(Ok(id))
+    }
+}
+
+pub async fn create_job(pool: &PgPool, options: ImportOptions, created_by: Option<Uuid>) -> Result<ImportJob, sqlx::Error> {
+    sqlx::query_as::<_, ImportJob>(
+        r#"
+        INSERT INTO import_jobs (id, format, dry_run, create_categories, created_by, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(options.format)
+    .bind(options.dry_run)
+    .bind(options.create_categories)
+    .bind(created_by)
+    .bind(Utc::now().naive_utc())
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn get_job(pool: &PgPool, id: Uuid) -> Result<Option<ImportJob>, sqlx::Error> {
+    sqlx::query_as::<_, ImportJob>("SELECT * FROM import_jobs WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn job_errors(pool: &PgPool, id: Uuid) -> Result<Vec<ImportRowError>, sqlx::Error> {
+    sqlx::query_as::<_, ImportRowError>(
+        "SELECT row_number, sku, message FROM import_job_errors WHERE job_id = $1 ORDER BY row_number",
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await
+}
+
+// the per-row error report as CSV
+pub fn errors_csv(errors: &[ImportRowError]) -> String {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let _ = writer.write_record(["row", "sku", "error"]);
+    for error in errors {
+        let _ = writer.write_record([&error.row_number.to_string(), error.sku.as_deref().unwrap_or_default(), &error.message]);
+    }
+    String::from_utf8(writer.into_inner().unwrap_or_default()).unwrap_or_default()
+}
+
+async fn finish_job(pool: &PgPool, id: Uuid, status: ImportStatus, error: Option<String>) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE import_jobs SET status = $1, error = $2, finished_at = $3 WHERE id = $4")
+        .bind(status)
+        .bind(error)
+        .bind(Utc::now().naive_utc())
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// each row is validated and written on its own, so one bad row doesn't stop the rest
+pub async fn run_import(pool: &PgPool, job_id: Uuid, options: ImportOptions, data: &[u8]) -> Result<(), sqlx::Error> {
+    let created_by: Option<Uuid> =
+        sqlx::query_scalar("UPDATE import_jobs SET status = 'running', started_at = $1 WHERE id = $2 RETURNING created_by")
+            .bind(Utc::now().naive_utc())
+            .bind(job_id)
+            .fetch_one(pool)
+            .await?;
+
+    let rows = match parse_rows(options.format, data) {
+        Ok(rows) => rows,
+        Err(message) => return finish_job(pool, job_id, ImportStatus::Failed, Some(message)).await,
+    };
+
+    sqlx::query("UPDATE import_jobs SET total_rows = $1 WHERE id = $2")
+        .bind(rows.len() as i32)
+        .bind(job_id)
+        .execute(pool)
+        .await?;
+
+    let mut importer =
+        Importer { pool, options, seen_skus: HashSet::new(), categories: HashMap::new(), actor_id: created_by };
+    let (mut created, mut updated, mut failed) = (0, 0, 0);
+    let total = rows.len();
+
+    for (i, (line, sku, row)) in rows.into_iter().enumerate() {
+        let outcome = match row {
+            Ok(row) => importer.import_row(row).await?,
+            Err(message) => Err(message),
+        };
+
+        match outcome {
+            Ok(RowOutcome::Created) => created += 1,
+            Ok(RowOutcome::Updated) => updated += 1,
+            Err(message) => {
+                failed += 1;
+                sqlx::query("INSERT INTO import_job_errors (job_id, row_number, sku, message) VALUES ($1, $2, $3, $4)")
+                    .bind(job_id)
+                    .bind(line)
+                    .bind(sku)
+                    .bind(message)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+
+        if (i + 1) % PROGRESS_EVERY == 0 || i + 1 == total {
+            sqlx::query(
+                "UPDATE import_jobs SET processed_rows = $1, created_count = $2, updated_count = $3, error_count = $4 WHERE id = $5",
+            )
+            .bind(i as i32 + 1)
+            .bind(created)
+            .bind(updated)
+            .bind(failed)
+            .bind(job_id)
+            .execute(pool)
+            .await?;
+        }
+    }
+
+    finish_job(pool, job_id, ImportStatus::Completed, None).await
+}
+
+// runs off the request; poll the job for progress
+pub fn spawn_import(pool: PgPool, job_id: Uuid, options: ImportOptions, data: Vec<u8>) {
+    tokio::spawn(async move {
+        if let Err(e) = run_import(&pool, job_id, options, &data).await {
+            eprintln!("❌ Import job {} failed: {:?}", job_id, e);
+            let _ = finish_job(&pool, job_id, ImportStatus::Failed, Some("database error".to_string())).await;
+        }
+    });
+}
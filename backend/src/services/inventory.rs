@@ -0,0 +1,327 @@
+use std::time::Duration;
+
+use crate::models::inventory::{NewStockMovement, StockDrift, StockMovement, StockMovementKind};
+use crate::services::warehouse::{adjust_location, allocation_strategy, default_warehouse_id, locked_levels};
+use chrono::Utc;
+use crate::pagination::{Keyed, PageRequest, Paged, SortDirection, SortKey};
+use sqlx::{PgConnection, PgPool, QueryBuilder};
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub enum InventoryError {
+    NotFound,
+    InsufficientStock,
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for InventoryError {
+    fn from(err: sqlx::Error) -> Self {
+        InventoryError::Database(err)
+    }
+}
+
+// write the ledger row only; callers must change the stock level in the same transaction
+pub async fn insert_movement(
+    conn: &mut PgConnection,
+    movement: &NewStockMovement,
+) -> Result<StockMovement, sqlx::Error> {
+    sqlx::query_as::<_, StockMovement>(
+        r#"
+        INSERT INTO stock_movements (id, product_id, variant_id, warehouse_id, kind, quantity, reason, actor_id, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING id, product_id, variant_id, warehouse_id, kind, quantity, reason, actor_id, created_at
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(movement.product_id)
+    .bind(movement.variant_id)
+    .bind(movement.warehouse_id)
+    .bind(movement.kind)
+    .bind(movement.quantity)
+    .bind(&movement.reason)
+    .bind(movement.actor_id)
+    .bind(Utc::now().naive_utc())
+    .fetch_one(conn)
+    .await
+}
+
+// apply a signed delta to product (or variant) stock and record it in the ledger,
+// one row per location touched
+pub async fn apply_movement(
+    conn: &mut PgConnection,
+    movement: NewStockMovement,
+) -> Result<Vec<StockMovement>, InventoryError> {
+    let now = Utc::now().naive_utc();
+
+    // the conditional update keeps stock from going negative without a separate lock
+    let updated: Option<i32> = match movement.variant_id {
+        Some(variant_id) => {
+            sqlx::query_scalar(
+                r#"
+                UPDATE product_variants
+                SET stock_quantity = stock_quantity + $1, updated_at = $2
+                WHERE id = $3 AND product_id = $4 AND stock_quantity + $1 >= 0
+                RETURNING stock_quantity
+                "#,
+            )
+            .bind(movement.quantity)
+            .bind(now)
+            .bind(variant_id)
+            .bind(movement.product_id)
+            .fetch_optional(&mut *conn)
+            .await?
+        }
+        None => {
+            sqlx::query_scalar(
+                r#"
+                UPDATE products
+                SET stock_quantity = stock_quantity + $1, updated_at = $2
+                WHERE id = $3 AND stock_quantity + $1 >= 0
+                RETURNING stock_quantity
+                "#,
+            )
+            .bind(movement.quantity)
+            .bind(now)
+            .bind(movement.product_id)
+            .fetch_optional(&mut *conn)
+            .await?
+        }
+    };
+
+    if updated.is_none() {
+        let exists: bool = match movement.variant_id {
+            Some(variant_id) => {
+                sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM product_variants WHERE id = $1 AND product_id = $2)")
+                    .bind(variant_id)
+                    .bind(movement.product_id)
+                    .fetch_one(&mut *conn)
+                    .await?
+            }
+            None => {
+                sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM products WHERE id = $1)")
+                    .bind(movement.product_id)
+                    .fetch_one(&mut *conn)
+                    .await?
+            }
+        };
+
+        return Err(if exists { InventoryError::InsufficientStock } else { InventoryError::NotFound });
+    }
+
+    let allocations: Vec<(Uuid, i32)> = match movement.warehouse_id {
+        Some(warehouse_id) => vec![(warehouse_id, movement.quantity)],
+        None if movement.quantity > 0 => vec![(default_warehouse_id(&mut *conn).await?, movement.quantity)],
+        None => {
+            let levels = locked_levels(&mut *conn, movement.product_id, movement.variant_id).await?;
+            allocation_strategy()
+                .allocate(&levels, -movement.quantity)
+                .ok_or(InventoryError::InsufficientStock)?
+                .into_iter()
+                .map(|allocation| (allocation.warehouse_id, -allocation.quantity))
+                .collect()
+        }
+    };
+
+    let mut movements = Vec::with_capacity(allocations.len());
+    for (warehouse_id, quantity) in allocations {
+        if !adjust_location(&mut *conn, warehouse_id, movement.product_id, movement.variant_id, quantity).await? {
+            return Err(InventoryError::InsufficientStock);
+        }
+
+        let located = NewStockMovement {
+            warehouse_id: Some(warehouse_id),
+            quantity,
+            reason: movement.reason.clone(),
+            ..movement
+        };
+        movements.push(insert_movement(&mut *conn, &located).await?);
+    }
+
+    Ok(movements)
+}
+
+pub async fn record_movement(pool: &PgPool, movement: NewStockMovement) -> Result<Vec<StockMovement>, InventoryError> {
+    let mut tx = pool.begin().await?;
+    let movement = apply_movement(&mut tx, movement).await?;
+    tx.commit().await?;
+
+    Ok(movement)
+}
+
+// opening stock of a newly created product or variant, received into the default location
+pub async fn receive_initial_stock(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    quantity: i32,
+) -> Result<(), InventoryError> {
+    let movement = NewStockMovement {
+        product_id,
+        variant_id,
+        warehouse_id: None,
+        kind: StockMovementKind::Receipt,
+        quantity,
+        reason: Some("initial stock".to_string()),
+        actor_id: None,
+    };
+
+    apply_movement(conn, movement).await?;
+    Ok(())
+}
+
+// bring stock to an absolute level by recording the difference as an adjustment
+pub async fn set_stock_level(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    target: i32,
+    actor_id: Option<Uuid>,
+    reason: &str,
+) -> Result<Vec<StockMovement>, InventoryError> {
+    let current: Option<i32> = match variant_id {
+        Some(variant_id) => {
+            sqlx::query_scalar("SELECT stock_quantity FROM product_variants WHERE id = $1 AND product_id = $2 FOR UPDATE")
+                .bind(variant_id)
+                .bind(product_id)
+                .fetch_optional(&mut *conn)
+                .await?
+        }
+        None => {
+            sqlx::query_scalar("SELECT stock_quantity FROM products WHERE id = $1 FOR UPDATE")
+                .bind(product_id)
+                .fetch_optional(&mut *conn)
+                .await?
+        }
+    };
+    let current = current.ok_or(InventoryError::NotFound)?;
+
+    let delta = target - current;
+    if delta == 0 {
+        return Ok(Vec::new());
+    }
+
+    let movement = NewStockMovement {
+        product_id,
+        variant_id,
+        warehouse_id: None,
+        kind: StockMovementKind::Adjustment,
+        quantity: delta,
+        reason: Some(reason.to_string()),
+        actor_id,
+    };
+
+    apply_movement(conn, movement).await
+}
+
+pub const STOCK_MOVEMENT_SORTS: &[SortKey] =
+    &[SortKey { name: "created_at", expr: "created_at", sql_type: "timestamp", direction: SortDirection::Desc }];
+
+pub async fn stock_history(
+    pool: &PgPool,
+    product_id: Uuid,
+    page: &PageRequest<'_>,
+) -> Result<Paged<StockMovement>, sqlx::Error> {
+    let mut builder =
+        QueryBuilder::new("SELECT id, product_id, variant_id, warehouse_id, kind, quantity, reason, actor_id, created_at");
+    page.push_sort_columns(&mut builder, "id");
+    builder.push(" FROM stock_movements WHERE product_id = ").push_bind(product_id);
+    page.push_cursor_filter(&mut builder, "id");
+    page.push_order_and_limit(&mut builder, "id");
+
+    let rows = builder.build_query_as::<Keyed<StockMovement>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM stock_movements WHERE product_id = $1")
+        .bind(product_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(page.finish(rows, total))
+}
+
+// compare ledger sums with stock_quantity and refresh the drift flags
+pub async fn reconcile_stock(pool: &PgPool) -> Result<Vec<StockDrift>, sqlx::Error> {
+    let started_at = Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        WITH ledger AS (
+            SELECT product_id, variant_id, SUM(quantity) AS quantity
+            FROM stock_movements
+            GROUP BY product_id, variant_id
+        ),
+        levels AS (
+            SELECT p.id AS product_id, NULL::uuid AS variant_id,
+                   COALESCE(l.quantity, 0) AS ledger_quantity, p.stock_quantity AS recorded_quantity
+            FROM products p
+            LEFT JOIN ledger l ON l.product_id = p.id AND l.variant_id IS NULL
+            UNION ALL
+            SELECT v.product_id, v.id,
+                   COALESCE(l.quantity, 0), v.stock_quantity
+            FROM product_variants v
+            LEFT JOIN ledger l ON l.variant_id = v.id
+        )
+        INSERT INTO stock_drift_flags (id, product_id, variant_id, ledger_quantity, recorded_quantity, detected_at, checked_at)
+        SELECT gen_random_uuid(), product_id, variant_id, ledger_quantity, recorded_quantity, $1, $1
+        FROM levels
+        WHERE ledger_quantity <> recorded_quantity
+        ON CONFLICT (product_id, variant_id) DO UPDATE
+        SET ledger_quantity = EXCLUDED.ledger_quantity,
+            recorded_quantity = EXCLUDED.recorded_quantity,
+            checked_at = EXCLUDED.checked_at
+        "#,
+    )
+    .bind(started_at)
+    .execute(&mut *tx)
+    .await?;
+
+    // anything not touched by this run has been fixed since
+    sqlx::query("DELETE FROM stock_drift_flags WHERE checked_at < $1")
+        .bind(started_at)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    sqlx::query_as::<_, StockDrift>(
+        r#"
+        SELECT product_id, variant_id, ledger_quantity, recorded_quantity, detected_at, checked_at
+        FROM stock_drift_flags
+        ORDER BY detected_at
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub const DRIFT_SORTS: &[SortKey] =
+    &[SortKey { name: "detected_at", expr: "detected_at", sql_type: "timestamp", direction: SortDirection::Asc }];
+
+pub async fn list_stock_drift(pool: &PgPool, page: &PageRequest<'_>) -> Result<Paged<StockDrift>, sqlx::Error> {
+    let mut builder =
+        QueryBuilder::new("SELECT product_id, variant_id, ledger_quantity, recorded_quantity, detected_at, checked_at");
+    page.push_sort_columns(&mut builder, "id");
+    builder.push(" FROM stock_drift_flags WHERE TRUE");
+    page.push_cursor_filter(&mut builder, "id");
+    page.push_order_and_limit(&mut builder, "id");
+
+    let rows = builder.build_query_as::<Keyed<StockDrift>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM stock_drift_flags").fetch_one(pool).await?;
+
+    Ok(page.finish(rows, total))
+}
+
+pub fn spawn_reconciliation_job(pool: PgPool, every: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        loop {
+            interval.tick().await;
+            match reconcile_stock(&pool).await {
+                Ok(drift) if !drift.is_empty() => {
+                    eprintln!("⚠️ Stock reconciliation found {} drifting stock levels", drift.len());
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("❌ Stock reconciliation failed: {:?}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,259 @@
+use std::time::Duration;
+
+use crate::models::history::HistoryEntity;
+use crate::models::inventory::{LowStockAlert, LowStockProduct};
+use crate::models::notification::AdminNotification;
+use crate::pagination::{Keyed, PageRequest, Paged, SortDirection, SortKey};
+use crate::services::history::{lock_product_at, saved_version, set_actor, Versioned};
+use crate::services::notifier::{notify_all, Notification, Notifier};
+use chrono::Utc;
+use sqlx::{PgPool, QueryBuilder};
+use uuid::Uuid;
+
+// sales over this many days drive the days-of-cover estimate
+const SALES_WINDOW_DAYS: i32 = 30;
+
+const ALERT_COLUMNS: &str = "id, product_id, stock_quantity, reorder_threshold, created_at, notified_at, resolved_at";
+
+// RowNotFound for missing and trashed products
+pub async fn reorder_threshold(pool: &PgPool, product_id: Uuid) -> Result<Option<i32>, sqlx::Error> {
+    sqlx::query_scalar("SELECT reorder_threshold FROM products WHERE id = $1 AND deleted_at IS NULL")
+        .bind(product_id)
+        .fetch_one(pool)
+        .await
+}
+
+// RowNotFound for missing and trashed products
+pub async fn set_reorder_threshold(
+    pool: &PgPool,
+    product_id: Uuid,
+    threshold: Option<i32>,
+    expected_version: Option<i32>,
+    actor_id: Option<Uuid>,
+) -> Result<Versioned<()>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    set_actor(&mut tx, actor_id).await?;
+
+    if !lock_product_at(&mut tx, product_id, expected_version).await? {
+        return Ok(Versioned::Stale);
+    }
+
+    sqlx::query("UPDATE products SET reorder_threshold = $1, updated_at = $2 WHERE id = $3")
+        .bind(threshold)
+        .bind(Utc::now().naive_utc())
+        .bind(product_id)
+        .execute(&mut *tx)
+        .await?;
+    let version = saved_version(&mut tx, HistoryEntity::Product, product_id).await?;
+
+    tx.commit().await?;
+
+    Ok(Versioned::Saved((), version))
+}
+
+pub const LOW_STOCK_SORTS: &[SortKey] = &[
+    // products that haven't sold in the window last forever
+    SortKey {
+        name: "days_of_cover",
+        expr: "COALESCE(days_of_cover, 'Infinity')",
+        sql_type: "float8",
+        direction: SortDirection::Asc,
+    },
+    SortKey { name: "stock_quantity", expr: "stock_quantity", sql_type: "integer", direction: SortDirection::Asc },
+];
+
+// products at or below their threshold, by default the ones running out soonest first
+pub async fn list_low_stock(pool: &PgPool, page: &PageRequest<'_>) -> Result<Paged<LowStockProduct>, sqlx::Error> {
+    let mut builder = QueryBuilder::new("SELECT product_id, name, stock_quantity, reorder_threshold, daily_sales, days_of_cover");
+    page.push_sort_columns(&mut builder, "product_id");
+    builder
+        .push(
+            r#"
+        FROM (
+            WITH sales AS (
+                SELECT product_id, SUM(-quantity)::float8 / "#,
+        )
+        .push_bind(SALES_WINDOW_DAYS)
+        .push(
+            r#" AS daily_sales
+                FROM stock_movements
+                WHERE kind = 'sale' AND created_at >= NOW() - make_interval(days => "#,
+        )
+        .push_bind(SALES_WINDOW_DAYS)
+        .push(
+            r#")
+                GROUP BY product_id
+            )
+            SELECT p.id AS product_id, p.name, p.stock_quantity, p.reorder_threshold,
+                   COALESCE(s.daily_sales, 0) AS daily_sales,
+                   p.stock_quantity / NULLIF(s.daily_sales, 0) AS days_of_cover
+            FROM products p
+            LEFT JOIN sales s ON s.product_id = p.id
+            WHERE p.deleted_at IS NULL
+              AND p.reorder_threshold IS NOT NULL
+              AND p.stock_quantity <= p.reorder_threshold
+        ) low
+        WHERE TRUE"#,
+        );
+    page.push_cursor_filter(&mut builder, "product_id");
+    page.push_order_and_limit(&mut builder, "product_id");
+
+    let rows = builder.build_query_as::<Keyed<LowStockProduct>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*) FROM products
+        WHERE deleted_at IS NULL AND reorder_threshold IS NOT NULL AND stock_quantity <= reorder_threshold
+        "#,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(page.finish(rows, total))
+}
+
+pub const ALERT_SORTS: &[SortKey] =
+    &[SortKey { name: "created_at", expr: "created_at", sql_type: "timestamp", direction: SortDirection::Desc }];
+
+pub async fn list_open_alerts(pool: &PgPool, page: &PageRequest<'_>) -> Result<Paged<LowStockAlert>, sqlx::Error> {
+    let mut builder =
+        QueryBuilder::new(format!("SELECT {}", ALERT_COLUMNS));
+    page.push_sort_columns(&mut builder, "id");
+    builder.push(" FROM low_stock_alerts WHERE resolved_at IS NULL");
+    page.push_cursor_filter(&mut builder, "id");
+    page.push_order_and_limit(&mut builder, "id");
+
+    let rows = builder.build_query_as::<Keyed<LowStockAlert>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM low_stock_alerts WHERE resolved_at IS NULL")
+        .fetch_one(pool)
+        .await?;
+
+    Ok(page.finish(rows, total))
+}
+
+// an open alert with the name of its product, for the notification
+#[derive(sqlx::FromRow)]
+struct PendingAlert {
+    #[sqlx(flatten)]
+    alert: LowStockAlert,
+    name: String,
+}
+
+// resolve recovered alerts, open alerts for products that crossed below, and notify on new
+// ones; open alerts no notifier took last time are sent again
+pub async fn check_low_stock(pool: &PgPool, notifiers: &[Box<dyn Notifier>]) -> Result<Vec<LowStockAlert>, sqlx::Error> {
+    let now = Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        UPDATE low_stock_alerts a
+        SET resolved_at = $1
+        FROM products p
+        WHERE a.product_id = p.id AND a.resolved_at IS NULL
+          AND (p.reorder_threshold IS NULL OR p.deleted_at IS NOT NULL OR p.stock_quantity > p.reorder_threshold)
+        "#,
+    )
+    .bind(now)
+    .execute(&mut *tx)
+    .await?;
+
+    let undelivered = sqlx::query_as::<_, PendingAlert>(
+        r#"
+        SELECT a.id, a.product_id, a.stock_quantity, a.reorder_threshold, a.created_at, a.notified_at, a.resolved_at, p.name
+        FROM low_stock_alerts a
+        JOIN products p ON p.id = a.product_id
+        WHERE a.resolved_at IS NULL AND a.notified_at IS NULL
+        ORDER BY a.created_at
+        "#,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    // the partial unique index keeps a product from being alerted twice while still low
+    let new_alerts = sqlx::query_as::<_, PendingAlert>(&format!(
+        r#"
+        WITH inserted AS (
+            INSERT INTO low_stock_alerts (id, product_id, stock_quantity, reorder_threshold, created_at)
+            SELECT gen_random_uuid(), id, stock_quantity, reorder_threshold, $1
+            FROM products
+            WHERE deleted_at IS NULL AND reorder_threshold IS NOT NULL AND stock_quantity <= reorder_threshold
+            ON CONFLICT (product_id) WHERE resolved_at IS NULL DO NOTHING
+            RETURNING {}
+        )
+        SELECT inserted.*, p.name
+        FROM inserted
+        JOIN products p ON p.id = inserted.product_id
+        "#,
+        ALERT_COLUMNS,
+    ))
+    .bind(now)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    for PendingAlert { alert, name } in undelivered.iter().chain(&new_alerts) {
+        let notification = Notification {
+            subject: format!("Low stock: {}", name),
+            body: format!(
+                "{} ({}) is down to {} units, at or below its reorder threshold of {}.",
+                name, alert.product_id, alert.stock_quantity, alert.reorder_threshold
+            ),
+        };
+
+        if notify_all(notifiers, &notification).await {
+            sqlx::query("UPDATE low_stock_alerts SET notified_at = $1 WHERE id = $2")
+                .bind(Utc::now().naive_utc())
+                .bind(alert.id)
+                .execute(pool)
+                .await?;
+        }
+    }
+
+    Ok(new_alerts.into_iter().map(|pending| pending.alert).collect())
+}
+
+pub const NOTIFICATION_SORTS: &[SortKey] =
+    &[SortKey { name: "created_at", expr: "created_at", sql_type: "timestamp", direction: SortDirection::Desc }];
+
+pub async fn list_notifications(
+    pool: &PgPool,
+    page: &PageRequest<'_>,
+) -> Result<Paged<AdminNotification>, sqlx::Error> {
+    let mut builder = QueryBuilder::new("SELECT id, subject, body, created_at, read_at");
+    page.push_sort_columns(&mut builder, "id");
+    builder.push(" FROM admin_notifications WHERE TRUE");
+    page.push_cursor_filter(&mut builder, "id");
+    page.push_order_and_limit(&mut builder, "id");
+
+    let rows = builder.build_query_as::<Keyed<AdminNotification>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM admin_notifications").fetch_one(pool).await?;
+
+    Ok(page.finish(rows, total))
+}
+
+pub async fn mark_notification_read(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+    let result = sqlx::query("UPDATE admin_notifications SET read_at = COALESCE(read_at, $1) WHERE id = $2")
+        .bind(Utc::now().naive_utc())
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+
+    Ok(())
+}
+
+pub fn spawn_low_stock_monitor(pool: PgPool, notifiers: Vec<Box<dyn Notifier>>, every: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        loop {
+            interval.tick().await;
+            if let Err(e) = check_low_stock(&pool, &notifiers).await {
+                eprintln!("❌ Low stock check failed: {:?}", e);
+            }
+        }
+    });
+}
@@ -2,4 +2,28 @@ pub mod auth;
 pub mod product;
 pub mod category;
 pub  mod  cart; 
+pub mod variant;
+pub mod inventory;
+pub mod reservation;
+pub mod notifier;
+pub mod low_stock;
+pub mod warehouse;
+pub mod search;
+pub mod search_analytics;
+pub mod slug;
 
+
+pub mod trash;
+pub mod attribute;
+pub mod import;
+pub mod export;
+pub mod bulk;
+pub mod history;
+pub mod pricing;
+pub mod currency;
+pub mod rate_provider;
+pub mod promotion;
+pub mod promotion_engine;
+pub mod tax;
+pub mod shipping;
+pub mod shipping_provider;
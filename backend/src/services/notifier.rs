@@ -0,0 +1,153 @@
+use std::env;
+
+use axum::async_trait;
+use chrono::Utc;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub type NotifyError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Serialize)]
+pub struct Notification {
+    pub subject: String,
+    pub body: String,
+}
+
+// a channel admins can be reached through
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError>;
+}
+
+// stores the notification so it shows up in the admin dashboard
+pub struct InAppNotifier {
+    pool: PgPool,
+}
+
+impl InAppNotifier {
+    pub fn new(pool: PgPool) -> Self {
+        InAppNotifier { pool }
+    }
+}
+
+#[async_trait]
+impl Notifier for InAppNotifier {
+    fn name(&self) -> &'static str {
+        "in-app"
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        sqlx::query("INSERT INTO admin_notifications (id, subject, body, created_at) VALUES ($1, $2, $3, $4)")
+            .bind(Uuid::new_v4())
+            .bind(&notification.subject)
+            .bind(&notification.body)
+            .bind(Utc::now().naive_utc())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+// posts the notification as JSON to a webhook (Slack, Discord, Zapier...)
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: String) -> Self {
+        WebhookNotifier { client: reqwest::Client::new(), url }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        self.client
+            .post(&self.url)
+            .json(notification)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: String,
+    to: String,
+}
+
+impl EmailNotifier {
+    pub fn new(host: &str, username: String, password: String, from: String, to: String) -> Result<Self, NotifyError> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::relay(host)?
+            .credentials(Credentials::new(username, password))
+            .build();
+
+        Ok(EmailNotifier { transport, from, to })
+    }
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    async fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let message = Message::builder()
+            .from(self.from.parse()?)
+            .to(self.to.parse()?)
+            .subject(&notification.subject)
+            .body(notification.body.clone())?;
+
+        self.transport.send(message).await?;
+
+        Ok(())
+    }
+}
+
+// in-app is always on; email and webhook are enabled by their env vars
+pub fn notifiers_from_env(pool: PgPool) -> Vec<Box<dyn Notifier>> {
+    let mut notifiers: Vec<Box<dyn Notifier>> = vec![Box::new(InAppNotifier::new(pool))];
+
+    if let Ok(url) = env::var("ADMIN_WEBHOOK_URL") {
+        notifiers.push(Box::new(WebhookNotifier::new(url)));
+    }
+
+    if let (Ok(host), Ok(to)) = (env::var("SMTP_HOST"), env::var("ADMIN_EMAIL")) {
+        let username = env::var("SMTP_USERNAME").unwrap_or_default();
+        let password = env::var("SMTP_PASSWORD").unwrap_or_default();
+        let from = env::var("SMTP_FROM").unwrap_or_else(|_| "no-reply@easybuy.local".to_string());
+
+        match EmailNotifier::new(&host, username, password, from, to) {
+            Ok(notifier) => notifiers.push(Box::new(notifier)),
+            Err(e) => eprintln!("❌ Email notifications disabled: {:?}", e),
+        }
+    }
+
+    notifiers
+}
+
+// deliver through every channel; one failing channel doesn't stop the others
+pub async fn notify_all(notifiers: &[Box<dyn Notifier>], notification: &Notification) -> bool {
+    let mut delivered = false;
+    for notifier in notifiers {
+        match notifier.notify(notification).await {
+            Ok(()) => delivered = true,
+            Err(e) => eprintln!("❌ {} notification failed: {:?}", notifier.name(), e),
+        }
+    }
+    delivered
+}
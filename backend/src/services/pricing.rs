@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDateTime, Utc};
+use sqlx::{PgConnection, PgPool, QueryBuilder};
+use uuid::Uuid;
+
+use crate::models::pricing::{
+    PriceHistoryEntry, PricingDetails, ProductPricing, ScheduledPriceChange, SchedulePriceChange, SetPricing,
+};
+use crate::pagination::{Keyed, PageRequest, Paged, SortDirection, SortKey};
+use crate::models::history::HistoryEntity;
+use crate::services::history::{lock_product_at, saved_version, set_actor, Versioned};
+
+pub const PRICE_HISTORY_SORTS: &[SortKey] =
+    &[SortKey { name: "changed_at", expr: "changed_at", sql_type: "timestamp", direction: SortDirection::Desc }];
+
+const PRICING_COLUMNS: &str = "id AS product_id, price, compare_at_price, sale_price, sale_starts_at, sale_ends_at";
+
+// pricing of several products in one round trip, keyed by product id
+pub async fn pricing_for_products(
+    conn: &mut PgConnection,
+    product_ids: &[Uuid],
+) -> Result<HashMap<Uuid, ProductPricing>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, ProductPricing>(&format!(
+        "SELECT {} FROM products WHERE id = ANY($1)",
+        PRICING_COLUMNS
+    ))
+    .bind(product_ids)
+    .fetch_all(conn)
+    .await?;
+
+    Ok(rows.into_iter().map(|pricing| (pricing.product_id, pricing)).collect())
+}
+
+async fn pending_changes(conn: &mut PgConnection, product_id: Uuid) -> Result<Vec<ScheduledPriceChange>, sqlx::Error> {
+    sqlx::query_as::<_, ScheduledPriceChange>(
+        r#"
+        SELECT id, product_id, price, apply_at, status, created_by, created_at, applied_at
+        FROM scheduled_price_changes
+        WHERE product_id = $1 AND status = 'pending'
+        ORDER BY apply_at
+        "#,
+    )
+    .bind(product_id)
+    .fetch_all(conn)
+    .await
+}
+
+// RowNotFound for missing and trashed products
+pub async fn get_pricing(pool: &PgPool, id: Uuid) -> Result<PricingDetails, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    let pricing = sqlx::query_as::<_, ProductPricing>(&format!(
+        "SELECT {} FROM products WHERE id = $1 AND deleted_at IS NULL",
+        PRICING_COLUMNS
+    ))
+    .bind(id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let now = Utc::now().naive_utc();
+    Ok(PricingDetails {
+        display: pricing.display(now),
+        on_sale: pricing.sale_active(now),
+        pricing,
+        scheduled_changes: pending_changes(&mut conn, id).await?,
+    })
+}
+
+// replaces the compare-at price and the sale; RowNotFound for missing and trashed products
+pub async fn set_pricing(
+    pool: &PgPool,
+    id: Uuid,
+    pricing: &SetPricing,
+    expected_version: Option<i32>,
+    actor_id: Option<Uuid>,
+) -> Result<Versioned<PricingDetails>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    set_actor(&mut tx, actor_id).await?;
+
+    if !lock_product_at(&mut tx, id, expected_version).await? {
+        return Ok(Versioned::Stale);
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE products
+        SET compare_at_price = $1, sale_price = $2, sale_starts_at = $3, sale_ends_at = $4, updated_at = $5
+        WHERE id = $6 AND deleted_at IS NULL
+        "#,
+    )
+    .bind(&pricing.compare_at_price)
+    .bind(&pricing.sale_price)
+    .bind(pricing.sale_starts_at)
+    .bind(pricing.sale_ends_at)
+    .bind(Utc::now().naive_utc())
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+    let version = saved_version(&mut tx, HistoryEntity::Product, id).await?;
+
+    tx.commit().await?;
+
+    Ok(Versioned::Saved(get_pricing(pool, id).await?, version))
+}
+
+// RowNotFound for missing and trashed products
+pub async fn schedule_price_change(
+    pool: &PgPool,
+    product_id: Uuid,
+    change: &SchedulePriceChange,
+    created_by: Option<Uuid>,
+) -> Result<ScheduledPriceChange, sqlx::Error> {
+    sqlx::query_as::<_, ScheduledPriceChange>(
+        r#"
+        INSERT INTO scheduled_price_changes (id, product_id, price, apply_at, created_by, created_at)
+        SELECT $1, p.id, $3, $4, $5, $6
+        FROM products p
+        WHERE p.id = $2 AND p.deleted_at IS NULL
+        RETURNING id, product_id, price, apply_at, status, created_by, created_at, applied_at
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(product_id)
+    .bind(&change.price)
+    .bind(change.apply_at)
+    .bind(created_by)
+    .bind(Utc::now().naive_utc())
+    .fetch_one(pool)
+    .await
+}
+
+pub const PRICE_SCHEDULE_SORTS: &[SortKey] =
+    &[SortKey { name: "apply_at", expr: "apply_at", sql_type: "timestamp", direction: SortDirection::Desc }];
+
+// every change scheduled for the product, applied and cancelled ones included
+pub async fn list_price_schedule(
+    pool: &PgPool,
+    product_id: Uuid,
+    page: &PageRequest<'_>,
+) -> Result<Paged<ScheduledPriceChange>, sqlx::Error> {
+    let mut builder =
+        QueryBuilder::new("SELECT id, product_id, price, apply_at, status, created_by, created_at, applied_at");
+    page.push_sort_columns(&mut builder, "id");
+    builder.push(" FROM scheduled_price_changes WHERE product_id = ").push_bind(product_id);
+    page.push_cursor_filter(&mut builder, "id");
+    page.push_order_and_limit(&mut builder, "id");
+
+    let rows = builder.build_query_as::<Keyed<ScheduledPriceChange>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM scheduled_price_changes WHERE product_id = $1")
+        .bind(product_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(page.finish(rows, total))
+}
+
+// false when the change doesn't exist or is no longer pending
+pub async fn cancel_price_change(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE scheduled_price_changes SET status = 'cancelled' WHERE id = $1 AND status = 'pending'")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn list_price_history(
+    pool: &PgPool,
+    product_id: Uuid,
+    page: &PageRequest<'_>,
+) -> Result<Paged<PriceHistoryEntry>, sqlx::Error> {
+    let mut builder = QueryBuilder::new(
+        "SELECT price, compare_at_price, sale_price, sale_starts_at, sale_ends_at, actor_id, changed_at",
+    );
+    page.push_sort_columns(&mut builder, "id");
+    builder.push(" FROM price_history WHERE product_id = ").push_bind(product_id);
+    page.push_cursor_filter(&mut builder, "id");
+    page.push_order_and_limit(&mut builder, "id");
+
+    let rows = builder.build_query_as::<Keyed<PriceHistoryEntry>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM price_history WHERE product_id = $1")
+        .bind(product_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(page.finish(rows, total))
+}
+
+// sets the base price of every change that has come due, oldest first, each in its own
+// transaction so the history credits whoever scheduled it. Changes to trashed products
+// still apply, the price is there if the product is restored
+pub async fn apply_due_price_changes(pool: &PgPool, now: NaiveDateTime) -> Result<u64, sqlx::Error> {
+    let mut applied = 0;
+    loop {
+        let mut tx = pool.begin().await?;
+        let due: Option<(Uuid, Uuid, BigDecimal, Option<Uuid>)> = sqlx::query_as(
+            r#"
+            SELECT id, product_id, price, created_by
+            FROM scheduled_price_changes
+            WHERE status = 'pending' AND apply_at <= $1
+            ORDER BY apply_at
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+            "#,
+        )
+        .bind(now)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some((id, product_id, price, created_by)) = due else {
+            return Ok(applied);
+        };
+
+        set_actor(&mut tx, created_by).await?;
+        sqlx::query("UPDATE products SET price = $1, updated_at = $2 WHERE id = $3")
+            .bind(price)
+            .bind(now)
+            .bind(product_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE scheduled_price_changes SET status = 'applied', applied_at = $1 WHERE id = $2")
+            .bind(now)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        applied += 1;
+    }
+}
+
+pub fn spawn_price_scheduler(pool: PgPool, every: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        loop {
+            interval.tick().await;
+            match apply_due_price_changes(&pool, Utc::now().naive_utc()).await {
+                Ok(0) => {}
+                Ok(applied) => println!("💲 Applied {} scheduled price changes", applied),
+                Err(e) => eprintln!("❌ Failed to apply scheduled price changes: {:?}", e),
+            }
+        }
+    });
+}
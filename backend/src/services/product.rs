@@ -1,30 +1,157 @@
-use crate::models::product::{CreateProduct, Product, UpdateProduct};
-use sqlx::PgPool;
+use std::collections::HashMap;
+
+use crate::models::category::CategorySummary;
+use crate::models::product::{CreateProduct, Product, ProductDetails, UpdateProduct};
+use crate::models::history::HistoryEntity;
+use crate::models::pricing::{PriceDisplay, ProductPricing};
+use crate::services::history::{lock_product_at, saved_version, set_actor};
+use crate::services::attribute::{applicable_definitions, set_tags, tags_for_products, validate_attributes};
+use crate::services::inventory::{receive_initial_stock, set_stock_level, InventoryError};
+use crate::services::currency::Converter;
+use crate::services::pricing::pricing_for_products;
+use crate::services::reservation::reserved_quantities;
+use crate::services::slug::{record_slug_change, release_slug, unique_slug, SlugOwner};
+use crate::services::variant::{available_to_sell, variant_details_for_products};
+use crate::services::warehouse::locations_for_products;
+use bigdecimal::BigDecimal;
+use serde_json::{Map, Value};
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 use chrono::Utc;
 
-pub async fn create_product(pool: &PgPool, new_product: CreateProduct) -> Result<Product, sqlx::Error> {
+#[derive(Debug)]
+pub enum ProductError {
+    NotFound,
+    CategoryNotFound, // missing or in the trash
+    InvalidAttributes(String),
+    VersionMismatch, // edited since the version the caller read
+    InsufficientStock, // a lower stock level the locations can't give up
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for ProductError {
+    fn from(err: sqlx::Error) -> Self {
+        match err {
+            sqlx::Error::RowNotFound => ProductError::NotFound,
+            err => ProductError::Database(err),
+        }
+    }
+}
+
+impl From<InventoryError> for ProductError {
+    fn from(err: InventoryError) -> Self {
+        match err {
+            InventoryError::NotFound => ProductError::NotFound,
+            InventoryError::InsufficientStock => ProductError::InsufficientStock,
+            InventoryError::Database(err) => ProductError::Database(err),
+        }
+    }
+}
+
+// every id must be a live category; the rows stay share-locked so none is deleted before commit
+pub async fn check_categories(conn: &mut PgConnection, ids: &[Uuid]) -> Result<(), ProductError> {
+    let mut wanted = ids.to_vec();
+    wanted.sort();
+    wanted.dedup();
+
+    let found: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM categories WHERE id = ANY($1) AND deleted_at IS NULL FOR SHARE")
+        .bind(&wanted)
+        .fetch_all(conn)
+        .await?;
+
+    if found.len() != wanted.len() {
+        return Err(ProductError::CategoryNotFound);
+    }
+
+    Ok(())
+}
+
+// replace the secondary categories; the primary one is never repeated among them
+pub async fn set_secondary_categories(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    primary: Option<Uuid>,
+    ids: &[Uuid],
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM product_categories WHERE product_id = $1")
+        .bind(product_id)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO product_categories (product_id, category_id)
+        SELECT $1, category_id FROM UNNEST($2::uuid[]) AS ids(category_id)
+        WHERE category_id IS DISTINCT FROM $3
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(product_id)
+    .bind(ids)
+    .bind(primary)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn create_product(
+    pool: &PgPool,
+    new_product: CreateProduct,
+    actor_id: Option<Uuid>,
+) -> Result<Product, ProductError> {
     let created_at = Utc::now().naive_utc();
     let updated_at = created_at;
+    let mut tx = pool.begin().await?;
+    set_actor(&mut tx, actor_id).await?;
+
+    let slug = match new_product.slug {
+        Some(slug) => {
+            release_slug(&mut tx, SlugOwner::Product, &slug).await?;
+            slug
+        }
+        None => unique_slug(&mut tx, SlugOwner::Product, &new_product.name).await?,
+    };
+
+    let categories: Vec<Uuid> = new_product.category_id.iter().chain(&new_product.secondary_category_ids).copied().collect();
+    check_categories(&mut tx, &categories).await?;
 
-    let rec = sqlx::query_as_unchecked!(
+    let definitions = applicable_definitions(&mut tx, &categories).await?;
+    validate_attributes(&definitions, &new_product.attributes).map_err(ProductError::InvalidAttributes)?;
+
+    // stock starts at zero; the opening quantity is received through the ledger below
+    let mut rec = sqlx::query_as_unchecked!(
         Product,
         r#"
-        INSERT INTO products (id, name, description, price, stock_quantity, created_at, updated_at)
-        VALUES ($1, $2, $3, $4, $5, $6, $7)
-        RETURNING id, name, description, price, stock_quantity, created_at, updated_at
+        INSERT INTO products (id, name, slug, sku, description, price, stock_quantity, category_id, attributes, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        RETURNING id, name, slug, sku, description, price, stock_quantity, category_id, attributes, created_at, updated_at
         "#,
         Uuid::new_v4(), 
         new_product.name, 
+        slug,
+        new_product.sku,
         new_product.description, 
         new_product.price,
-        new_product.stock_quantity,
+        0,
+        new_product.category_id,
+        Value::Object(new_product.attributes),
         created_at,
         updated_at
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await?;
 
+    set_secondary_categories(&mut tx, rec.id, rec.category_id, &new_product.secondary_category_ids).await?;
+    set_tags(&mut tx, rec.id, &new_product.tags).await?;
+
+    if new_product.stock_quantity > 0 {
+        receive_initial_stock(&mut tx, rec.id, None, new_product.stock_quantity).await?;
+        rec.stock_quantity = new_product.stock_quantity;
+    }
+
+    tx.commit().await?;
+
     Ok(rec)
 }
 
@@ -34,10 +161,34 @@ pub async fn update_product(
     pool: &PgPool,
     id: Uuid,
     update: UpdateProduct,
-) -> Result<Product, sqlx::Error> {
+    expected_version: Option<i32>,
+    actor_id: Option<Uuid>,
+) -> Result<(Product, i32), ProductError> {
     let current_time = Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+    set_actor(&mut tx, actor_id).await?;
+
+    if !lock_product_at(&mut tx, id, expected_version).await? {
+        return Err(ProductError::VersionMismatch);
+    }
+
+    // stock is never overwritten directly, the difference is recorded as an adjustment
+    if let Some(stock_quantity) = update.stock_quantity {
+        set_stock_level(&mut tx, id, None, stock_quantity, actor_id, "product update").await?;
+    }
+
+    if let Some(slug) = &update.slug {
+        let current: String = sqlx::query_scalar("SELECT slug FROM products WHERE id = $1 AND deleted_at IS NULL FOR UPDATE")
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+        record_slug_change(&mut tx, SlugOwner::Product, id, &current, slug).await?;
+    }
+
+    let categories: Vec<Uuid> = update.category_id.iter().chain(update.secondary_category_ids.iter().flatten()).copied().collect();
+    check_categories(&mut tx, &categories).await?;
 
-    let product = sqlx::query_as!(
+    let mut product = sqlx::query_as!(
         Product,
         r#"
         UPDATE products
@@ -45,50 +196,224 @@ pub async fn update_product(
             name = COALESCE($1, name),
             description = COALESCE($2, description),
             price = COALESCE($3, price),
-            stock_quantity = COALESCE($4, stock_quantity),
-            updated_at = $5
-        WHERE id = $6
-        RETURNING id, name, description, price, stock_quantity, created_at, updated_at
+            updated_at = $4,
+            slug = COALESCE($6, slug),
+            category_id = COALESCE($7, category_id),
+            sku = COALESCE($8, sku)
+        WHERE id = $5 AND deleted_at IS NULL
+        RETURNING id, name, slug, sku, description, price, stock_quantity, category_id, attributes, created_at, updated_at
         "#,
         update.name,
         update.description,
         update.price,
-        update.stock_quantity,
         current_time,
-        id
+        id,
+        update.slug,
+        update.category_id,
+        update.sku
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await?;
 
-    Ok(product)
+    match &update.secondary_category_ids {
+        Some(ids) => set_secondary_categories(&mut tx, id, product.category_id, ids).await?,
+        // a new primary category stops being a secondary one
+        None => {
+            sqlx::query("DELETE FROM product_categories WHERE product_id = $1 AND category_id = $2")
+                .bind(id)
+                .bind(product.category_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+
+    if let Some(tags) = &update.tags {
+        set_tags(&mut tx, id, tags).await?;
+    }
+
+    if let Some(attributes) = update.attributes {
+        let (removed, set): (Vec<_>, Vec<_>) = attributes.into_iter().partition(|(_, value)| value.is_null());
+        let set: Map<String, Value> = set.into_iter().collect();
+        let removed: Vec<String> = removed.into_iter().map(|(key, _)| key).collect();
+
+        // only the keys being set are checked, so a category change doesn't strand older values
+        let categories: Vec<Uuid> = sqlx::query_scalar(
+            "SELECT category_id FROM products WHERE id = $1 AND category_id IS NOT NULL UNION SELECT category_id FROM product_categories WHERE product_id = $1",
+        )
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?;
+        let definitions = applicable_definitions(&mut tx, &categories).await?;
+        validate_attributes(&definitions, &set).map_err(ProductError::InvalidAttributes)?;
+
+        product.attributes = sqlx::query_scalar("UPDATE products SET attributes = (attributes || $1) - $2::text[] WHERE id = $3 RETURNING attributes")
+            .bind(Value::Object(set))
+            .bind(&removed)
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+    }
+
+    let version = saved_version(&mut tx, HistoryEntity::Product, id).await?;
+
+    tx.commit().await?;
+
+    Ok((product, version))
 }
 
 
 
 // delete a product from data base (hard delete )
-pub async fn delete_product(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+pub async fn delete_product(pool: &PgPool, id: Uuid, actor_id: Option<Uuid>) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    set_actor(&mut tx, actor_id).await?;
+
     sqlx::query!(
         "DELETE FROM products WHERE id = $1",
         id
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
 
+    tx.commit().await?;
+
     Ok(())
 }
 
 
-//soft delete 
+//soft delete: the product stays in the trash until restored or purged
 
-pub async fn soft_delete_product(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+pub async fn soft_delete_product(pool: &PgPool, id: Uuid, actor_id: Option<Uuid>) -> Result<(), sqlx::Error> {
     let now = Utc::now().naive_utc();
-    sqlx::query!(
-        "UPDATE products SET deleted_at = $1 WHERE id = $2",
+    let mut tx = pool.begin().await?;
+    set_actor(&mut tx, actor_id).await?;
+
+    let result = sqlx::query!(
+        "UPDATE products SET deleted_at = $1 WHERE id = $2 AND deleted_at IS NULL",
         now,
         id
     )
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
 
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+
+    tx.commit().await?;
+
     Ok(())
 }
+
+
+// embed variants into products for the read endpoints, priced as of now in the
+// converter's currency
+pub async fn with_details(
+    pool: &PgPool,
+    products: Vec<Product>,
+    converter: &Converter,
+) -> Result<Vec<ProductDetails>, sqlx::Error> {
+    let product_ids: Vec<Uuid> = products.iter().map(|p| p.id).collect();
+    let now = Utc::now().naive_utc();
+    let pricing = pricing_for_products(&mut *pool.acquire().await?, &product_ids).await?;
+    let mut pricing: HashMap<Uuid, PriceDisplay> = products
+        .iter()
+        .map(|p| {
+            let display = match pricing.get(&p.id) {
+                Some(pricing) => pricing.display(now),
+                None => ProductPricing::base_only(p.id, p.price.clone()).display(now),
+            };
+            (p.id, display)
+        })
+        .collect();
+    let effective_prices: HashMap<Uuid, BigDecimal> =
+        pricing.iter().map(|(id, display)| (*id, display.effective_price.clone())).collect();
+    let reserved = reserved_quantities(pool, &product_ids).await?;
+    let mut locations = locations_for_products(pool, &product_ids).await?;
+    let mut variants = variant_details_for_products(pool, &effective_prices, &reserved, &mut locations).await?;
+    let (categories, mut secondary) = categories_for_products(pool, &products).await?;
+    let mut tags = tags_for_products(pool, &product_ids).await?;
+
+    Ok(products
+        .into_iter()
+        .map(|mut product| {
+            let available_quantity = available_to_sell(product.stock_quantity, reserved.get(&(product.id, None)));
+            let locations = locations.remove(&(product.id, None)).unwrap_or_default();
+            let mut variants = variants.remove(&product.id).unwrap_or_default();
+            let category = product.category_id.and_then(|id| categories.get(&id).cloned());
+            let secondary_categories = secondary.remove(&product.id).unwrap_or_default();
+            let tags = tags.remove(&product.id).unwrap_or_default();
+            let base = pricing.remove(&product.id).expect("priced above");
+
+            let effective_price = converter.convert(&base.effective_price);
+            let pricing = PriceDisplay {
+                // rounding can close a small discount
+                original_price: base.original_price.map(|p| converter.convert(&p)).filter(|p| *p > effective_price),
+                effective_price,
+                sale_ends_at: base.sale_ends_at,
+                currency: converter.code().to_string(),
+            };
+            product.price = converter.convert(&product.price);
+            for variant in &mut variants {
+                variant.price = converter.convert(&variant.price);
+            }
+
+            ProductDetails {
+                product,
+                pricing,
+                available_quantity,
+                category,
+                secondary_categories,
+                tags,
+                locations,
+                variants,
+            }
+        })
+        .collect())
+}
+
+// live categories keyed by id, and each product's secondary categories sorted by name
+async fn categories_for_products(
+    pool: &PgPool,
+    products: &[Product],
+) -> Result<(HashMap<Uuid, CategorySummary>, HashMap<Uuid, Vec<CategorySummary>>), sqlx::Error> {
+    let product_ids: Vec<Uuid> = products.iter().map(|p| p.id).collect();
+    let links: Vec<(Uuid, Uuid)> = sqlx::query_as(
+        "SELECT product_id, category_id FROM product_categories WHERE product_id = ANY($1)",
+    )
+    .bind(&product_ids)
+    .fetch_all(pool)
+    .await?;
+
+    let category_ids: Vec<Uuid> =
+        products.iter().filter_map(|p| p.category_id).chain(links.iter().map(|(_, c)| *c)).collect();
+    let categories: HashMap<Uuid, CategorySummary> = sqlx::query_as::<_, CategorySummary>(
+        "SELECT id, name, slug FROM categories WHERE id = ANY($1) AND deleted_at IS NULL",
+    )
+    .bind(&category_ids)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|c| (c.id, c))
+    .collect();
+
+    let mut secondary: HashMap<Uuid, Vec<CategorySummary>> = HashMap::new();
+    for (product_id, category_id) in links {
+        if let Some(category) = categories.get(&category_id) {
+            secondary.entry(product_id).or_default().push(category.clone());
+        }
+    }
+    for list in secondary.values_mut() {
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    Ok((categories, secondary))
+}
+
+// a live product by its current slug
+pub async fn product_by_slug(pool: &PgPool, slug: &str) -> Result<Option<Product>, sqlx::Error> {
+    sqlx::query_as::<_, Product>("SELECT * FROM products WHERE slug = $1 AND deleted_at IS NULL")
+        .bind(slug)
+        .fetch_optional(pool)
+        .await
+}
@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDateTime, Utc};
+use sqlx::{PgConnection, PgPool, QueryBuilder};
+use uuid::Uuid;
+
+use crate::models::cart::BaseCartLine;
+use crate::models::currency::Currency;
+use crate::models::promotion::{
+    DiscountLine, DiscountOutcome, Promotion, PromotionCandidate, PromotionDetails, PromotionInput,
+};
+use crate::pagination::{Keyed, PageRequest, Paged, SortDirection, SortKey};
+use crate::services::promotion_engine::evaluate;
+
+pub const PROMOTION_SORTS: &[SortKey] = &[
+    SortKey { name: "created_at", expr: "created_at", sql_type: "timestamp", direction: SortDirection::Desc },
+    SortKey { name: "name", expr: "name", sql_type: "text", direction: SortDirection::Asc },
+    SortKey { name: "priority", expr: "priority", sql_type: "integer", direction: SortDirection::Desc },
+];
+
+const PROMOTION_COLUMNS: &str = "id, name, code, kind, value, buy_quantity, get_quantity, starts_at, ends_at, \
+    usage_limit, per_user_limit, min_subtotal, stackable, priority, active, created_at, updated_at";
+
+#[derive(sqlx::FromRow)]
+struct CandidateRow {
+    #[sqlx(flatten)]
+    promotion: Promotion,
+    product_ids: Vec<Uuid>,
+    category_ids: Vec<Uuid>,
+    redemptions: i64,
+    user_redemptions: i64,
+}
+
+pub async fn list_promotions(pool: &PgPool, page: &PageRequest<'_>) -> Result<Paged<Promotion>, sqlx::Error> {
+    let mut builder = QueryBuilder::new(format!("SELECT {}", PROMOTION_COLUMNS));
+    page.push_sort_columns(&mut builder, "id");
+    builder.push(" FROM promotions WHERE TRUE");
+    page.push_cursor_filter(&mut builder, "id");
+    page.push_order_and_limit(&mut builder, "id");
+
+    let rows = builder.build_query_as::<Keyed<Promotion>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM promotions").fetch_one(pool).await?;
+
+    Ok(page.finish(rows, total))
+}
+
+// RowNotFound when there's no such promotion
+pub async fn get_promotion(conn: &mut PgConnection, id: Uuid) -> Result<PromotionDetails, sqlx::Error> {
+    let promotion = sqlx::query_as::<_, Promotion>(&format!("SELECT {} FROM promotions WHERE id = $1", PROMOTION_COLUMNS))
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await?;
+    let (product_ids, category_ids, redemptions): (Vec<Uuid>, Vec<Uuid>, i64) = sqlx::query_as(
+        r#"
+        SELECT ARRAY(SELECT product_id FROM promotion_products WHERE promotion_id = $1 ORDER BY product_id),
+               ARRAY(SELECT category_id FROM promotion_categories WHERE promotion_id = $1 ORDER BY category_id),
+               (SELECT COUNT(*) FROM promotion_redemptions WHERE promotion_id = $1)
+        "#,
+    )
+    .bind(id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(PromotionDetails { promotion, product_ids, category_ids, redemptions })
+}
+
+async fn set_restrictions(conn: &mut PgConnection, id: Uuid, input: &PromotionInput) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM promotion_products WHERE promotion_id = $1").bind(id).execute(&mut *conn).await?;
+    sqlx::query("DELETE FROM promotion_categories WHERE promotion_id = $1").bind(id).execute(&mut *conn).await?;
+    sqlx::query("INSERT INTO promotion_products (promotion_id, product_id) SELECT $1, unnest($2::uuid[]) ON CONFLICT DO NOTHING")
+        .bind(id)
+        .bind(&input.product_ids)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("INSERT INTO promotion_categories (promotion_id, category_id) SELECT $1, unnest($2::uuid[]) ON CONFLICT DO NOTHING")
+        .bind(id)
+        .bind(&input.category_ids)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+// a 23505 means the code is taken, a 23503 an unknown product or category
+pub async fn create_promotion(
+    pool: &PgPool,
+    input: &PromotionInput,
+    created_by: Option<Uuid>,
+) -> Result<PromotionDetails, sqlx::Error> {
+    let id = Uuid::new_v4();
+    let now = Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO promotions (id, name, code, kind, value, buy_quantity, get_quantity, starts_at, ends_at,
+            usage_limit, per_user_limit, min_subtotal, stackable, priority, active, created_by, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $17)
+        "#,
+    )
+    .bind(id)
+    .bind(input.name.trim())
+    .bind(&input.code)
+    .bind(input.kind)
+    .bind(&input.value)
+    .bind(input.buy_quantity)
+    .bind(input.get_quantity)
+    .bind(input.starts_at)
+    .bind(input.ends_at)
+    .bind(input.usage_limit)
+    .bind(input.per_user_limit)
+    .bind(&input.min_subtotal)
+    .bind(input.stackable)
+    .bind(input.priority)
+    .bind(input.active.unwrap_or(true))
+    .bind(created_by)
+    .bind(now)
+    .execute(&mut *tx)
+    .await?;
+
+    set_restrictions(&mut tx, id, input).await?;
+    let details = get_promotion(&mut tx, id).await?;
+    tx.commit().await?;
+
+    Ok(details)
+}
+
+// replaces every field and restriction; RowNotFound when there's no such promotion
+pub async fn update_promotion(pool: &PgPool, id: Uuid, input: &PromotionInput) -> Result<PromotionDetails, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query(
+        r#"
+        UPDATE promotions
+        SET name = $2, code = $3, kind = $4, value = $5, buy_quantity = $6, get_quantity = $7, starts_at = $8,
+            ends_at = $9, usage_limit = $10, per_user_limit = $11, min_subtotal = $12, stackable = $13,
+            priority = $14, active = $15, updated_at = $16
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(input.name.trim())
+    .bind(&input.code)
+    .bind(input.kind)
+    .bind(&input.value)
+    .bind(input.buy_quantity)
+    .bind(input.get_quantity)
+    .bind(input.starts_at)
+    .bind(input.ends_at)
+    .bind(input.usage_limit)
+    .bind(input.per_user_limit)
+    .bind(&input.min_subtotal)
+    .bind(input.stackable)
+    .bind(input.priority)
+    .bind(input.active.unwrap_or(true))
+    .bind(Utc::now().naive_utc())
+    .execute(&mut *tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+
+    set_restrictions(&mut tx, id, input).await?;
+    let details = get_promotion(&mut tx, id).await?;
+    tx.commit().await?;
+
+    Ok(details)
+}
+
+// false when there's no such promotion; a 23503 means it has been redeemed and can only be deactivated
+pub async fn delete_promotion(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM promotions WHERE id = $1").bind(id).execute(pool).await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// codes are matched case-insensitively
+pub async fn find_coupon(pool: &PgPool, code: &str) -> Result<Option<Uuid>, sqlx::Error> {
+    sqlx::query_scalar("SELECT id FROM promotions WHERE upper(code) = upper($1)")
+        .bind(code.trim())
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn add_cart_coupon(pool: &PgPool, user_id: Uuid, promotion_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO cart_coupons (user_id, promotion_id, added_at) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")
+        .bind(user_id)
+        .bind(promotion_id)
+        .bind(Utc::now().naive_utc())
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// false when the coupon wasn't on the cart
+pub async fn remove_cart_coupon(pool: &PgPool, user_id: Uuid, code: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM cart_coupons
+        WHERE user_id = $1 AND promotion_id IN (SELECT id FROM promotions WHERE upper(code) = upper($2))
+        "#,
+    )
+    .bind(user_id)
+    .bind(code.trim())
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// automatic promotions that are switched on plus the coupons on the user's cart, with their
+// restrictions and use so far. `lock` holds the promotions until the transaction ends, so
+// concurrent checkouts can't both take the last use of a limited coupon
+async fn candidates(conn: &mut PgConnection, user_id: Uuid, lock: bool) -> Result<Vec<PromotionCandidate>, sqlx::Error> {
+    let query = format!(
+        r#"
+        SELECT p.id, p.name, p.code, p.kind, p.value, p.buy_quantity, p.get_quantity, p.starts_at, p.ends_at,
+               p.usage_limit, p.per_user_limit, p.min_subtotal, p.stackable, p.priority, p.active, p.created_at, p.updated_at,
+               ARRAY(SELECT pp.product_id FROM promotion_products pp WHERE pp.promotion_id = p.id) AS product_ids,
+               ARRAY(
+                   WITH RECURSIVE tree AS (
+                       SELECT pc.category_id AS id FROM promotion_categories pc WHERE pc.promotion_id = p.id
+                       UNION
+                       SELECT c.id FROM categories c JOIN tree t ON c.parent_id = t.id WHERE c.deleted_at IS NULL
+                   )
+                   SELECT id FROM tree
+               ) AS category_ids,
+               (SELECT COUNT(*) FROM promotion_redemptions r WHERE r.promotion_id = p.id) AS redemptions,
+               (SELECT COUNT(*) FROM promotion_redemptions r WHERE r.promotion_id = p.id AND r.user_id = $1) AS user_redemptions
+        FROM promotions p
+        WHERE (p.code IS NULL AND p.active) OR p.id IN (SELECT promotion_id FROM cart_coupons WHERE user_id = $1)
+        ORDER BY p.priority DESC, p.created_at
+        {}
+        "#,
+        if lock { "FOR UPDATE OF p" } else { "" }
+    );
+    let rows = sqlx::query_as::<_, CandidateRow>(&query).bind(user_id).fetch_all(&mut *conn).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| PromotionCandidate {
+            promotion: row.promotion,
+            product_ids: row.product_ids.into_iter().collect(),
+            category_ids: row.category_ids.into_iter().collect(),
+            redemptions: row.redemptions,
+            user_redemptions: row.user_redemptions,
+        })
+        .collect())
+}
+
+// the promotions that apply to the user's cart, priced in the base currency
+pub async fn discounts_for_cart(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    lines: &[BaseCartLine],
+    now: NaiveDateTime,
+    base_currency: &Currency,
+    lock: bool,
+) -> Result<DiscountOutcome, sqlx::Error> {
+    let product_ids: Vec<Uuid> = lines.iter().map(|l| l.item.product_id).collect();
+    let categories: HashMap<Uuid, Vec<Uuid>> = sqlx::query_as::<_, (Uuid, Vec<Uuid>)>(
+        r#"
+        SELECT p.id,
+               array_remove(ARRAY[p.category_id] || ARRAY(SELECT pc.category_id FROM product_categories pc WHERE pc.product_id = p.id), NULL)
+        FROM products p
+        WHERE p.id = ANY($1)
+        "#,
+    )
+    .bind(&product_ids)
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .collect();
+
+    let lines: Vec<DiscountLine> = lines
+        .iter()
+        .map(|line| DiscountLine {
+            product_id: line.item.product_id,
+            category_ids: categories.get(&line.item.product_id).cloned().unwrap_or_default(), // variants share a product
+            unit_price: line.unit_price.clone(),
+            quantity: line.item.quantity,
+        })
+        .collect();
+    let candidates = candidates(conn, user_id, lock).await?;
+
+    Ok(evaluate(&lines, &candidates, now, base_currency))
+}
+
+// once a checkout completes: one redemption per promotion that applied, and the cart's coupons are used up
+pub async fn record_redemptions(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    checkout_id: Uuid,
+    outcome: &DiscountOutcome,
+) -> Result<(), sqlx::Error> {
+    let now = Utc::now().naive_utc();
+    for discount in &outcome.applied {
+        sqlx::query(
+            r#"
+            INSERT INTO promotion_redemptions (promotion_id, user_id, checkout_id, discount, redeemed_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (promotion_id, checkout_id) DO NOTHING
+            "#,
+        )
+        .bind(discount.promotion_id)
+        .bind(user_id)
+        .bind(checkout_id)
+        .bind(&discount.amount)
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    sqlx::query("DELETE FROM cart_coupons WHERE user_id = $1").bind(user_id).execute(&mut *conn).await?;
+
+    Ok(())
+}
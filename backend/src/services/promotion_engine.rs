@@ -0,0 +1,584 @@
+// Decides which promotions apply to a cart and for how much. Everything it needs is
+// passed in and nothing is read or written, so the same inputs always give the same
+// discounts, whether it's pricing the cart page or completing a checkout
+use std::cmp::Ordering;
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDateTime;
+
+use crate::models::currency::Currency;
+use crate::models::promotion::{
+    AppliedDiscount, DiscountLine, DiscountOutcome, PromotionCandidate, PromotionKind, RejectedCoupon,
+};
+use crate::services::currency::round;
+
+struct Offer<'a> {
+    candidate: &'a PromotionCandidate,
+    amount: BigDecimal,
+    weights: Vec<BigDecimal>, // what it takes off each line, before rounding; zero where it doesn't apply
+}
+
+// Free shipping always combines with everything else. Other discounts apply either all
+// together when they're stackable, or alone: whichever saves the customer more. The total
+// never exceeds the subtotal, and each discount only comes off the lines it applies to.
+// Amounts are in `currency`, rounded by its rules
+pub fn evaluate(
+    lines: &[DiscountLine],
+    candidates: &[PromotionCandidate],
+    now: NaiveDateTime,
+    currency: &Currency,
+) -> DiscountOutcome {
+    let subtotal = lines.iter().map(line_total).fold(BigDecimal::zero(), |sum, total| sum + total);
+
+    let mut rejected = Vec::new();
+    let mut offers = Vec::new();
+    for candidate in candidates {
+        match offer(candidate, lines, &subtotal, now, currency) {
+            Ok(weights) => {
+                let amount = round(&weights.iter().fold(BigDecimal::zero(), |sum, w| sum + w), currency);
+                offers.push(Offer { candidate, amount, weights })
+            }
+            Err(reason) => reject(&mut rejected, candidate, reason),
+        }
+    }
+    offers.sort_by(by_priority_then_amount);
+
+    let (shipping, discounts): (Vec<Offer>, Vec<Offer>) =
+        offers.into_iter().partition(|o| o.candidate.promotion.kind == PromotionKind::FreeShipping);
+    let mut shipping = shipping.into_iter();
+    let free_shipping = shipping.next();
+    for other in shipping {
+        reject(&mut rejected, other.candidate, "Shipping is already free".to_string());
+    }
+
+    let (stackable, exclusive): (Vec<Offer>, Vec<Offer>) =
+        discounts.into_iter().partition(|o| o.candidate.promotion.stackable);
+    let stacked_total = stackable.iter().fold(BigDecimal::zero(), |sum, o| sum + &o.amount);
+    let best_exclusive = exclusive
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.amount.cmp(&b.amount).then_with(|| by_priority_then_amount(b, a)))
+        .map(|(i, _)| i);
+
+    let (winners, losers) = match best_exclusive {
+        Some(i) if exclusive[i].amount > stacked_total => {
+            let mut exclusive = exclusive;
+            let winner = exclusive.remove(i);
+            exclusive.extend(stackable);
+            (vec![winner], exclusive)
+        }
+        _ => (stackable, exclusive),
+    };
+    if !losers.is_empty() {
+        let names: Vec<&str> = winners.iter().map(|o| o.candidate.promotion.name.as_str()).collect();
+        for loser in &losers {
+            reject(&mut rejected, loser.candidate, format!("Can't be combined with {}", names.join(", ")));
+        }
+    }
+
+    let mut applied = Vec::new();
+    let mut discount_total = BigDecimal::zero();
+    let mut line_discounts = vec![BigDecimal::zero(); lines.len()];
+    for winner in winners {
+        // what the discounts before it left of its lines
+        let room: Vec<BigDecimal> = lines
+            .iter()
+            .zip(&line_discounts)
+            .zip(&winner.weights)
+            .map(|((line, taken), weight)| if weight.is_zero() { BigDecimal::zero() } else { line_total(line) - taken })
+            .collect();
+        let amount = winner.amount.clone().min(room.iter().fold(BigDecimal::zero(), |sum, r| sum + r));
+
+        for (taken, share) in line_discounts.iter_mut().zip(share_out(&amount, &winner.weights, &room, currency)) {
+            *taken += share;
+        }
+        discount_total += &amount;
+        applied.push(applied_discount(winner.candidate, amount));
+    }
+    if let Some(offer) = &free_shipping {
+        applied.push(applied_discount(offer.candidate, BigDecimal::zero()));
+    }
+
+    DiscountOutcome { applied, rejected, discount_total, free_shipping: free_shipping.is_some(), line_discounts }
+}
+
+// `amount` shared out in proportion to `weights`, the last weighted line taking what rounding
+// leaves over; no line gets more than its `room`, and what that holds back goes to the others
+fn share_out(amount: &BigDecimal, weights: &[BigDecimal], room: &[BigDecimal], currency: &Currency) -> Vec<BigDecimal> {
+    let mut shares = vec![BigDecimal::zero(); weights.len()];
+    let total_weight = weights.iter().fold(BigDecimal::zero(), |sum, w| sum + w);
+    if total_weight.is_zero() {
+        return shares;
+    }
+
+    let last = weights.iter().rposition(|w| !w.is_zero());
+    let mut remaining = amount.clone();
+    for (i, weight) in weights.iter().enumerate().filter(|(_, w)| !w.is_zero()) {
+        let share = if Some(i) == last {
+            remaining.clone()
+        } else {
+            round(&(amount * weight / &total_weight), currency).min(remaining.clone())
+        };
+        shares[i] = share.min(room[i].clone());
+        remaining -= &shares[i];
+    }
+    for (i, weight) in weights.iter().enumerate() {
+        if remaining.is_zero() {
+            break;
+        }
+        if !weight.is_zero() {
+            let extra = (&room[i] - &shares[i]).min(remaining.clone());
+            shares[i] += &extra;
+            remaining -= extra;
+        }
+    }
+    shares
+}
+
+fn line_total(line: &DiscountLine) -> BigDecimal {
+    &line.unit_price * BigDecimal::from(line.quantity)
+}
+
+fn by_priority_then_amount(a: &Offer, b: &Offer) -> Ordering {
+    b.candidate.promotion.priority.cmp(&a.candidate.promotion.priority).then_with(|| b.amount.cmp(&a.amount))
+}
+
+// only coupons the customer entered are reported; automatic promotions that don't apply stay quiet
+fn reject(rejected: &mut Vec<RejectedCoupon>, candidate: &PromotionCandidate, reason: String) {
+    if let Some(code) = &candidate.promotion.code {
+        rejected.push(RejectedCoupon { code: code.clone(), reason });
+    }
+}
+
+fn applied_discount(candidate: &PromotionCandidate, amount: BigDecimal) -> AppliedDiscount {
+    let promotion = &candidate.promotion;
+    AppliedDiscount {
+        promotion_id: promotion.id,
+        name: promotion.name.clone(),
+        code: promotion.code.clone(),
+        kind: promotion.kind,
+        amount,
+    }
+}
+
+fn qualifies(candidate: &PromotionCandidate, line: &DiscountLine) -> bool {
+    if candidate.product_ids.is_empty() && candidate.category_ids.is_empty() {
+        return true;
+    }
+    candidate.product_ids.contains(&line.product_id)
+        || line.category_ids.iter().any(|id| candidate.category_ids.contains(id))
+}
+
+// what `candidate` would take off each line on its own, or why it doesn't apply
+fn offer(
+    candidate: &PromotionCandidate,
+    lines: &[DiscountLine],
+    subtotal: &BigDecimal,
+    now: NaiveDateTime,
+    currency: &Currency,
+) -> Result<Vec<BigDecimal>, String> {
+    let promotion = &candidate.promotion;
+    if !promotion.active {
+        return Err("This coupon is no longer active".to_string());
+    }
+    if promotion.starts_at.is_some_and(|start| start > now) {
+        return Err("This coupon isn't valid yet".to_string());
+    }
+    if promotion.ends_at.is_some_and(|end| end <= now) {
+        return Err("This coupon has expired".to_string());
+    }
+    if promotion.usage_limit.is_some_and(|limit| candidate.redemptions >= i64::from(limit)) {
+        return Err("This coupon has been fully redeemed".to_string());
+    }
+    if promotion.per_user_limit.is_some_and(|limit| candidate.user_redemptions >= i64::from(limit)) {
+        return Err("You've already used this coupon".to_string());
+    }
+    if let Some(min) = promotion.min_subtotal.as_ref().filter(|min| subtotal < *min) {
+        return Err(format!("Spend at least {} {} to use this coupon", min.with_scale(2), currency.code));
+    }
+
+    let eligible: Vec<usize> = (0..lines.len()).filter(|&i| qualifies(candidate, &lines[i])).collect();
+    if eligible.is_empty() {
+        return Err("None of the items in the cart qualify".to_string());
+    }
+    let eligible_subtotal = eligible.iter().map(|&i| line_total(&lines[i])).fold(BigDecimal::zero(), |sum, t| sum + t);
+
+    let hundred = BigDecimal::from(100);
+    let value = promotion.value.clone().unwrap_or_default();
+    let mut weights = vec![BigDecimal::zero(); lines.len()];
+    match promotion.kind {
+        PromotionKind::Percentage => {
+            for &i in &eligible {
+                weights[i] = line_total(&lines[i]) * &value / &hundred;
+            }
+        }
+        // shared over the qualifying lines by their totals
+        PromotionKind::FixedAmount => {
+            let amount = value.min(eligible_subtotal.clone());
+            for &i in &eligible {
+                weights[i] = &amount * line_total(&lines[i]) / &eligible_subtotal;
+            }
+        }
+        PromotionKind::FreeShipping => {}
+        PromotionKind::BuyXGetY => {
+            let buy = i64::from(promotion.buy_quantity.unwrap_or(1));
+            let get = i64::from(promotion.get_quantity.unwrap_or(1));
+            let units: i64 = eligible.iter().map(|&i| i64::from(lines[i].quantity)).sum();
+            let mut discounted_units = units / (buy + get) * get;
+            if discounted_units == 0 {
+                return Err(format!("Add {} qualifying items to get {} at a discount", buy + get, get));
+            }
+
+            // the cheapest qualifying items are the ones discounted
+            let mut cheapest_first = eligible;
+            cheapest_first.sort_by(|&a, &b| lines[a].unit_price.cmp(&lines[b].unit_price));
+            let percent = promotion.value.clone().unwrap_or(hundred.clone());
+            for i in cheapest_first {
+                let units = discounted_units.min(i64::from(lines[i].quantity));
+                weights[i] = &lines[i].unit_price * BigDecimal::from(units) * &percent / &hundred;
+                discounted_units -= units;
+                if discounted_units == 0 {
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(weights)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::str::FromStr;
+
+    use chrono::NaiveDate;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::models::cart::{BaseCartLine, CartItem};
+    use crate::models::currency::RoundingMode;
+    use crate::models::promotion::Promotion;
+    use crate::services::tax::taxable_lines;
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 1, 15).unwrap().and_hms_opt(12, 0, 0).unwrap()
+    }
+
+    fn usd() -> Currency {
+        Currency {
+            code: "USD".to_string(),
+            name: "US Dollar".to_string(),
+            symbol: Some("$".to_string()),
+            decimals: 2,
+            rounding_increment: None,
+            rounding: RoundingMode::HalfUp,
+            enabled: true,
+        }
+    }
+
+    fn dec(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    fn line(unit_price: &str, quantity: i32) -> DiscountLine {
+        DiscountLine { product_id: Uuid::new_v4(), category_ids: Vec::new(), unit_price: dec(unit_price), quantity }
+    }
+
+    fn promotion(name: &str, kind: PromotionKind, value: Option<&str>) -> Promotion {
+        Promotion {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            code: Some(name.to_uppercase()),
+            kind,
+            value: value.map(dec),
+            buy_quantity: None,
+            get_quantity: None,
+            starts_at: None,
+            ends_at: None,
+            usage_limit: None,
+            per_user_limit: None,
+            min_subtotal: None,
+            stackable: false,
+            priority: 0,
+            active: true,
+            created_at: now(),
+            updated_at: now(),
+        }
+    }
+
+    fn candidate(promotion: Promotion) -> PromotionCandidate {
+        PromotionCandidate {
+            promotion,
+            product_ids: HashSet::new(),
+            category_ids: HashSet::new(),
+            redemptions: 0,
+            user_redemptions: 0,
+        }
+    }
+
+    fn applied_names(outcome: &DiscountOutcome) -> Vec<&str> {
+        outcome.applied.iter().map(|d| d.name.as_str()).collect()
+    }
+
+    #[test]
+    fn stackable_promotions_combine() {
+        let mut ten = promotion("ten", PromotionKind::Percentage, Some("10"));
+        ten.stackable = true;
+        let mut five = promotion("five", PromotionKind::FixedAmount, Some("5"));
+        five.stackable = true;
+
+        let outcome = evaluate(&[line("50", 2)], &[candidate(ten), candidate(five)], now(), &usd());
+
+        assert_eq!(outcome.discount_total, dec("15"));
+        assert_eq!(outcome.applied.len(), 2);
+        assert!(outcome.rejected.is_empty());
+    }
+
+    #[test]
+    fn exclusive_promotion_wins_when_it_saves_more() {
+        let mut ten = promotion("ten", PromotionKind::Percentage, Some("10"));
+        ten.stackable = true;
+        let mut five = promotion("five", PromotionKind::FixedAmount, Some("5"));
+        five.stackable = true;
+        let thirty = promotion("thirty", PromotionKind::FixedAmount, Some("30"));
+
+        let outcome =
+            evaluate(&[line("50", 2)], &[candidate(ten), candidate(five), candidate(thirty)], now(), &usd());
+
+        assert_eq!(applied_names(&outcome), vec!["thirty"]);
+        assert_eq!(outcome.discount_total, dec("30"));
+        assert_eq!(outcome.rejected.len(), 2);
+        assert!(outcome.rejected.iter().all(|r| r.reason == "Can't be combined with thirty"));
+    }
+
+    #[test]
+    fn stacked_promotions_win_over_a_smaller_exclusive_one() {
+        let mut ten = promotion("ten", PromotionKind::Percentage, Some("10"));
+        ten.stackable = true;
+        let mut five = promotion("five", PromotionKind::FixedAmount, Some("5"));
+        five.stackable = true;
+        let twelve = promotion("twelve", PromotionKind::FixedAmount, Some("12"));
+
+        let outcome =
+            evaluate(&[line("50", 2)], &[candidate(ten), candidate(five), candidate(twelve)], now(), &usd());
+
+        assert_eq!(outcome.discount_total, dec("15"));
+        assert_eq!(outcome.rejected.len(), 1);
+        assert_eq!(outcome.rejected[0].code, "TWELVE");
+    }
+
+    #[test]
+    fn discounts_never_exceed_the_subtotal() {
+        let mut big = promotion("big", PromotionKind::FixedAmount, Some("80"));
+        big.stackable = true;
+        let mut half = promotion("half", PromotionKind::Percentage, Some("50"));
+        half.stackable = true;
+
+        let outcome = evaluate(&[line("100", 1)], &[candidate(big), candidate(half)], now(), &usd());
+
+        assert_eq!(outcome.discount_total, dec("100"));
+    }
+
+    #[test]
+    fn buy_x_get_y_discounts_the_cheapest_items() {
+        let mut deal = promotion("deal", PromotionKind::BuyXGetY, None);
+        deal.buy_quantity = Some(2);
+        deal.get_quantity = Some(1);
+
+        let outcome = evaluate(&[line("30", 2), line("10", 1), line("20", 3)], &[candidate(deal)], now(), &usd());
+
+        // six items make two free ones: the 10 and one of the 20s
+        assert_eq!(outcome.discount_total, dec("30"));
+    }
+
+    #[test]
+    fn buy_x_get_y_at_a_percentage_off() {
+        let mut deal = promotion("deal", PromotionKind::BuyXGetY, Some("50"));
+        deal.buy_quantity = Some(1);
+        deal.get_quantity = Some(1);
+
+        let outcome = evaluate(&[line("20", 2)], &[candidate(deal)], now(), &usd());
+
+        assert_eq!(outcome.discount_total, dec("10"));
+    }
+
+    #[test]
+    fn buy_x_get_y_needs_enough_items() {
+        let mut deal = promotion("deal", PromotionKind::BuyXGetY, None);
+        deal.buy_quantity = Some(2);
+        deal.get_quantity = Some(1);
+
+        let outcome = evaluate(&[line("30", 2)], &[candidate(deal)], now(), &usd());
+
+        assert!(outcome.applied.is_empty());
+        assert_eq!(outcome.rejected[0].reason, "Add 3 qualifying items to get 1 at a discount");
+    }
+
+    #[test]
+    fn min_subtotal_is_enforced() {
+        let mut ten = promotion("ten", PromotionKind::Percentage, Some("10"));
+        ten.min_subtotal = Some(dec("100"));
+
+        let short = evaluate(&[line("99.99", 1)], &[candidate(ten.clone())], now(), &usd());
+        assert!(short.applied.is_empty());
+        assert_eq!(short.rejected[0].reason, "Spend at least 100.00 USD to use this coupon");
+
+        let enough = evaluate(&[line("100", 1)], &[candidate(ten)], now(), &usd());
+        assert_eq!(enough.discount_total, dec("10"));
+    }
+
+    #[test]
+    fn usage_limit_is_enforced() {
+        let mut ten = promotion("ten", PromotionKind::Percentage, Some("10"));
+        ten.usage_limit = Some(3);
+        let mut used_up = candidate(ten.clone());
+        used_up.redemptions = 3;
+        let mut available = candidate(ten);
+        available.redemptions = 2;
+
+        let outcome = evaluate(&[line("100", 1)], &[used_up], now(), &usd());
+        assert!(outcome.applied.is_empty());
+        assert_eq!(outcome.rejected[0].reason, "This coupon has been fully redeemed");
+
+        let outcome = evaluate(&[line("100", 1)], &[available], now(), &usd());
+        assert_eq!(outcome.discount_total, dec("10"));
+    }
+
+    #[test]
+    fn per_user_limit_is_enforced() {
+        let mut ten = promotion("ten", PromotionKind::Percentage, Some("10"));
+        ten.per_user_limit = Some(1);
+        let mut used = candidate(ten.clone());
+        used.redemptions = 5;
+        used.user_redemptions = 1;
+        let mut unused = candidate(ten);
+        unused.redemptions = 5;
+
+        let outcome = evaluate(&[line("100", 1)], &[used], now(), &usd());
+        assert!(outcome.applied.is_empty());
+        assert_eq!(outcome.rejected[0].reason, "You've already used this coupon");
+
+        let outcome = evaluate(&[line("100", 1)], &[unused], now(), &usd());
+        assert_eq!(outcome.discount_total, dec("10"));
+    }
+
+    #[test]
+    fn free_shipping_combines_with_an_exclusive_discount() {
+        let shipping = promotion("ship", PromotionKind::FreeShipping, None);
+        let thirty = promotion("thirty", PromotionKind::FixedAmount, Some("30"));
+
+        let outcome = evaluate(&[line("50", 2)], &[candidate(shipping), candidate(thirty)], now(), &usd());
+
+        assert!(outcome.free_shipping);
+        assert_eq!(outcome.discount_total, dec("30"));
+        assert!(outcome.rejected.is_empty());
+    }
+
+    #[test]
+    fn automatic_promotions_that_dont_apply_are_not_reported() {
+        let mut automatic = promotion("auto", PromotionKind::Percentage, Some("10"));
+        automatic.code = None;
+        automatic.min_subtotal = Some(dec("500"));
+
+        let outcome = evaluate(&[line("100", 1)], &[candidate(automatic)], now(), &usd());
+
+        assert!(outcome.applied.is_empty());
+        assert!(outcome.rejected.is_empty());
+    }
+
+    fn cart_line(line: &DiscountLine) -> BaseCartLine {
+        BaseCartLine {
+            item: CartItem {
+                id: Uuid::new_v4(),
+                user_id: Uuid::nil(),
+                product_id: line.product_id,
+                variant_id: None,
+                quantity: line.quantity,
+                created_at: None,
+                updated_at: None,
+            },
+            unit_price: line.unit_price.clone(),
+            original_unit_price: None,
+        }
+    }
+
+    #[test]
+    fn restricted_promotions_only_come_off_qualifying_lines() {
+        let shoes = Uuid::new_v4();
+        let mut boots = line("80", 1);
+        boots.category_ids = vec![shoes];
+        let lines = [boots, line("20", 1)];
+        let mut ten = candidate(promotion("ten", PromotionKind::FixedAmount, Some("10")));
+        ten.category_ids.insert(shoes);
+
+        let outcome = evaluate(&lines, &[ten], now(), &usd());
+
+        assert_eq!(outcome.discount_total, dec("10"));
+        assert_eq!(outcome.line_discounts, vec![dec("10"), dec("0")]);
+    }
+
+    #[test]
+    fn buy_x_get_y_comes_off_the_discounted_items() {
+        let mut deal = promotion("deal", PromotionKind::BuyXGetY, None);
+        deal.buy_quantity = Some(2);
+        deal.get_quantity = Some(1);
+
+        let outcome = evaluate(&[line("30", 2), line("10", 1), line("20", 3)], &[candidate(deal)], now(), &usd());
+
+        assert_eq!(outcome.line_discounts, vec![dec("0"), dec("10"), dec("20")]);
+    }
+
+    #[test]
+    fn stacked_discounts_on_one_line_stop_at_its_total() {
+        let product = Uuid::new_v4();
+        let mut mug = line("15", 1);
+        mug.product_id = product;
+        let lines = [mug, line("100", 1)];
+        let mut twelve = promotion("twelve", PromotionKind::FixedAmount, Some("12"));
+        twelve.stackable = true;
+        twelve.priority = 1;
+        let mut twelve = candidate(twelve);
+        twelve.product_ids.insert(product);
+        let mut ten = promotion("ten", PromotionKind::FixedAmount, Some("10"));
+        ten.stackable = true;
+        let mut ten = candidate(ten);
+        ten.product_ids.insert(product);
+
+        let outcome = evaluate(&lines, &[twelve, ten], now(), &usd());
+
+        // the second only has 3 of the mug left to take
+        assert_eq!(outcome.discount_total, dec("15"));
+        assert_eq!(outcome.line_discounts, vec![dec("15"), dec("0")]);
+    }
+
+    #[test]
+    fn cart_wide_discounts_are_shared_by_line_totals() {
+        let lines = [line("10", 1), line("20", 1), line("30", 1)];
+        let ten = promotion("ten", PromotionKind::FixedAmount, Some("10"));
+
+        let outcome = evaluate(&lines, &[candidate(ten)], now(), &usd());
+
+        assert_eq!(outcome.line_discounts, vec![dec("1.67"), dec("3.33"), dec("5.00")]);
+        let shared = outcome.line_discounts.iter().fold(BigDecimal::zero(), |sum, d| sum + d);
+        assert_eq!(shared, outcome.discount_total);
+    }
+
+    #[test]
+    fn lines_in_other_tax_classes_keep_their_full_taxable_amount() {
+        // books and electronics are taxed at different rates; the coupon is for books only
+        let books = Uuid::new_v4();
+        let mut novel = line("40", 1);
+        novel.category_ids = vec![books];
+        let lines = [novel, line("60", 1)];
+        let mut half = candidate(promotion("half", PromotionKind::Percentage, Some("50")));
+        half.category_ids.insert(books);
+
+        let outcome = evaluate(&lines, &[half], now(), &usd());
+        let cart: Vec<BaseCartLine> = lines.iter().map(cart_line).collect();
+        let taxable = taxable_lines(&cart, &outcome.line_discounts);
+
+        assert_eq!(taxable[0].amount, dec("20"));
+        assert_eq!(taxable[1].amount, dec("60"));
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use axum::async_trait;
+use bigdecimal::{BigDecimal, Zero};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use sqlx::PgPool;
+
+use crate::config::store_currency;
+use crate::services::currency::set_rate;
+
+pub type RateError = Box<dyn std::error::Error + Send + Sync>;
+
+// a source of exchange rates
+#[async_trait]
+pub trait RateProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+    // units of each currency one unit of `base` buys, keyed by ISO 4217 code
+    async fn fetch_rates(&self, base: &str) -> Result<HashMap<String, BigDecimal>, RateError>;
+}
+
+// reads rates from a JSON file, for offline installs and tests:
+// {"base": "USD", "rates": {"XAF": "605.25", "NGN": 1530}}
+pub struct StaticFileRateProvider {
+    path: PathBuf,
+}
+
+impl StaticFileRateProvider {
+    pub fn new(path: PathBuf) -> Self {
+        StaticFileRateProvider { path }
+    }
+}
+
+#[derive(Deserialize)]
+struct RatesFile {
+    base: String,
+    rates: Map<String, Value>,
+}
+
+#[async_trait]
+impl RateProvider for StaticFileRateProvider {
+    fn name(&self) -> &'static str {
+        "static-file"
+    }
+
+    async fn fetch_rates(&self, base: &str) -> Result<HashMap<String, BigDecimal>, RateError> {
+        let file: RatesFile = serde_json::from_slice(&tokio::fs::read(&self.path).await?)?;
+
+        let mut rates = HashMap::new();
+        for (code, value) in file.rates {
+            // numbers are read from their text so no precision is lost to floats
+            let text = match &value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            let rate = BigDecimal::from_str(&text).map_err(|_| format!("Rate for {} isn't a number", code))?;
+            rates.insert(code.to_uppercase(), rate);
+        }
+        rates.insert(file.base.to_uppercase(), BigDecimal::from(1));
+
+        // a file quoted against another currency is rebased through the store's own
+        let base_rate = rates
+            .get(&base.to_uppercase())
+            .cloned()
+            .filter(|rate| !rate.is_zero())
+            .ok_or_else(|| format!("{} has no rate for {}", self.path.display(), base))?;
+        Ok(rates.into_iter().map(|(code, rate)| (code, rate / &base_rate)).collect())
+    }
+}
+
+// EXCHANGE_RATES_FILE points at a static rates file; no provider means admins keep rates up to date
+pub fn rate_provider_from_env() -> Option<Box<dyn RateProvider>> {
+    env::var("EXCHANGE_RATES_FILE")
+        .ok()
+        .map(|path| Box::new(StaticFileRateProvider::new(PathBuf::from(path))) as Box<dyn RateProvider>)
+}
+
+// stores the provider's rate for every currency that's set up; others are ignored
+pub async fn refresh_rates(pool: &PgPool, provider: &dyn RateProvider) -> Result<usize, RateError> {
+    let base = store_currency();
+    let rates = provider.fetch_rates(&base).await?;
+
+    let codes: Vec<String> = sqlx::query_scalar("SELECT code FROM currencies WHERE code <> $1")
+        .bind(&base)
+        .fetch_all(pool)
+        .await?;
+
+    let mut tx = pool.begin().await?;
+    let mut updated = 0;
+    for code in codes {
+        if let Some(rate) = rates.get(&code).filter(|rate| *rate > &BigDecimal::zero()) {
+            set_rate(&mut tx, &code, rate, provider.name(), None).await?;
+            updated += 1;
+        }
+    }
+    tx.commit().await?;
+
+    Ok(updated)
+}
+
+pub fn spawn_rate_refresher(pool: PgPool, provider: Box<dyn RateProvider>, every: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        loop {
+            interval.tick().await;
+            match refresh_rates(&pool, provider.as_ref()).await {
+                Ok(0) => {}
+                Ok(updated) => println!("💱 Refreshed {} exchange rates from {}", updated, provider.name()),
+                Err(e) => eprintln!("❌ Failed to refresh exchange rates from {}: {:?}", provider.name(), e),
+            }
+        }
+    });
+}
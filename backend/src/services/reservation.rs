@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::models::inventory::{NewStockMovement, StockMovement, StockMovementKind};
+use crate::models::reservation::{CheckoutReservation, StockReservation};
+use crate::models::tax::TaxAddress;
+use crate::models::cart::CartItem;
+use crate::services::cart::price_cart_items;
+use crate::services::currency::base_currency;
+use crate::services::inventory::{apply_movement, InventoryError};
+use crate::services::promotion::{discounts_for_cart, record_redemptions};
+use crate::services::tax::{record_checkout_tax, tax_calculator_from_env, taxable_lines};
+use chrono::{SubsecRound, Utc};
+use sqlx::{FromRow, PgConnection, PgPool};
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub enum ReservationError {
+    EmptyCart,
+    InvalidQuantity(Uuid),
+    NotFound,
+    Expired,
+    InsufficientStock(Uuid),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for ReservationError {
+    fn from(err: sqlx::Error) -> Self {
+        ReservationError::Database(err)
+    }
+}
+
+#[derive(FromRow)]
+struct CartLine {
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    quantity: i32,
+}
+
+#[derive(FromRow)]
+struct ReservedQuantity {
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    quantity: i64,
+}
+
+// quantity currently held by unexpired reservations for one product or variant
+async fn reserved_quantity(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT COALESCE(SUM(quantity), 0)::bigint
+        FROM stock_reservations
+        WHERE product_id = $1 AND variant_id IS NOT DISTINCT FROM $2
+          AND status = 'active' AND expires_at > $3
+        "#,
+    )
+    .bind(product_id)
+    .bind(variant_id)
+    .bind(Utc::now().naive_utc())
+    .fetch_one(conn)
+    .await
+}
+
+// reserved quantities for several products, keyed by (product_id, variant_id)
+pub async fn reserved_quantities(
+    pool: &PgPool,
+    product_ids: &[Uuid],
+) -> Result<HashMap<(Uuid, Option<Uuid>), i64>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, ReservedQuantity>(
+        r#"
+        SELECT product_id, variant_id, SUM(quantity)::bigint AS quantity
+        FROM stock_reservations
+        WHERE product_id = ANY($1) AND status = 'active' AND expires_at > $2
+        GROUP BY product_id, variant_id
+        "#,
+    )
+    .bind(product_ids)
+    .bind(Utc::now().naive_utc())
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ((row.product_id, row.variant_id), row.quantity))
+        .collect())
+}
+
+// hold every line of the user's cart for `ttl`; all or nothing
+pub async fn reserve_cart(
+    pool: &PgPool,
+    user_id: Uuid,
+    ttl: Duration,
+) -> Result<CheckoutReservation, ReservationError> {
+    // postgres keeps microseconds; truncate so the response matches the stored rows
+    let now = Utc::now().naive_utc().trunc_subsecs(6);
+    let expires_at = now + chrono::Duration::from_std(ttl).unwrap_or_else(|_| chrono::Duration::minutes(15));
+    let checkout_id = Uuid::new_v4();
+    let mut tx = pool.begin().await?;
+
+    // a restarted checkout replaces whatever the previous one was holding
+    sqlx::query(
+        "UPDATE stock_reservations SET status = 'released', updated_at = $1 WHERE user_id = $2 AND status = 'active'",
+    )
+    .bind(now)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    // lock rows in a stable order so concurrent checkouts can't deadlock
+    let lines = sqlx::query_as::<_, CartLine>(
+        r#"
+        SELECT product_id, variant_id, quantity
+        FROM cart_items
+        WHERE user_id = $1
+        ORDER BY product_id, variant_id NULLS FIRST
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if lines.is_empty() {
+        return Err(ReservationError::EmptyCart);
+    }
+    if let Some(line) = lines.iter().find(|line| line.quantity <= 0) {
+        return Err(ReservationError::InvalidQuantity(line.product_id));
+    }
+
+    let mut reservations = Vec::with_capacity(lines.len());
+    for line in lines {
+        let stock: Option<i32> = match line.variant_id {
+            Some(variant_id) => {
+                sqlx::query_scalar(
+                    "SELECT stock_quantity FROM product_variants WHERE id = $1 AND product_id = $2 AND deleted_at IS NULL FOR UPDATE",
+                )
+                .bind(variant_id)
+                .bind(line.product_id)
+                .fetch_optional(&mut *tx)
+                .await?
+            }
+            None => {
+                sqlx::query_scalar("SELECT stock_quantity FROM products WHERE id = $1 AND deleted_at IS NULL FOR UPDATE")
+                    .bind(line.product_id)
+                    .fetch_optional(&mut *tx)
+                    .await?
+            }
+        };
+        let stock = stock.ok_or(ReservationError::NotFound)?;
+
+        let reserved = reserved_quantity(&mut tx, line.product_id, line.variant_id).await?;
+        if i64::from(stock) - reserved < i64::from(line.quantity) {
+            return Err(ReservationError::InsufficientStock(line.product_id));
+        }
+
+        let reservation = sqlx::query_as::<_, StockReservation>(
+            r#"
+            INSERT INTO stock_reservations (id, checkout_id, user_id, product_id, variant_id, quantity, status, expires_at, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, 'active', $7, $8, $8)
+            RETURNING id, checkout_id, user_id, product_id, variant_id, quantity, status, expires_at, created_at, updated_at
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(checkout_id)
+        .bind(user_id)
+        .bind(line.product_id)
+        .bind(line.variant_id)
+        .bind(line.quantity)
+        .bind(expires_at)
+        .bind(now)
+        .fetch_one(&mut *tx)
+        .await?;
+        reservations.push(reservation);
+    }
+
+    tx.commit().await?;
+
+    Ok(CheckoutReservation { checkout_id, expires_at, reservations })
+}
+
+// payment failed or the checkout was abandoned
+pub async fn release_checkout(pool: &PgPool, checkout_id: Uuid, user_id: Uuid) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE stock_reservations
+        SET status = 'released', updated_at = $1
+        WHERE checkout_id = $2 AND user_id = $3 AND status = 'active'
+        "#,
+    )
+    .bind(Utc::now().naive_utc())
+    .bind(checkout_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+// payment succeeded: turn the held stock into sales, record the discounts and the tax for
+// `address`, and empty the cart
+pub async fn convert_checkout(
+    pool: &PgPool,
+    checkout_id: Uuid,
+    user_id: Uuid,
+    address: Option<&TaxAddress>,
+) -> Result<Vec<StockMovement>, ReservationError> {
+    let now = Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+
+    let reservations = sqlx::query_as::<_, StockReservation>(
+        r#"
+        SELECT id, checkout_id, user_id, product_id, variant_id, quantity, status, expires_at, created_at, updated_at
+        FROM stock_reservations
+        WHERE checkout_id = $1 AND user_id = $2 AND status = 'active'
+        ORDER BY product_id, variant_id NULLS FIRST
+        FOR UPDATE
+        "#,
+    )
+    .bind(checkout_id)
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if reservations.is_empty() {
+        return Err(ReservationError::NotFound);
+    }
+
+    if reservations.iter().any(|r| r.expires_at <= now) {
+        return Err(ReservationError::Expired);
+    }
+
+    let mut movements = Vec::with_capacity(reservations.len());
+    for reservation in &reservations {
+        let movement = NewStockMovement {
+            product_id: reservation.product_id,
+            variant_id: reservation.variant_id,
+            warehouse_id: None,
+            kind: StockMovementKind::Sale,
+            quantity: StockMovementKind::Sale.signed(reservation.quantity),
+            reason: Some(format!("checkout {}", checkout_id)),
+            actor_id: Some(user_id),
+        };
+        let recorded = apply_movement(&mut tx, movement).await.map_err(|e| match e {
+            InventoryError::NotFound => ReservationError::NotFound,
+            InventoryError::InsufficientStock => ReservationError::InsufficientStock(reservation.product_id),
+            InventoryError::Database(err) => ReservationError::Database(err),
+        })?;
+        movements.extend(recorded);
+    }
+
+    sqlx::query("UPDATE stock_reservations SET status = 'converted', updated_at = $1 WHERE checkout_id = $2 AND status = 'active'")
+        .bind(now)
+        .bind(checkout_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // the lines sold are the reserved ones, whatever the cart holds by now. The promotions that
+    // apply to them are the ones redeemed; the promotion rows stay locked until commit so limited
+    // coupons can't be over-redeemed by checkouts finishing together
+    let lines = price_cart_items(&mut tx, reserved_items(&reservations), now).await?;
+    let base = base_currency(&mut tx).await?;
+    let discounts = discounts_for_cart(&mut tx, user_id, &lines, now, &base, true).await?;
+    record_redemptions(&mut tx, user_id, checkout_id, &discounts).await?;
+
+    let taxable = taxable_lines(&lines, &discounts.line_discounts);
+    let calculator = tax_calculator_from_env();
+    let tax = calculator.calculate(&mut tx, address, &taxable, &base).await?;
+    record_checkout_tax(&mut tx, checkout_id, user_id, address, calculator.name(), &tax).await?;
+
+    // take the sold quantities out of the cart; anything added since reserving stays in it
+    sqlx::query(
+        r#"
+        DELETE FROM cart_items c
+        USING (SELECT product_id, variant_id, SUM(quantity) AS quantity FROM stock_reservations
+               WHERE checkout_id = $1 GROUP BY product_id, variant_id) r
+        WHERE c.user_id = $2 AND c.product_id = r.product_id AND c.variant_id IS NOT DISTINCT FROM r.variant_id
+          AND c.quantity <= r.quantity
+        "#,
+    )
+    .bind(checkout_id)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        r#"
+        UPDATE cart_items c
+        SET quantity = c.quantity - r.quantity, updated_at = $3
+        FROM (SELECT product_id, variant_id, SUM(quantity) AS quantity FROM stock_reservations
+              WHERE checkout_id = $1 GROUP BY product_id, variant_id) r
+        WHERE c.user_id = $2 AND c.product_id = r.product_id AND c.variant_id IS NOT DISTINCT FROM r.variant_id
+        "#,
+    )
+    .bind(checkout_id)
+    .bind(user_id)
+    .bind(now)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(movements)
+}
+
+// a checkout's reservations as the cart lines they were made from
+fn reserved_items(reservations: &[StockReservation]) -> Vec<CartItem> {
+    reservations
+        .iter()
+        .map(|r| CartItem {
+            id: r.id,
+            user_id: r.user_id,
+            product_id: r.product_id,
+            variant_id: r.variant_id,
+            quantity: r.quantity,
+            created_at: Some(r.created_at),
+            updated_at: Some(r.updated_at),
+        })
+        .collect()
+}
+
+pub async fn expire_reservations(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let now = Utc::now().naive_utc();
+    let result = sqlx::query(
+        "UPDATE stock_reservations SET status = 'expired', updated_at = $1 WHERE status = 'active' AND expires_at <= $1",
+    )
+    .bind(now)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+pub fn spawn_reservation_sweeper(pool: PgPool, every: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        loop {
+            interval.tick().await;
+            if let Err(e) = expire_reservations(&pool).await {
+                eprintln!("❌ Failed to expire stock reservations: {:?}", e);
+            }
+        }
+    });
+}
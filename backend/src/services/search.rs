@@ -0,0 +1,496 @@
+use std::str::FromStr;
+
+use crate::config::search_fuzzy_threshold;
+use crate::models::product::{ProductQueryParams, ProductSearchHit, ProductSearchRow};
+use crate::models::search::{
+    AvailabilityFacet, CategoryFacet, OptionFacet, PriceRangeFacet, SearchFacets, SearchOutcome, Suggestion,
+};
+use crate::pagination::{Keyed, PageRequest, Paged, SortDirection, SortKey};
+use crate::services::currency::Converter;
+use crate::services::product::with_details;
+use crate::services::slug::slugify;
+use bigdecimal::BigDecimal;
+use serde_json::{json, Value};
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder};
+
+const SNIPPET_OPTIONS: &str = "StartSel=<mark>, StopSel=</mark>, MaxFragments=2, MaxWords=20, MinWords=5";
+const DEFAULT_PRICE_BUCKETS: [u32; 5] = [25, 50, 100, 250, 500];
+
+// what a customer pays right now, as ProductPricing::effective_price works it out
+const EFFECTIVE_PRICE: &str = "CASE WHEN sale_price IS NOT NULL \
+    AND (sale_starts_at IS NULL OR sale_starts_at <= now() AT TIME ZONE 'utc') \
+    AND (sale_ends_at IS NULL OR sale_ends_at > now() AT TIME ZONE 'utc') \
+    THEN sale_price ELSE price END";
+
+// sort keys accepted by the product list and search endpoints
+pub const PRODUCT_SORTS: &[SortKey] = &[
+    SortKey { name: "relevance", expr: "COALESCE(rank, 0)", sql_type: "real", direction: SortDirection::Desc },
+    SortKey { name: "price", expr: EFFECTIVE_PRICE, sql_type: "numeric", direction: SortDirection::Asc },
+    SortKey {
+        name: "created_at",
+        expr: "COALESCE(created_at, 'epoch'::timestamp)",
+        sql_type: "timestamp",
+        direction: SortDirection::Desc,
+    },
+    SortKey { name: "name", expr: "name", sql_type: "text", direction: SortDirection::Asc },
+    // all-time units sold, read from the stock ledger
+    SortKey {
+        name: "popularity",
+        expr: "(SELECT COALESCE(-SUM(sm.quantity), 0) FROM stock_movements sm WHERE sm.product_id = products.id AND sm.kind = 'sale')",
+        sql_type: "bigint",
+        direction: SortDirection::Desc,
+    },
+];
+
+// relevance when there is search text, newest first otherwise
+pub fn default_product_sort(params: &ProductQueryParams) -> &'static str {
+    if params.text().is_some() { "relevance" } else { "created_at" }
+}
+
+// how the search text is matched against products
+enum TextMatch {
+    All,
+    FullText(String),
+    Fuzzy(String),
+}
+
+// filters a facet can leave out when counting its own values
+#[derive(Clone, Copy, PartialEq)]
+enum Facet {
+    Category,
+    Price,
+    Availability,
+}
+
+fn push_text_match(builder: &mut QueryBuilder<'_, Postgres>, text_match: &TextMatch) {
+    match text_match {
+        TextMatch::All => {}
+        TextMatch::FullText(text) => {
+            builder
+                .push(" AND search_vector @@ websearch_to_tsquery('english', ")
+                .push_bind(text.clone())
+                .push(")");
+        }
+        TextMatch::Fuzzy(text) => {
+            builder.push(" AND ").push_bind(text.clone()).push(" <% name");
+        }
+    }
+}
+
+// "true"/"false" and numbers are matched as JSON booleans and numbers as well as text
+fn attribute_value(raw: &str) -> Value {
+    match raw {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => serde_json::from_str::<serde_json::Number>(raw)
+            .map(Value::Number)
+            .unwrap_or_else(|_| Value::String(raw.to_string())),
+    }
+}
+
+// "key:a|b" matches either value; "key:>=n" (also >, <=, <) compares numeric attributes
+fn push_attribute_filters(builder: &mut QueryBuilder<'_, Postgres>, filter: &str) {
+    let pairs = filter
+        .split(',')
+        .filter_map(|pair| pair.split_once(':'))
+        .map(|(key, value)| (key.trim(), value.trim()));
+
+    for (key, value) in pairs {
+        let comparison = [">=", "<=", ">", "<"]
+            .into_iter()
+            .find_map(|op| value.strip_prefix(op).map(|n| (op, n)))
+            .and_then(|(op, n)| BigDecimal::from_str(n.trim()).ok().map(|n| (op, n)));
+
+        if let Some((op, n)) = comparison {
+            builder
+                .push(" AND CASE WHEN jsonb_typeof(attributes -> ")
+                .push_bind(key.to_string())
+                .push(") = 'number' THEN (attributes ->> ")
+                .push_bind(key.to_string())
+                .push(format!(")::numeric END {} ", op))
+                .push_bind(n);
+            continue;
+        }
+
+        builder.push(" AND (FALSE");
+        for alternative in value.split('|').map(str::trim) {
+            builder
+                .push(" OR attributes @> ")
+                .push_bind(json!({ key: attribute_value(alternative) }))
+                .push(" OR attributes @> ")
+                .push_bind(json!({ key: alternative }));
+        }
+        builder.push(")");
+    }
+}
+
+// the non-text filters shared by every search mode
+fn push_filters(builder: &mut QueryBuilder<'_, Postgres>, params: &ProductQueryParams, except: Option<Facet>) {
+    let in_stock = params.in_stock == Some(true) && except != Some(Facet::Availability);
+
+    // the primary category or any of the secondary ones
+    if let Some(category_id) = params.category_id.filter(|_| except != Some(Facet::Category)) {
+        builder.push(" AND EXISTS (SELECT 1 FROM (SELECT products.category_id UNION ALL SELECT pc.category_id FROM product_categories pc WHERE pc.product_id = products.id) pcs WHERE pcs.category_id");
+        if params.include_descendants == Some(true) {
+            builder
+                .push(" IN (WITH RECURSIVE subtree AS (SELECT id FROM categories WHERE id = ")
+                .push_bind(category_id)
+                .push(" UNION ALL SELECT c.id FROM categories c JOIN subtree s ON c.parent_id = s.id) SELECT id FROM subtree))");
+        } else {
+            builder.push(" = ").push_bind(category_id).push(")");
+        }
+    }
+
+    if except != Some(Facet::Price) {
+        if let Some(min_price) = &params.min_price {
+            builder.push(format!(" AND {} >= ", EFFECTIVE_PRICE)).push_bind(min_price.clone());
+        }
+
+        if let Some(max_price) = &params.max_price {
+            builder.push(format!(" AND {} <= ", EFFECTIVE_PRICE)).push_bind(max_price.clone());
+        }
+    }
+
+    if in_stock {
+        builder.push(" AND (stock_quantity > 0 OR EXISTS (SELECT 1 FROM product_variants pv WHERE pv.product_id = products.id AND pv.deleted_at IS NULL AND pv.stock_quantity > 0))");
+    }
+
+    for tag in params.tags.as_deref().unwrap_or_default().split(',').map(slugify).filter(|t| !t.is_empty()) {
+        builder
+            .push(" AND EXISTS (SELECT 1 FROM product_tags t WHERE t.product_id = products.id AND t.tag = ")
+            .push_bind(tag)
+            .push(")");
+    }
+
+    push_attribute_filters(builder, params.attributes.as_deref().unwrap_or_default());
+
+    // variant filters: a single variant has to match the sku and every option pair
+    let option_pairs: Vec<(&str, &str)> = params
+        .options
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|pair| pair.split_once(':'))
+        .map(|(name, value)| (name.trim(), value.trim()))
+        .collect();
+
+    if params.sku.is_some() || !option_pairs.is_empty() {
+        builder.push(" AND EXISTS (SELECT 1 FROM product_variants pv WHERE pv.product_id = products.id AND pv.deleted_at IS NULL");
+
+        if let Some(sku) = &params.sku {
+            builder.push(" AND pv.sku = ").push_bind(sku.clone());
+        }
+
+        if in_stock {
+            builder.push(" AND pv.stock_quantity > 0");
+        }
+
+        for (name, value) in option_pairs {
+            builder
+                .push(" AND EXISTS (SELECT 1 FROM product_variant_option_values pvov JOIN product_option_values ov ON ov.id = pvov.option_value_id JOIN product_options o ON o.id = ov.option_id WHERE pvov.variant_id = pv.id AND o.name ILIKE ")
+                .push_bind(name.to_string())
+                .push(" AND ov.value ILIKE ")
+                .push_bind(value.to_string())
+                .push(")");
+        }
+
+        builder.push(")");
+    }
+}
+
+// FROM/WHERE for the matching products, optionally ignoring one facet's filter
+fn push_matching(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    params: &ProductQueryParams,
+    text_match: &TextMatch,
+    except: Option<Facet>,
+) {
+    builder.push(" FROM products WHERE deleted_at IS NULL");
+    push_text_match(builder, text_match);
+    push_filters(builder, params, except);
+}
+
+// every product matching the search filters, oldest first, for streaming exports;
+// `columns` is the select list. Run it in the transaction it was resolved in
+pub async fn export_query(
+    conn: &mut PgConnection,
+    params: &ProductQueryParams,
+    columns: &str,
+) -> Result<QueryBuilder<'static, Postgres>, sqlx::Error> {
+    let text_match = resolve_text_match(conn, params).await?;
+
+    let mut builder = QueryBuilder::new(format!("SELECT {}", columns));
+    push_matching(&mut builder, params, &text_match, None);
+    builder.push(" ORDER BY created_at, id");
+
+    Ok(builder)
+}
+
+// use full-text matching when the text hits anything under the current filters,
+// otherwise fall back to trigram matching on the name (usually a misspelling)
+async fn resolve_text_match(conn: &mut PgConnection, params: &ProductQueryParams) -> Result<TextMatch, sqlx::Error> {
+    let Some(text) = params.text() else {
+        return Ok(TextMatch::All);
+    };
+
+    let full_text = TextMatch::FullText(text.to_string());
+    let mut builder = QueryBuilder::new("SELECT EXISTS (SELECT 1");
+    push_matching(&mut builder, params, &full_text, None);
+    builder.push(")");
+
+    if builder.build_query_scalar::<bool>().fetch_one(&mut *conn).await? {
+        return Ok(full_text);
+    }
+
+    // the threshold only applies to the caller's transaction, so `<%` can still use the trigram index
+    sqlx::query("SELECT set_config('pg_trgm.word_similarity_threshold', $1, true)")
+        .bind(search_fuzzy_threshold().to_string())
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(TextMatch::Fuzzy(text.to_string()))
+}
+
+// the matching products with their rank, computed in a subquery so relevance can be
+// sorted and paged like any other key; snippets are only built for the returned page
+async fn fetch_hits(
+    conn: &mut PgConnection,
+    params: &ProductQueryParams,
+    text_match: &TextMatch,
+    page: &PageRequest<'_>,
+) -> Result<Vec<Keyed<ProductSearchRow>>, sqlx::Error> {
+    let mut builder = QueryBuilder::new("SELECT products.*, ");
+
+    match text_match {
+        TextMatch::FullText(text) => {
+            builder
+                .push("ts_headline('english', concat_ws('. ', name, description), websearch_to_tsquery('english', ")
+                .push_bind(text.clone())
+                .push("), ")
+                .push_bind(SNIPPET_OPTIONS)
+                .push(") AS snippet");
+        }
+        TextMatch::All | TextMatch::Fuzzy(_) => {
+            builder.push("NULL::text AS snippet");
+        }
+    }
+    page.push_sort_columns(&mut builder, "products.id");
+
+    builder.push(" FROM (SELECT products.*, ");
+    match text_match {
+        TextMatch::All => {
+            builder.push("NULL::real AS rank");
+        }
+        TextMatch::FullText(text) => {
+            builder
+                .push("ts_rank_cd(search_vector, websearch_to_tsquery('english', ")
+                .push_bind(text.clone())
+                .push(")) AS rank");
+        }
+        TextMatch::Fuzzy(text) => {
+            builder.push("word_similarity(").push_bind(text.clone()).push(", name) AS rank");
+        }
+    }
+    push_matching(&mut builder, params, text_match, None);
+    builder.push(") AS products WHERE TRUE");
+
+    page.push_cursor_filter(&mut builder, "products.id");
+    page.push_order_and_limit(&mut builder, "products.id");
+
+    builder.build_query_as::<Keyed<ProductSearchRow>>().fetch_all(conn).await
+}
+
+// cut points for the price facet, from "25,50,100" or the defaults; always starts at zero
+fn price_bounds(params: &ProductQueryParams) -> Vec<BigDecimal> {
+    let mut bounds: Vec<BigDecimal> = match params.price_buckets.as_deref() {
+        Some(buckets) => buckets
+            .split(',')
+            .filter_map(|b| BigDecimal::from_str(b.trim()).ok())
+            .filter(|b| *b > BigDecimal::from(0))
+            .collect(),
+        None => DEFAULT_PRICE_BUCKETS.iter().map(|b| BigDecimal::from(*b)).collect(),
+    };
+
+    bounds.push(BigDecimal::from(0));
+    bounds.sort();
+    bounds.dedup();
+    bounds
+}
+
+async fn count_total(
+    conn: &mut PgConnection,
+    params: &ProductQueryParams,
+    text_match: &TextMatch,
+) -> Result<i64, sqlx::Error> {
+    let mut builder = QueryBuilder::new("SELECT COUNT(*)");
+    push_matching(&mut builder, params, text_match, None);
+
+    builder.build_query_scalar::<i64>().fetch_one(conn).await
+}
+
+async fn facet_counts(
+    conn: &mut PgConnection,
+    params: &ProductQueryParams,
+    text_match: &TextMatch,
+    converter: &Converter,
+) -> Result<SearchFacets, sqlx::Error> {
+    // a product counts towards its primary category and each secondary one, as the filter matches them
+    let mut builder = QueryBuilder::new(
+        r#"
+        SELECT c.id AS category_id, c.name, m.count
+        FROM (
+            SELECT pcs.category_id, COUNT(*) AS count
+            FROM (SELECT products.id, products.category_id"#,
+    );
+    push_matching(&mut builder, params, text_match, Some(Facet::Category));
+    builder.push(
+        r#") p
+            CROSS JOIN LATERAL (
+                SELECT p.category_id UNION SELECT pc.category_id FROM product_categories pc WHERE pc.product_id = p.id
+            ) pcs
+            GROUP BY pcs.category_id
+        ) m
+        JOIN categories c ON c.id = m.category_id
+        ORDER BY m.count DESC, c.name
+        "#,
+    );
+    let categories = builder.build_query_as::<CategoryFacet>().fetch_all(&mut *conn).await?;
+
+    // width_bucket puts prices in 1..=n for n cut points; the cut points are in the
+    // requested currency and prices are stored in the base one
+    let bounds = price_bounds(params);
+    let base_bounds: Vec<BigDecimal> = bounds.iter().map(|bound| converter.to_base(bound)).collect();
+    let mut builder = QueryBuilder::new(format!("SELECT width_bucket({}, ", EFFECTIVE_PRICE));
+    builder.push_bind(base_bounds).push(") AS bucket, COUNT(*)");
+    push_matching(&mut builder, params, text_match, Some(Facet::Price));
+    builder.push(" GROUP BY bucket");
+    let bucket_counts: Vec<(i32, i64)> = builder.build_query_as().fetch_all(&mut *conn).await?;
+
+    let price_ranges = bounds
+        .iter()
+        .enumerate()
+        .map(|(i, min)| PriceRangeFacet {
+            min: min.clone(),
+            max: bounds.get(i + 1).cloned(),
+            count: bucket_counts
+                .iter()
+                .find(|(bucket, _)| *bucket as usize == i + 1)
+                .map_or(0, |(_, count)| *count),
+        })
+        .collect();
+
+    let mut builder = QueryBuilder::new("SELECT (stock_quantity > 0 OR EXISTS (SELECT 1 FROM product_variants pv WHERE pv.product_id = products.id AND pv.deleted_at IS NULL AND pv.stock_quantity > 0)) AS in_stock, COUNT(*) AS count");
+    push_matching(&mut builder, params, text_match, Some(Facet::Availability));
+    builder.push(" GROUP BY 1 ORDER BY 1 DESC");
+    let availability = builder.build_query_as::<AvailabilityFacet>().fetch_all(&mut *conn).await?;
+
+    let mut builder = QueryBuilder::new(
+        r#"
+        SELECT o.name, ov.value, COUNT(DISTINCT m.id) AS count
+        FROM (SELECT products.id"#,
+    );
+    push_matching(&mut builder, params, text_match, None);
+    builder.push(
+        r#") m
+        JOIN product_variants pv ON pv.product_id = m.id AND pv.deleted_at IS NULL
+        JOIN product_variant_option_values pvov ON pvov.variant_id = pv.id
+        JOIN product_option_values ov ON ov.id = pvov.option_value_id
+        JOIN product_options o ON o.id = ov.option_id
+        GROUP BY o.name, ov.value, o.position, ov.position
+        ORDER BY o.position, o.name, ov.position, ov.value
+        "#,
+    );
+    let options = builder.build_query_as::<OptionFacet>().fetch_all(&mut *conn).await?;
+
+    Ok(SearchFacets { categories, price_ranges, availability, options })
+}
+
+// full-text search with a fuzzy fallback, paged and sorted by `page`; facet counts
+// are computed against the same filters when `facets` is set. Hits, the price filters
+// and the price facet are all in the converter's currency
+pub async fn search_products(
+    pool: &PgPool,
+    params: &ProductQueryParams,
+    page: &PageRequest<'_>,
+    converter: &Converter,
+) -> Result<SearchOutcome, sqlx::Error> {
+    let params = &ProductQueryParams {
+        min_price: params.min_price.as_ref().map(|price| converter.to_base(price)),
+        max_price: params.max_price.as_ref().map(|price| converter.to_base(price)),
+        ..params.clone()
+    };
+    let mut tx = pool.begin().await?;
+
+    let text_match = resolve_text_match(&mut tx, params).await?;
+    let rows = fetch_hits(&mut tx, params, &text_match, page).await?;
+    let total = count_total(&mut tx, params, &text_match).await?;
+
+    let facets = match params.facets {
+        Some(true) => Some(facet_counts(&mut tx, params, &text_match, converter).await?),
+        _ => None,
+    };
+
+    tx.commit().await?;
+
+    let paged = page.finish(rows, total);
+    let mut scores = Vec::with_capacity(paged.items.len());
+    let mut products = Vec::with_capacity(paged.items.len());
+    for row in paged.items {
+        scores.push((row.rank, row.snippet));
+        products.push(row.product);
+    }
+
+    let items = with_details(pool, products, converter)
+        .await?
+        .into_iter()
+        .zip(scores)
+        .map(|(product, (rank, snippet))| ProductSearchHit { product, rank, snippet })
+        .collect();
+
+    Ok(SearchOutcome {
+        hits: Paged { items, total: paged.total, next_cursor: paged.next_cursor },
+        fuzzy: matches!(text_match, TextMatch::Fuzzy(_)),
+        facets,
+    })
+}
+
+// product and category names completing `text`: prefix matches first, then by units sold,
+// then by trigram word similarity; one entry per distinct name
+pub async fn suggest(pool: &PgPool, text: &str, limit: u32) -> Result<Vec<Suggestion>, sqlx::Error> {
+    let escaped = text.to_lowercase().replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+
+    sqlx::query_as::<_, Suggestion>(
+        r#"
+        SELECT text, kind, id
+        FROM (
+            SELECT DISTINCT ON (kind, lower(text)) text, kind, id, is_prefix, popularity, similarity
+            FROM (
+                SELECT p.name AS text, 'product' AS kind, p.id,
+                       lower(p.name) LIKE $2 AS is_prefix,
+                       word_similarity($1, p.name) AS similarity,
+                       (SELECT COALESCE(-SUM(sm.quantity), 0) FROM stock_movements sm
+                        WHERE sm.product_id = p.id AND sm.kind = 'sale') AS popularity
+                FROM products p
+                WHERE p.deleted_at IS NULL AND (lower(p.name) LIKE $2 OR $1 <% p.name)
+                UNION ALL
+                SELECT c.name, 'category', c.id,
+                       lower(c.name) LIKE $2,
+                       word_similarity($1, c.name),
+                       (SELECT COALESCE(-SUM(sm.quantity), 0) FROM stock_movements sm
+                        JOIN products p ON p.id = sm.product_id
+                        WHERE p.category_id = c.id AND sm.kind = 'sale')
+                FROM categories c
+                WHERE c.deleted_at IS NULL AND (lower(c.name) LIKE $2 OR $1 <% c.name)
+            ) candidates
+            ORDER BY kind, lower(text), is_prefix DESC, popularity DESC, similarity DESC
+        ) deduped
+        ORDER BY is_prefix DESC, popularity DESC, similarity DESC, text
+        LIMIT $3
+        "#,
+    )
+    .bind(text)
+    .bind(format!("{}%", escaped))
+    .bind(limit as i64)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,169 @@
+use crate::models::product::ProductQueryParams;
+use crate::models::search::{ClickThroughReport, SearchReportParams, TopSearchQuery, ZeroResultQuery};
+use chrono::{Days, NaiveDateTime, Utc};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+// lower-case and collapse whitespace so "Red  Shoes" and "red shoes" count as one query
+pub fn normalize_query(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+// the filters that were applied, without paging
+pub fn search_filters(params: &ProductQueryParams) -> Value {
+    let mut filters = json!({
+        "category_id": params.category_id,
+        "include_descendants": params.include_descendants,
+        "min_price": params.min_price,
+        "max_price": params.max_price,
+        "in_stock": params.in_stock,
+        "sku": params.sku,
+        "options": params.options,
+        "tags": params.tags,
+        "attributes": params.attributes,
+        "sort": params.sort,
+    });
+
+    if let Value::Object(map) = &mut filters {
+        map.retain(|_, v| !v.is_null());
+    }
+
+    filters
+}
+
+pub async fn record_search(
+    pool: &PgPool,
+    id: Uuid,
+    query: &str,
+    filters: Value,
+    result_count: i64,
+    fuzzy: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO search_events (id, query, filters, result_count, fuzzy, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(id)
+    .bind(normalize_query(query))
+    .bind(filters)
+    .bind(result_count)
+    .bind(fuzzy)
+    .bind(Utc::now().naive_utc())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// written off the request path so a slow insert never delays search results
+pub fn spawn_record_search(pool: PgPool, id: Uuid, query: String, filters: Value, result_count: i64, fuzzy: bool) {
+    tokio::spawn(async move {
+        if let Err(e) = record_search(&pool, id, &query, filters, result_count, fuzzy).await {
+            eprintln!("❌ Failed to record search event: {:?}", e);
+        }
+    });
+}
+
+// the first click after a search is the one that counts
+pub async fn record_click(pool: &PgPool, search_id: Uuid, product_id: Uuid) -> Result<(), sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE search_events
+        SET clicked_product_id = COALESCE(clicked_product_id, $1),
+            clicked_at = COALESCE(clicked_at, $2)
+        WHERE id = $3
+        "#,
+    )
+    .bind(product_id)
+    .bind(Utc::now().naive_utc())
+    .bind(search_id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+
+    Ok(())
+}
+
+// [start, end) timestamps covering the requested days
+fn report_range(params: &SearchReportParams) -> (NaiveDateTime, NaiveDateTime) {
+    let today = Utc::now().date_naive();
+    let to = params.to.unwrap_or(today);
+    let from = params.from.unwrap_or_else(|| to - Days::new(30));
+
+    let start = from.and_hms_opt(0, 0, 0).unwrap_or_default();
+    let end = (to + Days::new(1)).and_hms_opt(0, 0, 0).unwrap_or_default();
+    (start, end)
+}
+
+fn report_limit(params: &SearchReportParams) -> i64 {
+    params.limit.unwrap_or(20).min(100) as i64
+}
+
+pub async fn top_queries(pool: &PgPool, params: &SearchReportParams) -> Result<Vec<TopSearchQuery>, sqlx::Error> {
+    let (start, end) = report_range(params);
+
+    sqlx::query_as::<_, TopSearchQuery>(
+        r#"
+        SELECT query,
+               COUNT(*) AS searches,
+               AVG(result_count)::float8 AS avg_results,
+               COUNT(clicked_product_id) AS clicks,
+               COUNT(clicked_product_id)::float8 / COUNT(*) AS click_through_rate
+        FROM search_events
+        WHERE created_at >= $1 AND created_at < $2
+        GROUP BY query
+        ORDER BY searches DESC, query
+        LIMIT $3
+        "#,
+    )
+    .bind(start)
+    .bind(end)
+    .bind(report_limit(params))
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn zero_result_queries(pool: &PgPool, params: &SearchReportParams) -> Result<Vec<ZeroResultQuery>, sqlx::Error> {
+    let (start, end) = report_range(params);
+
+    sqlx::query_as::<_, ZeroResultQuery>(
+        r#"
+        SELECT query, COUNT(*) AS searches, MAX(created_at) AS last_searched_at
+        FROM search_events
+        WHERE created_at >= $1 AND created_at < $2 AND result_count = 0
+        GROUP BY query
+        ORDER BY searches DESC, last_searched_at DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(start)
+    .bind(end)
+    .bind(report_limit(params))
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn click_through(pool: &PgPool, params: &SearchReportParams) -> Result<ClickThroughReport, sqlx::Error> {
+    let (start, end) = report_range(params);
+
+    sqlx::query_as::<_, ClickThroughReport>(
+        r#"
+        SELECT COUNT(*) AS searches,
+               COUNT(clicked_product_id) AS clicked_searches,
+               COUNT(*) FILTER (WHERE result_count = 0) AS zero_result_searches,
+               COALESCE(COUNT(clicked_product_id)::float8 / NULLIF(COUNT(*), 0), 0) AS click_through_rate
+        FROM search_events
+        WHERE created_at >= $1 AND created_at < $2
+        "#,
+    )
+    .bind(start)
+    .bind(end)
+    .fetch_one(pool)
+    .await
+}
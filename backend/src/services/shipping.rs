@@ -0,0 +1,407 @@
+use std::collections::HashMap;
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::Utc;
+use sqlx::{FromRow, PgConnection, PgPool, Postgres, QueryBuilder, Transaction};
+use uuid::Uuid;
+
+use crate::models::shipping::{
+    ProductShipping, ShippingArea, ShippingMethod, ShippingMethodDetails, ShippingMethodInput, ShippingQuote,
+    ShippingRequest, ShippingZone, ShippingZoneDetails, ShippingZoneInput, WeightRate,
+};
+use crate::pagination::{Keyed, PageRequest, Paged, SortDirection, SortKey};
+use crate::services::cart::base_priced_lines;
+use crate::services::currency::{base_currency, Converter};
+use crate::models::history::HistoryEntity;
+use crate::services::history::{lock_product_at, saved_version, set_actor, Versioned};
+use crate::services::promotion::discounts_for_cart;
+use crate::services::shipping_provider::{quote_all, ShippingRateProvider};
+
+const SHIPPING_METHOD_COLUMNS: &str = "id, zone_id, name, kind, price, free_threshold, pickup_warehouse_id, \
+    min_days, max_days, position, enabled, created_at, updated_at";
+
+async fn zone_details(conn: &mut PgConnection, zones: Vec<ShippingZone>) -> Result<Vec<ShippingZoneDetails>, sqlx::Error> {
+    let ids: Vec<Uuid> = zones.iter().map(|zone| zone.id).collect();
+
+    let mut areas: HashMap<Uuid, Vec<ShippingArea>> = HashMap::new();
+    for (zone_id, country, region) in sqlx::query_as::<_, (Uuid, String, Option<String>)>(
+        "SELECT zone_id, country, region FROM shipping_zone_areas WHERE zone_id = ANY($1) ORDER BY country, region NULLS FIRST",
+    )
+    .bind(&ids)
+    .fetch_all(&mut *conn)
+    .await?
+    {
+        areas.entry(zone_id).or_default().push(ShippingArea { country, region });
+    }
+
+    let methods = sqlx::query_as::<_, ShippingMethod>(&format!(
+        "SELECT {} FROM shipping_methods WHERE zone_id = ANY($1) ORDER BY position, name",
+        SHIPPING_METHOD_COLUMNS
+    ))
+    .bind(&ids)
+    .fetch_all(&mut *conn)
+    .await?;
+    let mut methods_by_zone: HashMap<Uuid, Vec<ShippingMethodDetails>> = HashMap::new();
+    for method in method_details(conn, methods).await? {
+        methods_by_zone.entry(method.method.zone_id).or_default().push(method);
+    }
+
+    Ok(zones
+        .into_iter()
+        .map(|zone| ShippingZoneDetails {
+            areas: areas.remove(&zone.id).unwrap_or_default(),
+            methods: methods_by_zone.remove(&zone.id).unwrap_or_default(),
+            zone,
+        })
+        .collect())
+}
+
+async fn method_details(
+    conn: &mut PgConnection,
+    methods: Vec<ShippingMethod>,
+) -> Result<Vec<ShippingMethodDetails>, sqlx::Error> {
+    let ids: Vec<Uuid> = methods.iter().map(|method| method.id).collect();
+    let mut rates: HashMap<Uuid, Vec<WeightRate>> = HashMap::new();
+    for (method_id, up_to_grams, price) in sqlx::query_as::<_, (Uuid, i32, BigDecimal)>(
+        "SELECT method_id, up_to_grams, price FROM shipping_weight_rates WHERE method_id = ANY($1) ORDER BY up_to_grams",
+    )
+    .bind(&ids)
+    .fetch_all(&mut *conn)
+    .await?
+    {
+        rates.entry(method_id).or_default().push(WeightRate { up_to_grams, price });
+    }
+
+    Ok(methods
+        .into_iter()
+        .map(|method| ShippingMethodDetails { weight_rates: rates.remove(&method.id).unwrap_or_default(), method })
+        .collect())
+}
+
+pub const SHIPPING_ZONE_SORTS: &[SortKey] = &[
+    SortKey { name: "name", expr: "name", sql_type: "text", direction: SortDirection::Asc },
+    SortKey { name: "created_at", expr: "created_at", sql_type: "timestamp", direction: SortDirection::Desc },
+];
+
+pub async fn list_shipping_zones(
+    pool: &PgPool,
+    page: &PageRequest<'_>,
+) -> Result<Paged<ShippingZoneDetails>, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+
+    let mut builder = QueryBuilder::new("SELECT id, name, created_at, updated_at");
+    page.push_sort_columns(&mut builder, "id");
+    builder.push(" FROM shipping_zones WHERE TRUE");
+    page.push_cursor_filter(&mut builder, "id");
+    page.push_order_and_limit(&mut builder, "id");
+
+    let rows = builder.build_query_as::<Keyed<ShippingZone>>().fetch_all(&mut *conn).await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM shipping_zones").fetch_one(&mut *conn).await?;
+    let zones = page.finish(rows, total);
+
+    Ok(Paged {
+        items: zone_details(&mut conn, zones.items).await?,
+        total: zones.total,
+        next_cursor: zones.next_cursor,
+    })
+}
+
+// RowNotFound when there's no such zone
+pub async fn get_shipping_zone(conn: &mut PgConnection, id: Uuid) -> Result<ShippingZoneDetails, sqlx::Error> {
+    let zone = sqlx::query_as::<_, ShippingZone>("SELECT id, name, created_at, updated_at FROM shipping_zones WHERE id = $1")
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let mut zones = zone_details(conn, vec![zone]).await?;
+    zones.pop().ok_or(sqlx::Error::RowNotFound)
+}
+
+// replaces the zone's areas. Expects countries upper-cased and empty regions turned into None.
+// RowNotFound when updating a zone that doesn't exist, a unique violation when an area
+// already belongs to another zone
+pub async fn save_shipping_zone(
+    pool: &PgPool,
+    id: Option<Uuid>,
+    input: &ShippingZoneInput,
+) -> Result<ShippingZoneDetails, sqlx::Error> {
+    let now = Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+
+    let id: Uuid = match id {
+        Some(id) => {
+            sqlx::query_scalar("UPDATE shipping_zones SET name = $1, updated_at = $2 WHERE id = $3 RETURNING id")
+                .bind(input.name.trim())
+                .bind(now)
+                .bind(id)
+                .fetch_one(&mut *tx)
+                .await?
+        }
+        None => {
+            sqlx::query_scalar(
+                "INSERT INTO shipping_zones (id, name, created_at, updated_at) VALUES ($1, $2, $3, $3) RETURNING id",
+            )
+            .bind(Uuid::new_v4())
+            .bind(input.name.trim())
+            .bind(now)
+            .fetch_one(&mut *tx)
+            .await?
+        }
+    };
+
+    sqlx::query("DELETE FROM shipping_zone_areas WHERE zone_id = $1").bind(id).execute(&mut *tx).await?;
+    let countries: Vec<&str> = input.areas.iter().map(|area| area.country.as_str()).collect();
+    let regions: Vec<Option<&str>> = input.areas.iter().map(|area| area.region.as_deref()).collect();
+    sqlx::query(
+        "INSERT INTO shipping_zone_areas (zone_id, country, region) SELECT $1, * FROM unnest($2::text[], $3::text[])",
+    )
+    .bind(id)
+    .bind(&countries)
+    .bind(&regions)
+    .execute(&mut *tx)
+    .await?;
+
+    let zone = get_shipping_zone(&mut tx, id).await?;
+    tx.commit().await?;
+
+    Ok(zone)
+}
+
+// its areas and methods go with it
+pub async fn delete_shipping_zone(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM shipping_zones WHERE id = $1").bind(id).execute(pool).await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// a foreign key violation for an unknown zone or warehouse
+pub async fn create_shipping_method(
+    pool: &PgPool,
+    zone_id: Uuid,
+    input: &ShippingMethodInput,
+) -> Result<ShippingMethodDetails, sqlx::Error> {
+    let now = Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+
+    let method = sqlx::query_as::<_, ShippingMethod>(&format!(
+        r#"
+        INSERT INTO shipping_methods (id, zone_id, name, kind, price, free_threshold, pickup_warehouse_id,
+                                      min_days, max_days, position, enabled, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $12)
+        RETURNING {}
+        "#,
+        SHIPPING_METHOD_COLUMNS
+    ))
+    .bind(Uuid::new_v4())
+    .bind(zone_id)
+    .bind(input.name.trim())
+    .bind(input.kind)
+    .bind(input.price.clone().unwrap_or_else(BigDecimal::zero))
+    .bind(&input.free_threshold)
+    .bind(input.pickup_warehouse_id)
+    .bind(input.min_days)
+    .bind(input.max_days)
+    .bind(input.position)
+    .bind(input.enabled.unwrap_or(true))
+    .bind(now)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    save_weight_rates(tx, method, input).await
+}
+
+// RowNotFound when there's no such method, a foreign key violation for an unknown warehouse
+pub async fn update_shipping_method(
+    pool: &PgPool,
+    id: Uuid,
+    input: &ShippingMethodInput,
+) -> Result<ShippingMethodDetails, sqlx::Error> {
+    let now = Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+
+    let method = sqlx::query_as::<_, ShippingMethod>(&format!(
+        r#"
+        UPDATE shipping_methods
+        SET name = $1, kind = $2, price = $3, free_threshold = $4, pickup_warehouse_id = $5, min_days = $6,
+            max_days = $7, position = $8, enabled = $9, updated_at = $10
+        WHERE id = $11
+        RETURNING {}
+        "#,
+        SHIPPING_METHOD_COLUMNS
+    ))
+    .bind(input.name.trim())
+    .bind(input.kind)
+    .bind(input.price.clone().unwrap_or_else(BigDecimal::zero))
+    .bind(&input.free_threshold)
+    .bind(input.pickup_warehouse_id)
+    .bind(input.min_days)
+    .bind(input.max_days)
+    .bind(input.position)
+    .bind(input.enabled.unwrap_or(true))
+    .bind(now)
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    save_weight_rates(tx, method, input).await
+}
+
+// replaces the method's weight brackets and commits
+async fn save_weight_rates(
+    mut tx: Transaction<'_, Postgres>,
+    method: ShippingMethod,
+    input: &ShippingMethodInput,
+) -> Result<ShippingMethodDetails, sqlx::Error> {
+    sqlx::query("DELETE FROM shipping_weight_rates WHERE method_id = $1").bind(method.id).execute(&mut *tx).await?;
+    let up_to: Vec<i32> = input.weight_rates.iter().map(|rate| rate.up_to_grams).collect();
+    let prices: Vec<BigDecimal> = input.weight_rates.iter().map(|rate| rate.price.clone()).collect();
+    sqlx::query(
+        "INSERT INTO shipping_weight_rates (method_id, up_to_grams, price) SELECT $1, * FROM unnest($2::int[], $3::numeric[])",
+    )
+    .bind(method.id)
+    .bind(&up_to)
+    .bind(&prices)
+    .execute(&mut *tx)
+    .await?;
+
+    let mut methods = method_details(&mut tx, vec![method]).await?;
+    tx.commit().await?;
+
+    methods.pop().ok_or(sqlx::Error::RowNotFound)
+}
+
+pub async fn delete_shipping_method(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM shipping_methods WHERE id = $1").bind(id).execute(pool).await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// RowNotFound for missing and trashed products
+pub async fn product_shipping(pool: &PgPool, product_id: Uuid) -> Result<ProductShipping, sqlx::Error> {
+    sqlx::query_as::<_, ProductShipping>(
+        "SELECT weight_grams, length_mm, width_mm, height_mm FROM products WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(product_id)
+    .fetch_one(pool)
+    .await
+}
+
+// RowNotFound for missing and trashed products
+pub async fn set_product_shipping(
+    pool: &PgPool,
+    product_id: Uuid,
+    shipping: &ProductShipping,
+    expected_version: Option<i32>,
+    actor_id: Option<Uuid>,
+) -> Result<Versioned<ProductShipping>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    set_actor(&mut tx, actor_id).await?;
+
+    if !lock_product_at(&mut tx, product_id, expected_version).await? {
+        return Ok(Versioned::Stale);
+    }
+
+    let saved = sqlx::query_as::<_, ProductShipping>(
+        r#"
+        UPDATE products
+        SET weight_grams = $1, length_mm = $2, width_mm = $3, height_mm = $4, updated_at = $5
+        WHERE id = $6 AND deleted_at IS NULL
+        RETURNING weight_grams, length_mm, width_mm, height_mm
+        "#,
+    )
+    .bind(shipping.weight_grams)
+    .bind(shipping.length_mm)
+    .bind(shipping.width_mm)
+    .bind(shipping.height_mm)
+    .bind(Utc::now().naive_utc())
+    .bind(product_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    let version = saved_version(&mut tx, HistoryEntity::Product, product_id).await?;
+
+    tx.commit().await?;
+
+    Ok(Versioned::Saved(saved, version))
+}
+
+#[derive(FromRow)]
+struct ParcelItem {
+    id: Uuid,
+    weight_grams: Option<i32>,
+    length_mm: Option<i32>,
+    width_mm: Option<i32>,
+    height_mm: Option<i32>,
+}
+
+// volumetric weight at the usual 5000 cm³ per kilogram
+fn volumetric_grams(item: &ParcelItem) -> i64 {
+    match (item.length_mm, item.width_mm, item.height_mm) {
+        (Some(l), Some(w), Some(h)) => i64::from(l) * i64::from(w) * i64::from(h) / 5000,
+        _ => 0,
+    }
+}
+
+// delivery options for the user's cart to `country`/`region`, cheapest first, in the
+// converter's currency. A free shipping promotion on the cart makes every option free
+pub async fn quote_cart(
+    pool: &PgPool,
+    user_id: Uuid,
+    country: String,
+    region: Option<String>,
+    converter: &Converter,
+    providers: &[Box<dyn ShippingRateProvider>],
+) -> Result<ShippingQuote, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    let now = Utc::now().naive_utc();
+    let lines = base_priced_lines(&mut conn, user_id, now).await?;
+    let base = base_currency(&mut conn).await?;
+    let discounts = discounts_for_cart(&mut conn, user_id, &lines, now, &base, false).await?;
+
+    let product_ids: Vec<Uuid> = lines.iter().map(|line| line.item.product_id).collect();
+    let items: HashMap<Uuid, ParcelItem> = sqlx::query_as::<_, ParcelItem>(
+        "SELECT id, weight_grams, length_mm, width_mm, height_mm FROM products WHERE id = ANY($1)",
+    )
+    .bind(&product_ids)
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|item| (item.id, item))
+    .collect();
+    drop(conn);
+
+    let mut weight_grams = 0;
+    let mut volumetric_weight_grams = 0;
+    let mut subtotal = BigDecimal::zero();
+    for line in &lines {
+        let quantity = i64::from(line.item.quantity);
+        if let Some(item) = items.get(&line.item.product_id) {
+            weight_grams += i64::from(item.weight_grams.unwrap_or(0)) * quantity;
+            volumetric_weight_grams += volumetric_grams(item) * quantity;
+        }
+        subtotal += &line.unit_price * BigDecimal::from(line.item.quantity);
+    }
+    let request = ShippingRequest {
+        country,
+        region,
+        weight_grams,
+        volumetric_weight_grams,
+        subtotal: (subtotal - &discounts.discount_total).max(BigDecimal::zero()),
+    };
+
+    let mut options = quote_all(providers, &request).await;
+    for option in &mut options {
+        if discounts.free_shipping && option.price > BigDecimal::zero() {
+            option.original_price = Some(converter.convert(&option.price));
+            option.price = BigDecimal::zero();
+        }
+        option.price = converter.convert(&option.price);
+    }
+    options.sort_by(|a, b| a.price.cmp(&b.price).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(ShippingQuote {
+        currency: converter.code().to_string(),
+        weight_grams,
+        free_shipping: discounts.free_shipping,
+        options,
+    })
+}
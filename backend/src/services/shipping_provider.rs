@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::env;
+
+use axum::async_trait;
+use bigdecimal::{BigDecimal, Zero};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::shipping::{ShippingMethod, ShippingMethodKind, ShippingOption, ShippingRequest, WeightRate};
+
+pub type ShippingError = Box<dyn std::error::Error + Send + Sync>;
+
+// a source of delivery options and their prices
+#[async_trait]
+pub trait ShippingRateProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+    // options for the request, in the base currency; none when it doesn't deliver there
+    async fn quote(&self, request: &ShippingRequest) -> Result<Vec<ShippingOption>, ShippingError>;
+}
+
+// the methods set up for the address's shipping zone
+pub struct TableRateProvider {
+    pool: PgPool,
+}
+
+impl TableRateProvider {
+    pub fn new(pool: PgPool) -> Self {
+        TableRateProvider { pool }
+    }
+}
+
+#[async_trait]
+impl ShippingRateProvider for TableRateProvider {
+    fn name(&self) -> &'static str {
+        "table"
+    }
+
+    async fn quote(&self, request: &ShippingRequest) -> Result<Vec<ShippingOption>, ShippingError> {
+        // the region's zone when it has one, else the country's
+        let methods = sqlx::query_as::<_, ShippingMethod>(
+            r#"
+            SELECT id, zone_id, name, kind, price, free_threshold, pickup_warehouse_id, min_days, max_days,
+                   position, enabled, created_at, updated_at
+            FROM shipping_methods
+            WHERE enabled AND zone_id = (
+                SELECT zone_id FROM shipping_zone_areas
+                WHERE country = $1 AND (region IS NULL OR upper(region) = upper($2))
+                ORDER BY region IS NULL
+                LIMIT 1
+            )
+            ORDER BY position, name
+            "#,
+        )
+        .bind(&request.country)
+        .bind(request.region.as_deref())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let ids: Vec<Uuid> = methods.iter().map(|m| m.id).collect();
+        let mut brackets: HashMap<Uuid, Vec<WeightRate>> = HashMap::new();
+        for (method_id, rate) in sqlx::query_as::<_, (Uuid, i32, BigDecimal)>(
+            "SELECT method_id, up_to_grams, price FROM shipping_weight_rates WHERE method_id = ANY($1) ORDER BY up_to_grams",
+        )
+        .bind(&ids)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|(method_id, up_to_grams, price)| (method_id, WeightRate { up_to_grams, price }))
+        {
+            brackets.entry(method_id).or_default().push(rate);
+        }
+
+        Ok(methods
+            .into_iter()
+            .filter_map(|method| {
+                let price = match method.kind {
+                    ShippingMethodKind::FlatRate | ShippingMethodKind::LocalPickup => method.price.clone(),
+                    ShippingMethodKind::WeightBased => brackets
+                        .get(&method.id)?
+                        .iter()
+                        .find(|bracket| i64::from(bracket.up_to_grams) >= request.weight_grams)?
+                        .price
+                        .clone(),
+                    ShippingMethodKind::FreeOverThreshold => match &method.free_threshold {
+                        Some(threshold) if request.subtotal >= *threshold => BigDecimal::zero(),
+                        _ => method.price.clone(),
+                    },
+                };
+                Some(ShippingOption {
+                    id: format!("{}:{}", self.name(), method.id),
+                    provider: self.name().to_string(),
+                    name: method.name,
+                    kind: Some(method.kind),
+                    price,
+                    original_price: None,
+                    min_days: method.min_days,
+                    max_days: method.max_days,
+                    pickup_warehouse_id: method.pickup_warehouse_id,
+                })
+            })
+            .collect())
+    }
+}
+
+// stands in for a carrier's rate API in development and tests: a standard and an express
+// service priced per started kilogram of whichever is larger, actual or volumetric weight
+pub struct StubCarrierProvider;
+
+impl StubCarrierProvider {
+    fn service(&self, code: &str, name: &str, base: i64, per_kg: i64, days: (i32, i32), kilograms: i64) -> ShippingOption {
+        ShippingOption {
+            id: format!("{}:{}", self.name(), code),
+            provider: self.name().to_string(),
+            name: name.to_string(),
+            kind: None,
+            price: BigDecimal::from(base) + BigDecimal::from(per_kg * kilograms) / BigDecimal::from(100),
+            original_price: None,
+            min_days: Some(days.0),
+            max_days: Some(days.1),
+            pickup_warehouse_id: None,
+        }
+    }
+}
+
+#[async_trait]
+impl ShippingRateProvider for StubCarrierProvider {
+    fn name(&self) -> &'static str {
+        "stub-carrier"
+    }
+
+    async fn quote(&self, request: &ShippingRequest) -> Result<Vec<ShippingOption>, ShippingError> {
+        let billable = request.weight_grams.max(request.volumetric_weight_grams);
+        let kilograms = ((billable + 999) / 1000).max(1);
+
+        Ok(vec![
+            self.service("standard", "Stub Carrier Standard", 4, 150, (3, 6), kilograms),
+            self.service("express", "Stub Carrier Express", 9, 300, (1, 2), kilograms),
+        ])
+    }
+}
+
+// the shipping tables are always on; SHIPPING_STUB_CARRIER adds the stub carrier
+pub fn shipping_providers_from_env(pool: PgPool) -> Vec<Box<dyn ShippingRateProvider>> {
+    let mut providers: Vec<Box<dyn ShippingRateProvider>> = vec![Box::new(TableRateProvider::new(pool))];
+
+    if env::var("SHIPPING_STUB_CARRIER").is_ok_and(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes")) {
+        providers.push(Box::new(StubCarrierProvider));
+    }
+
+    providers
+}
+
+// options from every provider; one failing provider doesn't stop the others
+pub async fn quote_all(providers: &[Box<dyn ShippingRateProvider>], request: &ShippingRequest) -> Vec<ShippingOption> {
+    let mut options = Vec::new();
+    for provider in providers {
+        match provider.quote(request).await {
+            Ok(quoted) => options.extend(quoted),
+            Err(e) => eprintln!("❌ {} shipping rates failed: {:?}", provider.name(), e),
+        }
+    }
+    options
+}
@@ -0,0 +1,156 @@
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+pub const INVALID_SLUG: &str = "Slug must be lower-case letters, digits and single dashes";
+
+// which kind of record a slug belongs to
+#[derive(Clone, Copy)]
+pub enum SlugOwner {
+    Product,
+    Category,
+}
+
+impl SlugOwner {
+    fn table(self) -> &'static str {
+        match self {
+            SlugOwner::Product => "products",
+            SlugOwner::Category => "categories",
+        }
+    }
+
+    fn history_table(self) -> &'static str {
+        match self {
+            SlugOwner::Product => "product_slug_history",
+            SlugOwner::Category => "category_slug_history",
+        }
+    }
+
+    fn history_key(self) -> &'static str {
+        match self {
+            SlugOwner::Product => "product_id",
+            SlugOwner::Category => "category_id",
+        }
+    }
+
+    fn fallback(self) -> &'static str {
+        match self {
+            SlugOwner::Product => "product",
+            SlugOwner::Category => "category",
+        }
+    }
+}
+
+// "Men's T-Shirt (XL)" -> "men-s-t-shirt-xl"
+pub fn slugify(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// lower-case ascii words joined by single dashes
+pub fn is_valid_slug(slug: &str) -> bool {
+    !slug.is_empty() && slug.len() <= 200 && slugify(slug) == slug
+}
+
+// first free slug out of base, base-2, base-3, ...; slugs still redirecting for
+// another record count as taken. Holds a lock on the base until the transaction ends
+// so concurrent creates with the same name get consecutive numbers.
+pub async fn unique_slug(conn: &mut PgConnection, owner: SlugOwner, name: &str) -> Result<String, sqlx::Error> {
+    let base = match slugify(name) {
+        slug if slug.is_empty() => owner.fallback().to_string(),
+        slug => slug,
+    };
+
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+        .bind(format!("{}:{}", owner.table(), base))
+        .execute(&mut *conn)
+        .await?;
+
+    let pattern = format!("^{}(-[0-9]+)?$", base);
+    let taken: Vec<String> = sqlx::query_scalar(&format!(
+        "SELECT slug FROM {} WHERE slug ~ $1 UNION SELECT slug FROM {} WHERE slug ~ $1",
+        owner.table(),
+        owner.history_table()
+    ))
+    .bind(&pattern)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let slug = std::iter::once(base.clone())
+        .chain((2..).map(|n| format!("{}-{}", base, n)))
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap_or(base);
+
+    Ok(slug)
+}
+
+// a slug taken explicitly stops redirecting to whatever record used to have it
+pub async fn release_slug(conn: &mut PgConnection, owner: SlugOwner, slug: &str) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!("DELETE FROM {} WHERE slug = $1", owner.history_table()))
+        .bind(slug)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+// keep the old slug as a redirect and free the new one from any redirect;
+// call before writing the new slug onto the record
+pub async fn record_slug_change(
+    conn: &mut PgConnection,
+    owner: SlugOwner,
+    id: Uuid,
+    old_slug: &str,
+    new_slug: &str,
+) -> Result<(), sqlx::Error> {
+    if old_slug == new_slug {
+        return Ok(());
+    }
+
+    release_slug(&mut *conn, owner, new_slug).await?;
+
+    sqlx::query(&format!(
+        "INSERT INTO {table} (slug, {key}) VALUES ($1, $2) ON CONFLICT (slug) DO UPDATE SET {key} = EXCLUDED.{key}, created_at = CURRENT_TIMESTAMP",
+        table = owner.history_table(),
+        key = owner.history_key()
+    ))
+    .bind(old_slug)
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// the record an old slug now points to, with its current slug
+pub async fn resolve_old_slug(
+    conn: &mut PgConnection,
+    owner: SlugOwner,
+    slug: &str,
+) -> Result<Option<(Uuid, String)>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT t.id, t.slug FROM {history} h JOIN {table} t ON t.id = h.{key} WHERE h.slug = $1",
+        history = owner.history_table(),
+        table = owner.table(),
+        key = owner.history_key()
+    ))
+    .bind(slug)
+    .fetch_optional(conn)
+    .await
+}
+
+// 301 to the record's current address, with the details in the body for API clients
+pub fn slug_redirect(location: String, id: Uuid, slug: &str) -> Response {
+    (
+        StatusCode::MOVED_PERMANENTLY,
+        [(header::LOCATION, location.clone())],
+        Json(json!({ "id": id, "slug": slug, "location": location })),
+    )
+        .into_response()
+}
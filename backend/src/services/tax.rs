@@ -0,0 +1,511 @@
+use std::collections::HashMap;
+
+use axum::async_trait;
+use bigdecimal::{BigDecimal, Zero};
+use chrono::Utc;
+use sqlx::{FromRow, PgConnection, PgPool, QueryBuilder};
+use uuid::Uuid;
+
+use crate::config::prices_include_tax;
+use crate::models::cart::BaseCartLine;
+use crate::models::currency::Currency;
+use crate::models::history::HistoryEntity;
+use crate::models::tax::{
+    TaxAddress, TaxBreakdown, TaxClass, TaxClassInput, TaxLine, TaxRate, TaxZone, TaxZoneDetails, TaxZoneInput,
+    TaxableLine,
+};
+use crate::pagination::{Keyed, PageRequest, Paged, SortDirection, SortKey};
+use crate::services::currency::{base_currency, round};
+use crate::services::history::{lock_product_at, saved_version, set_actor, Versioned};
+
+// works out the tax on a set of lines shipped to an address
+#[async_trait]
+pub trait TaxCalculator: Send + Sync {
+    fn name(&self) -> &'static str;
+    // amounts in and out are in `currency`, rounded by its rules; no address means no tax
+    async fn calculate(
+        &self,
+        conn: &mut PgConnection,
+        address: Option<&TaxAddress>,
+        lines: &[TaxableLine],
+        currency: &Currency,
+    ) -> Result<TaxBreakdown, sqlx::Error>;
+}
+
+// rates from the tax_zones and tax_rates tables, by each product's tax class
+pub struct TableTaxCalculator {
+    prices_include_tax: bool,
+}
+
+impl TableTaxCalculator {
+    pub fn new(prices_include_tax: bool) -> Self {
+        TableTaxCalculator { prices_include_tax }
+    }
+}
+
+#[derive(FromRow)]
+struct ProductRate {
+    product_id: Uuid,
+    tax_class: Option<String>,
+    zone: Option<String>,
+    rate_name: Option<String>,
+    rate: Option<BigDecimal>,
+}
+
+#[async_trait]
+impl TaxCalculator for TableTaxCalculator {
+    fn name(&self) -> &'static str {
+        "table"
+    }
+
+    async fn calculate(
+        &self,
+        conn: &mut PgConnection,
+        address: Option<&TaxAddress>,
+        lines: &[TaxableLine],
+        currency: &Currency,
+    ) -> Result<TaxBreakdown, sqlx::Error> {
+        let product_ids: Vec<Uuid> = lines.iter().map(|line| line.product_id).collect();
+        // the region's own rate for the class when it has one, else the country's
+        let rates: HashMap<Uuid, ProductRate> = sqlx::query_as::<_, ProductRate>(
+            r#"
+            SELECT p.id AS product_id, c.name AS tax_class, r.zone, r.rate_name, r.rate
+            FROM products p
+            LEFT JOIN tax_classes c ON c.id = COALESCE(p.tax_class_id, (SELECT id FROM tax_classes WHERE is_default))
+            LEFT JOIN LATERAL (
+                SELECT z.name AS zone, tr.name AS rate_name, tr.rate
+                FROM tax_rates tr
+                JOIN tax_zones z ON z.id = tr.zone_id
+                WHERE tr.tax_class_id = c.id AND z.country = $2
+                  AND (z.region IS NULL OR upper(z.region) = upper($3))
+                ORDER BY z.region IS NULL
+                LIMIT 1
+            ) r ON TRUE
+            WHERE p.id = ANY($1)
+            "#,
+        )
+        .bind(&product_ids)
+        .bind(address.map(|a| a.country.as_str()))
+        .bind(address.and_then(|a| a.region.as_deref()))
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|row| (row.product_id, row))
+        .collect();
+
+        let hundred = BigDecimal::from(100);
+        let mut tax_total = BigDecimal::zero();
+        let mut breakdown = Vec::with_capacity(lines.len());
+        for line in lines {
+            let found = rates.get(&line.product_id);
+            let rate = found.and_then(|r| r.rate.clone()).unwrap_or_else(BigDecimal::zero);
+            let (taxable_amount, tax_amount) = if self.prices_include_tax {
+                let tax = round(&(&line.amount * &rate / (&hundred + &rate)), currency);
+                (&line.amount - &tax, tax)
+            } else {
+                (line.amount.clone(), round(&(&line.amount * &rate / &hundred), currency))
+            };
+            tax_total += &tax_amount;
+            breakdown.push(TaxLine {
+                product_id: line.product_id,
+                variant_id: line.variant_id,
+                quantity: line.quantity,
+                tax_class: found.and_then(|r| r.tax_class.clone()),
+                zone: found.and_then(|r| r.zone.clone()),
+                rate_name: found.and_then(|r| r.rate_name.clone()),
+                rate,
+                taxable_amount,
+                tax_amount,
+            });
+        }
+
+        Ok(TaxBreakdown { prices_include_tax: self.prices_include_tax, lines: breakdown, tax_total })
+    }
+}
+
+// the tax tables are the only calculator for now; PRICES_INCLUDE_TAX picks the pricing mode
+pub fn tax_calculator_from_env() -> Box<dyn TaxCalculator> {
+    Box::new(TableTaxCalculator::new(prices_include_tax()))
+}
+
+// the cart lines as the customer pays for them: each less the discounts that came off it,
+// `line_discounts` being one amount per line as the promotion engine shared them out
+pub fn taxable_lines(lines: &[BaseCartLine], line_discounts: &[BigDecimal]) -> Vec<TaxableLine> {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let total = &line.unit_price * BigDecimal::from(line.item.quantity);
+            let discount = line_discounts.get(i).cloned().unwrap_or_else(BigDecimal::zero);
+            TaxableLine {
+                product_id: line.item.product_id,
+                variant_id: line.item.variant_id,
+                quantity: line.item.quantity,
+                amount: total - discount,
+            }
+        })
+        .collect()
+}
+
+// the tax charged on a completed checkout, kept as it was at the time
+pub async fn record_checkout_tax(
+    conn: &mut PgConnection,
+    checkout_id: Uuid,
+    user_id: Uuid,
+    address: Option<&TaxAddress>,
+    calculator: &str,
+    tax: &TaxBreakdown,
+) -> Result<(), sqlx::Error> {
+    for line in &tax.lines {
+        sqlx::query(
+            r#"
+            INSERT INTO checkout_tax_lines (checkout_id, user_id, product_id, variant_id, quantity, tax_class, zone,
+                                            rate_name, rate, country, region, prices_include_tax, calculator,
+                                            taxable_amount, tax_amount)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            "#,
+        )
+        .bind(checkout_id)
+        .bind(user_id)
+        .bind(line.product_id)
+        .bind(line.variant_id)
+        .bind(line.quantity)
+        .bind(&line.tax_class)
+        .bind(&line.zone)
+        .bind(&line.rate_name)
+        .bind(&line.rate)
+        .bind(address.map(|a| a.country.as_str()))
+        .bind(address.and_then(|a| a.region.as_deref()))
+        .bind(tax.prices_include_tax)
+        .bind(calculator)
+        .bind(&line.taxable_amount)
+        .bind(&line.tax_amount)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[derive(FromRow)]
+struct RecordedTaxLine {
+    prices_include_tax: bool,
+    #[sqlx(flatten)]
+    line: TaxLine,
+}
+
+// RowNotFound when the user has no completed checkout with that id
+pub async fn checkout_tax(pool: &PgPool, checkout_id: Uuid, user_id: Uuid) -> Result<TaxBreakdown, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    let rows = sqlx::query_as::<_, RecordedTaxLine>(
+        r#"
+        SELECT prices_include_tax, product_id, variant_id, quantity, tax_class, zone, rate_name, rate,
+               taxable_amount, tax_amount
+        FROM checkout_tax_lines
+        WHERE checkout_id = $1 AND user_id = $2
+        ORDER BY created_at, id
+        "#,
+    )
+    .bind(checkout_id)
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let prices_include_tax = rows.first().map(|row| row.prices_include_tax).ok_or(sqlx::Error::RowNotFound)?;
+    // numerics come back with postgres' own scale; show them as the currency writes them
+    let base = base_currency(&mut conn).await?;
+    let lines: Vec<TaxLine> = rows
+        .into_iter()
+        .map(|row| TaxLine {
+            taxable_amount: round(&row.line.taxable_amount, &base),
+            tax_amount: round(&row.line.tax_amount, &base),
+            ..row.line
+        })
+        .collect();
+    let tax_total = lines.iter().fold(BigDecimal::zero(), |sum, line| sum + &line.tax_amount);
+
+    Ok(TaxBreakdown { prices_include_tax, lines, tax_total })
+}
+
+const TAX_CLASS_COLUMNS: &str = "id, name, description, is_default, created_at, updated_at";
+
+// the default class first, then by name
+pub const TAX_CLASS_SORTS: &[SortKey] = &[
+    SortKey { name: "name", expr: "name", sql_type: "text", direction: SortDirection::Asc },
+    SortKey { name: "created_at", expr: "created_at", sql_type: "timestamp", direction: SortDirection::Desc },
+];
+
+pub async fn list_tax_classes(pool: &PgPool, page: &PageRequest<'_>) -> Result<Paged<TaxClass>, sqlx::Error> {
+    let mut builder = QueryBuilder::new(format!("SELECT {}", TAX_CLASS_COLUMNS));
+    page.push_sort_columns(&mut builder, "id");
+    builder.push(" FROM tax_classes WHERE TRUE");
+    page.push_cursor_filter(&mut builder, "id");
+    page.push_order_and_limit(&mut builder, "id");
+
+    let rows = builder.build_query_as::<Keyed<TaxClass>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tax_classes").fetch_one(pool).await?;
+
+    Ok(page.finish(rows, total))
+}
+
+pub async fn get_tax_class(pool: &PgPool, id: Uuid) -> Result<Option<TaxClass>, sqlx::Error> {
+    sqlx::query_as::<_, TaxClass>(&format!("SELECT {} FROM tax_classes WHERE id = $1", TAX_CLASS_COLUMNS))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+// making a class the default takes it from whichever class had it.
+// RowNotFound when updating a class that doesn't exist
+pub async fn save_tax_class(pool: &PgPool, id: Option<Uuid>, input: &TaxClassInput) -> Result<TaxClass, sqlx::Error> {
+    let now = Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+
+    if input.is_default {
+        sqlx::query("UPDATE tax_classes SET is_default = FALSE, updated_at = $1 WHERE is_default AND id IS DISTINCT FROM $2")
+            .bind(now)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let class = match id {
+        Some(id) => {
+            // the default only moves by making another class the default
+            sqlx::query_as::<_, TaxClass>(&format!(
+                r#"
+                UPDATE tax_classes SET name = $1, description = $2, is_default = is_default OR $3, updated_at = $4
+                WHERE id = $5
+                RETURNING {}
+                "#,
+                TAX_CLASS_COLUMNS
+            ))
+            .bind(input.name.trim())
+            .bind(&input.description)
+            .bind(input.is_default)
+            .bind(now)
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?
+        }
+        None => {
+            sqlx::query_as::<_, TaxClass>(&format!(
+                r#"
+                INSERT INTO tax_classes (id, name, description, is_default, created_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, $5)
+                RETURNING {}
+                "#,
+                TAX_CLASS_COLUMNS
+            ))
+            .bind(Uuid::new_v4())
+            .bind(input.name.trim())
+            .bind(&input.description)
+            .bind(input.is_default)
+            .bind(now)
+            .fetch_one(&mut *tx)
+            .await?
+        }
+    };
+
+    tx.commit().await?;
+
+    Ok(class)
+}
+
+// products in the class fall back to the default one; the default itself can't be deleted
+pub async fn delete_tax_class(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM tax_classes WHERE id = $1 AND NOT is_default")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+const TAX_ZONE_COLUMNS: &str = "id, name, country, region, created_at, updated_at";
+
+async fn zone_rates(pool: &PgPool, zone_ids: &[Uuid]) -> Result<Vec<TaxRate>, sqlx::Error> {
+    sqlx::query_as::<_, TaxRate>(
+        r#"
+        SELECT r.zone_id, r.tax_class_id, c.name AS tax_class, r.name, r.rate, r.updated_at
+        FROM tax_rates r
+        JOIN tax_classes c ON c.id = r.tax_class_id
+        WHERE r.zone_id = ANY($1)
+        ORDER BY c.is_default DESC, c.name
+        "#,
+    )
+    .bind(zone_ids)
+    .fetch_all(pool)
+    .await
+}
+
+// by country, the whole-country zone before its regions
+pub const TAX_ZONE_SORTS: &[SortKey] = &[
+    // a country's own zone comes before its regions
+    SortKey {
+        name: "country",
+        expr: "country || '/' || COALESCE(region, '')",
+        sql_type: "text",
+        direction: SortDirection::Asc,
+    },
+    SortKey { name: "name", expr: "name", sql_type: "text", direction: SortDirection::Asc },
+];
+
+pub async fn list_tax_zones(pool: &PgPool, page: &PageRequest<'_>) -> Result<Paged<TaxZoneDetails>, sqlx::Error> {
+    let mut builder = QueryBuilder::new(format!("SELECT {}", TAX_ZONE_COLUMNS));
+    page.push_sort_columns(&mut builder, "id");
+    builder.push(" FROM tax_zones WHERE TRUE");
+    page.push_cursor_filter(&mut builder, "id");
+    page.push_order_and_limit(&mut builder, "id");
+
+    let rows = builder.build_query_as::<Keyed<TaxZone>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tax_zones").fetch_one(pool).await?;
+    let zones = page.finish(rows, total);
+
+    let ids: Vec<Uuid> = zones.items.iter().map(|zone| zone.id).collect();
+    let mut rates: HashMap<Uuid, Vec<TaxRate>> = HashMap::new();
+    for rate in zone_rates(pool, &ids).await? {
+        rates.entry(rate.zone_id).or_default().push(rate);
+    }
+
+    Ok(Paged {
+        items: zones
+            .items
+            .into_iter()
+            .map(|zone| {
+                let rates = rates.remove(&zone.id).unwrap_or_default();
+                TaxZoneDetails { zone, rates }
+            })
+            .collect(),
+        total: zones.total,
+        next_cursor: zones.next_cursor,
+    })
+}
+
+// RowNotFound when there's no such zone
+pub async fn get_tax_zone(pool: &PgPool, id: Uuid) -> Result<TaxZoneDetails, sqlx::Error> {
+    let zone = sqlx::query_as::<_, TaxZone>(&format!("SELECT {} FROM tax_zones WHERE id = $1", TAX_ZONE_COLUMNS))
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+    let rates = zone_rates(pool, &[id]).await?;
+
+    Ok(TaxZoneDetails { zone, rates })
+}
+
+// expects the country upper-cased and an empty region turned into None.
+// RowNotFound when updating a zone that doesn't exist
+pub async fn save_tax_zone(pool: &PgPool, id: Option<Uuid>, input: &TaxZoneInput) -> Result<TaxZoneDetails, sqlx::Error> {
+    let now = Utc::now().naive_utc();
+    let id = match id {
+        Some(id) => {
+            sqlx::query_scalar(
+                "UPDATE tax_zones SET name = $1, country = $2, region = $3, updated_at = $4 WHERE id = $5 RETURNING id",
+            )
+            .bind(input.name.trim())
+            .bind(&input.country)
+            .bind(&input.region)
+            .bind(now)
+            .bind(id)
+            .fetch_one(pool)
+            .await?
+        }
+        None => {
+            sqlx::query_scalar(
+                r#"
+                INSERT INTO tax_zones (id, name, country, region, created_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, $5)
+                RETURNING id
+                "#,
+            )
+            .bind(Uuid::new_v4())
+            .bind(input.name.trim())
+            .bind(&input.country)
+            .bind(&input.region)
+            .bind(now)
+            .fetch_one(pool)
+            .await?
+        }
+    };
+
+    get_tax_zone(pool, id).await
+}
+
+pub async fn delete_tax_zone(pool: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM tax_zones WHERE id = $1").bind(id).execute(pool).await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// foreign key violation when the zone or class doesn't exist
+pub async fn set_tax_rate(
+    pool: &PgPool,
+    zone_id: Uuid,
+    tax_class_id: Uuid,
+    name: &str,
+    rate: &BigDecimal,
+) -> Result<TaxZoneDetails, sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO tax_rates (zone_id, tax_class_id, name, rate, updated_at)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (zone_id, tax_class_id) DO UPDATE
+        SET name = EXCLUDED.name, rate = EXCLUDED.rate, updated_at = EXCLUDED.updated_at
+        "#,
+    )
+    .bind(zone_id)
+    .bind(tax_class_id)
+    .bind(name.trim())
+    .bind(rate)
+    .bind(Utc::now().naive_utc())
+    .execute(pool)
+    .await?;
+
+    get_tax_zone(pool, zone_id).await
+}
+
+pub async fn delete_tax_rate(pool: &PgPool, zone_id: Uuid, tax_class_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM tax_rates WHERE zone_id = $1 AND tax_class_id = $2")
+        .bind(zone_id)
+        .bind(tax_class_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// RowNotFound for missing and trashed products
+pub async fn product_tax_class(pool: &PgPool, product_id: Uuid) -> Result<Option<Uuid>, sqlx::Error> {
+    sqlx::query_scalar("SELECT tax_class_id FROM products WHERE id = $1 AND deleted_at IS NULL")
+        .bind(product_id)
+        .fetch_one(pool)
+        .await
+}
+
+// RowNotFound for missing and trashed products, a foreign key violation for an unknown class
+pub async fn set_product_tax_class(
+    pool: &PgPool,
+    product_id: Uuid,
+    tax_class_id: Option<Uuid>,
+    expected_version: Option<i32>,
+    actor_id: Option<Uuid>,
+) -> Result<Versioned<()>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    set_actor(&mut tx, actor_id).await?;
+
+    if !lock_product_at(&mut tx, product_id, expected_version).await? {
+        return Ok(Versioned::Stale);
+    }
+
+    sqlx::query("UPDATE products SET tax_class_id = $1, updated_at = $2 WHERE id = $3")
+        .bind(tax_class_id)
+        .bind(Utc::now().naive_utc())
+        .bind(product_id)
+        .execute(&mut *tx)
+        .await?;
+    let version = saved_version(&mut tx, HistoryEntity::Product, product_id).await?;
+
+    tx.commit().await?;
+
+    Ok(Versioned::Saved((), version))
+}
@@ -0,0 +1,217 @@
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use sqlx::{PgConnection, PgPool, QueryBuilder};
+use uuid::Uuid;
+
+use crate::models::trash::{CategoryRestore, RestoreOutcome, TrashedCategory, TrashedProduct};
+use crate::services::history::set_actor;
+use crate::pagination::{Keyed, PageRequest, Paged, SortDirection, SortKey};
+
+pub const TRASH_SORTS: &[SortKey] = &[
+    SortKey { name: "deleted_at", expr: "deleted_at", sql_type: "timestamp", direction: SortDirection::Desc },
+    SortKey { name: "name", expr: "name", sql_type: "text", direction: SortDirection::Asc },
+];
+
+pub async fn list_trashed_products(
+    pool: &PgPool,
+    page: &PageRequest<'_>,
+    retention: Duration,
+) -> Result<Paged<TrashedProduct>, sqlx::Error> {
+    let mut builder = QueryBuilder::new(
+        "SELECT id, name, slug, sku, description, price, stock_quantity, category_id, attributes, created_at, updated_at, deleted_at, deleted_at + make_interval(secs => ",
+    );
+    builder.push_bind(retention.as_secs_f64()).push(") AS purge_at");
+    page.push_sort_columns(&mut builder, "id");
+    builder.push(" FROM products WHERE deleted_at IS NOT NULL");
+    page.push_cursor_filter(&mut builder, "id");
+    page.push_order_and_limit(&mut builder, "id");
+
+    let rows = builder.build_query_as::<Keyed<TrashedProduct>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM products WHERE deleted_at IS NOT NULL")
+        .fetch_one(pool)
+        .await?;
+
+    Ok(page.finish(rows, total))
+}
+
+pub async fn list_trashed_categories(
+    pool: &PgPool,
+    page: &PageRequest<'_>,
+    retention: Duration,
+) -> Result<Paged<TrashedCategory>, sqlx::Error> {
+    let mut builder = QueryBuilder::new(
+        "SELECT id, name, slug, description, created_at, updated_at, parent_id, deleted_at, deleted_at + make_interval(secs => ",
+    );
+    builder.push_bind(retention.as_secs_f64()).push(") AS purge_at");
+    page.push_sort_columns(&mut builder, "id");
+    builder.push(" FROM categories WHERE deleted_at IS NOT NULL");
+    page.push_cursor_filter(&mut builder, "id");
+    page.push_order_and_limit(&mut builder, "id");
+
+    let rows = builder.build_query_as::<Keyed<TrashedCategory>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM categories WHERE deleted_at IS NOT NULL")
+        .fetch_one(pool)
+        .await?;
+
+    Ok(page.finish(rows, total))
+}
+
+// RowNotFound when the product isn't in the trash
+pub async fn restore_product(pool: &PgPool, id: Uuid, actor_id: Option<Uuid>) -> Result<RestoreOutcome<()>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    set_actor(&mut tx, actor_id).await?;
+    let outcome = restore_product_in(&mut tx, id).await?;
+    tx.commit().await?;
+
+    Ok(outcome)
+}
+
+// restore inside the caller's transaction
+pub async fn restore_product_in(conn: &mut PgConnection, id: Uuid) -> Result<RestoreOutcome<()>, sqlx::Error> {
+    let category_in_trash: bool = sqlx::query_scalar(
+        r#"
+        SELECT c.deleted_at IS NOT NULL
+        FROM products p
+        LEFT JOIN categories c ON c.id = p.category_id
+        WHERE p.id = $1 AND p.deleted_at IS NOT NULL
+        FOR UPDATE OF p
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or(sqlx::Error::RowNotFound)?;
+
+    if category_in_trash {
+        return Ok(RestoreOutcome::ParentInTrash);
+    }
+
+    sqlx::query("UPDATE products SET deleted_at = NULL, updated_at = $1 WHERE id = $2")
+        .bind(Utc::now().naive_utc())
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(RestoreOutcome::Restored(()))
+}
+
+// brings back the category with the subcategories and products deleted along with it;
+// RowNotFound when the category isn't in the trash
+pub async fn restore_category(
+    pool: &PgPool,
+    id: Uuid,
+    actor_id: Option<Uuid>,
+) -> Result<RestoreOutcome<CategoryRestore>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    set_actor(&mut tx, actor_id).await?;
+
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext('categories_tree'))")
+        .execute(&mut *tx)
+        .await?;
+
+    let (deleted_at, parent_in_trash): (NaiveDateTime, bool) = sqlx::query_as(
+        r#"
+        SELECT c.deleted_at, COALESCE(p.deleted_at IS NOT NULL, FALSE)
+        FROM categories c
+        LEFT JOIN categories p ON p.id = c.parent_id
+        WHERE c.id = $1 AND c.deleted_at IS NOT NULL
+        FOR UPDATE OF c
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(sqlx::Error::RowNotFound)?;
+
+    if parent_in_trash {
+        return Ok(RestoreOutcome::ParentInTrash);
+    }
+
+    let subtree: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        WITH RECURSIVE subtree AS (
+            SELECT id FROM categories WHERE id = $1
+            UNION ALL
+            SELECT c.id FROM categories c JOIN subtree s ON c.parent_id = s.id
+            WHERE c.deleted_at = $2
+        )
+        SELECT id FROM subtree
+        "#,
+    )
+    .bind(id)
+    .bind(deleted_at)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let categories = sqlx::query("UPDATE categories SET deleted_at = NULL WHERE id = ANY($1)")
+        .bind(&subtree)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    let products = sqlx::query("UPDATE products SET deleted_at = NULL WHERE category_id = ANY($1) AND deleted_at = $2")
+        .bind(&subtree)
+        .bind(deleted_at)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    tx.commit().await?;
+
+    Ok(RestoreOutcome::Restored(CategoryRestore { categories, products }))
+}
+
+// permanently remove products and categories that have been in the trash longer than `retention`
+pub async fn purge_trash(pool: &PgPool, retention: Duration) -> Result<(u64, u64), sqlx::Error> {
+    let cutoff = Utc::now().naive_utc() - chrono::Duration::seconds(retention.as_secs() as i64);
+    let mut tx = pool.begin().await?;
+
+    // cart lines don't cascade; everything else hanging off a product does
+    sqlx::query(
+        "DELETE FROM cart_items WHERE product_id IN (SELECT id FROM products WHERE deleted_at < $1)",
+    )
+    .bind(cutoff)
+    .execute(&mut *tx)
+    .await?;
+
+    let products = sqlx::query("DELETE FROM products WHERE deleted_at < $1")
+        .bind(cutoff)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    // a category still referenced by a product or a newer subcategory waits for them
+    let categories = sqlx::query(
+        r#"
+        DELETE FROM categories c
+        WHERE c.deleted_at < $1
+          AND NOT EXISTS (SELECT 1 FROM products p WHERE p.category_id = c.id)
+          AND NOT EXISTS (SELECT 1 FROM categories k WHERE k.parent_id = c.id AND (k.deleted_at IS NULL OR k.deleted_at >= $1))
+        "#,
+    )
+    .bind(cutoff)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    tx.commit().await?;
+
+    Ok((products, categories))
+}
+
+pub fn spawn_trash_purger(pool: PgPool, every: Duration, retention: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        loop {
+            interval.tick().await;
+            match purge_trash(&pool, retention).await {
+                Ok((0, 0)) => {}
+                Ok((products, categories)) => {
+                    println!("🗑️ Purged {} products and {} categories from the trash", products, categories)
+                }
+                Err(e) => eprintln!("❌ Failed to purge trash: {:?}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+
+use crate::models::warehouse::LocationStock;
+use crate::models::variant::{
+    CreateProductOption, CreateVariant, ProductOption, ProductOptionValue, ProductOptionWithValues,
+    ProductVariant, UpdateVariant, VariantDetails, VariantOptionValue,
+};
+use crate::services::inventory::{receive_initial_stock, set_stock_level, InventoryError};
+use crate::pagination::{Keyed, PageRequest, Paged, SortDirection, SortKey};
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use sqlx::{PgPool, QueryBuilder};
+use uuid::Uuid;
+
+// create an option type together with its values
+pub async fn create_option(
+    pool: &PgPool,
+    product_id: Uuid,
+    data: CreateProductOption,
+) -> Result<ProductOptionWithValues, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let option = sqlx::query_as::<_, ProductOption>(
+        r#"
+        INSERT INTO product_options (id, product_id, name, position)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, product_id, name, position
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(product_id)
+    .bind(&data.name)
+    .bind(data.position.unwrap_or(0))
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let mut values = Vec::with_capacity(data.values.len());
+    for (position, value) in data.values.iter().enumerate() {
+        let value = sqlx::query_as::<_, ProductOptionValue>(
+            r#"
+            INSERT INTO product_option_values (id, option_id, value, position)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, option_id, value, position
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(option.id)
+        .bind(value)
+        .bind(position as i32)
+        .fetch_one(&mut *tx)
+        .await?;
+        values.push(value);
+    }
+
+    tx.commit().await?;
+
+    Ok(ProductOptionWithValues { option, values })
+}
+
+pub const OPTION_SORTS: &[SortKey] = &[
+    SortKey { name: "position", expr: "position", sql_type: "integer", direction: SortDirection::Asc },
+    SortKey { name: "name", expr: "name", sql_type: "text", direction: SortDirection::Asc },
+];
+
+pub async fn list_options(
+    pool: &PgPool,
+    product_id: Uuid,
+    page: &PageRequest<'_>,
+) -> Result<Paged<ProductOptionWithValues>, sqlx::Error> {
+    let mut builder = QueryBuilder::new("SELECT id, product_id, name, position");
+    page.push_sort_columns(&mut builder, "id");
+    builder.push(" FROM product_options WHERE product_id = ").push_bind(product_id);
+    page.push_cursor_filter(&mut builder, "id");
+    page.push_order_and_limit(&mut builder, "id");
+
+    let rows = builder.build_query_as::<Keyed<ProductOption>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM product_options WHERE product_id = $1")
+        .bind(product_id)
+        .fetch_one(pool)
+        .await?;
+    let options = page.finish(rows, total);
+
+    let option_ids: Vec<Uuid> = options.items.iter().map(|o| o.id).collect();
+    let values = sqlx::query_as::<_, ProductOptionValue>(
+        "SELECT id, option_id, value, position FROM product_option_values WHERE option_id = ANY($1) ORDER BY position, value",
+    )
+    .bind(&option_ids)
+    .fetch_all(pool)
+    .await?;
+
+    let mut grouped: HashMap<Uuid, Vec<ProductOptionValue>> = HashMap::new();
+    for value in values {
+        grouped.entry(value.option_id).or_default().push(value);
+    }
+
+    Ok(Paged {
+        items: options
+            .items
+            .into_iter()
+            .map(|option| {
+                let values = grouped.remove(&option.id).unwrap_or_default();
+                ProductOptionWithValues { option, values }
+            })
+            .collect(),
+        total: options.total,
+        next_cursor: options.next_cursor,
+    })
+}
+
+// true when every value belongs to one of the product's options and no option is used twice
+pub async fn option_values_valid_for_product(
+    pool: &PgPool,
+    product_id: Uuid,
+    option_value_ids: &[Uuid],
+) -> Result<bool, sqlx::Error> {
+    let option_ids: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT v.option_id
+        FROM product_option_values v
+        JOIN product_options o ON o.id = v.option_id
+        WHERE v.id = ANY($1) AND o.product_id = $2
+        "#,
+    )
+    .bind(option_value_ids)
+    .bind(product_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut distinct = option_ids.clone();
+    distinct.sort();
+    distinct.dedup();
+
+    Ok(option_ids.len() == option_value_ids.len() && distinct.len() == option_ids.len())
+}
+
+pub async fn create_variant(
+    pool: &PgPool,
+    product_id: Uuid,
+    data: CreateVariant,
+) -> Result<ProductVariant, InventoryError> {
+    let now = Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+
+    let mut variant = sqlx::query_as::<_, ProductVariant>(
+        r#"
+        INSERT INTO product_variants (id, product_id, sku, price, stock_quantity, barcode, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, 0, $5, $6, $6)
+        RETURNING id, product_id, sku, price, stock_quantity, barcode, created_at, updated_at
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(product_id)
+    .bind(&data.sku)
+    .bind(&data.price)
+    .bind(&data.barcode)
+    .bind(now)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO product_variant_option_values (variant_id, option_value_id)
+        SELECT $1, UNNEST($2::uuid[])
+        "#,
+    )
+    .bind(variant.id)
+    .bind(&data.option_value_ids)
+    .execute(&mut *tx)
+    .await?;
+
+    if data.stock_quantity > 0 {
+        receive_initial_stock(&mut tx, product_id, Some(variant.id), data.stock_quantity).await?;
+        variant.stock_quantity = data.stock_quantity;
+    }
+
+    tx.commit().await?;
+
+    Ok(variant)
+}
+
+pub const VARIANT_SORTS: &[SortKey] = &[
+    SortKey { name: "created_at", expr: "created_at", sql_type: "timestamp", direction: SortDirection::Asc },
+    SortKey { name: "sku", expr: "sku", sql_type: "text", direction: SortDirection::Asc },
+];
+
+pub async fn list_variants(
+    pool: &PgPool,
+    product_id: Uuid,
+    page: &PageRequest<'_>,
+) -> Result<Paged<ProductVariant>, sqlx::Error> {
+    let mut builder = QueryBuilder::new("SELECT id, product_id, sku, price, stock_quantity, barcode, created_at, updated_at");
+    page.push_sort_columns(&mut builder, "id");
+    builder
+        .push(" FROM product_variants WHERE deleted_at IS NULL AND product_id = ")
+        .push_bind(product_id);
+    page.push_cursor_filter(&mut builder, "id");
+    page.push_order_and_limit(&mut builder, "id");
+
+    let rows = builder.build_query_as::<Keyed<ProductVariant>>().fetch_all(pool).await?;
+    let total: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM product_variants WHERE product_id = $1 AND deleted_at IS NULL")
+            .bind(product_id)
+            .fetch_one(pool)
+            .await?;
+
+    Ok(page.finish(rows, total))
+}
+
+pub async fn update_variant(
+    pool: &PgPool,
+    id: Uuid,
+    update: UpdateVariant,
+) -> Result<ProductVariant, InventoryError> {
+    let mut tx = pool.begin().await?;
+
+    if let Some(stock_quantity) = update.stock_quantity {
+        let product_id: Uuid =
+            sqlx::query_scalar("SELECT product_id FROM product_variants WHERE id = $1 AND deleted_at IS NULL")
+                .bind(id)
+                .fetch_one(&mut *tx)
+                .await?;
+        set_stock_level(&mut tx, product_id, Some(id), stock_quantity, None, "variant update").await?;
+    }
+
+    let variant = sqlx::query_as::<_, ProductVariant>(
+        r#"
+        UPDATE product_variants
+        SET
+            sku = COALESCE($1, sku),
+            price = COALESCE($2, price),
+            barcode = COALESCE($3, barcode),
+            updated_at = $4
+        WHERE id = $5 AND deleted_at IS NULL
+        RETURNING id, product_id, sku, price, stock_quantity, barcode, created_at, updated_at
+        "#,
+    )
+    .bind(update.sku)
+    .bind(update.price)
+    .bind(update.barcode)
+    .bind(Utc::now().naive_utc())
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(variant)
+}
+
+// variants stay referenced by cart lines, so they are only ever soft deleted
+pub async fn soft_delete_variant(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+    let result = sqlx::query("UPDATE product_variants SET deleted_at = $1 WHERE id = $2 AND deleted_at IS NULL")
+        .bind(Utc::now().naive_utc())
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
+    }
+
+    Ok(())
+}
+
+pub async fn variant_belongs_to_product(
+    pool: &PgPool,
+    variant_id: Uuid,
+    product_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM product_variants WHERE id = $1 AND product_id = $2 AND deleted_at IS NULL)",
+    )
+    .bind(variant_id)
+    .bind(product_id)
+    .fetch_one(pool)
+    .await
+}
+
+// stock minus active reservations, never below zero
+pub fn available_to_sell(stock_quantity: i32, reserved: Option<&i64>) -> i32 {
+    let available = i64::from(stock_quantity) - reserved.copied().unwrap_or(0);
+    available.clamp(0, i64::from(i32::MAX)) as i32
+}
+
+// variants of several products in one round trip, keyed by product id; variants without
+// a price of their own charge the product's, `base_prices` holds those by product id
+pub async fn variant_details_for_products(
+    pool: &PgPool,
+    base_prices: &HashMap<Uuid, BigDecimal>,
+    reserved: &HashMap<(Uuid, Option<Uuid>), i64>,
+    locations: &mut HashMap<(Uuid, Option<Uuid>), Vec<LocationStock>>,
+) -> Result<HashMap<Uuid, Vec<VariantDetails>>, sqlx::Error> {
+    let product_ids: Vec<Uuid> = base_prices.keys().copied().collect();
+
+    let variants = sqlx::query_as::<_, ProductVariant>(
+        r#"
+        SELECT id, product_id, sku, price, stock_quantity, barcode, created_at, updated_at
+        FROM product_variants
+        WHERE product_id = ANY($1) AND deleted_at IS NULL
+        ORDER BY created_at
+        "#,
+    )
+    .bind(&product_ids)
+    .fetch_all(pool)
+    .await?;
+
+    let variant_ids: Vec<Uuid> = variants.iter().map(|v| v.id).collect();
+    let option_values = sqlx::query_as::<_, VariantOptionValue>(
+        r#"
+        SELECT pvov.variant_id, o.name AS option, v.value
+        FROM product_variant_option_values pvov
+        JOIN product_option_values v ON v.id = pvov.option_value_id
+        JOIN product_options o ON o.id = v.option_id
+        WHERE pvov.variant_id = ANY($1)
+        ORDER BY o.position, o.name
+        "#,
+    )
+    .bind(&variant_ids)
+    .fetch_all(pool)
+    .await?;
+
+    let mut options_by_variant: HashMap<Uuid, Vec<VariantOptionValue>> = HashMap::new();
+    for option_value in option_values {
+        options_by_variant.entry(option_value.variant_id).or_default().push(option_value);
+    }
+
+    let mut details: HashMap<Uuid, Vec<VariantDetails>> = HashMap::new();
+    for variant in variants {
+        let price = match variant.price {
+            Some(price) => price,
+            None => base_prices[&variant.product_id].clone(),
+        };
+        let available_quantity =
+            available_to_sell(variant.stock_quantity, reserved.get(&(variant.product_id, Some(variant.id))));
+        details.entry(variant.product_id).or_default().push(VariantDetails {
+            id: variant.id,
+            sku: variant.sku,
+            price,
+            stock_quantity: variant.stock_quantity,
+            barcode: variant.barcode,
+            available_quantity,
+            available: available_quantity > 0,
+            locations: locations.remove(&(variant.product_id, Some(variant.id))).unwrap_or_default(),
+            options: options_by_variant.remove(&variant.id).unwrap_or_default(),
+        });
+    }
+
+    Ok(details)
+}
@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+use std::env;
+
+use crate::models::inventory::{NewStockMovement, StockMovementKind};
+use crate::models::warehouse::{
+    Allocation, CreateStockTransfer, CreateWarehouse, LocationStock, StockTransfer, UpdateWarehouse, Warehouse,
+    WarehouseLevel, WarehouseStockLevel,
+};
+use crate::pagination::{Keyed, PageRequest, Paged, SortDirection, SortKey};
+use crate::services::inventory::{insert_movement, InventoryError};
+use chrono::Utc;
+use sqlx::{PgConnection, PgPool, QueryBuilder};
+use uuid::Uuid;
+
+// decides which locations a stock decrease (an order line) is taken from
+pub trait AllocationStrategy: Send + Sync {
+    // levels come sorted by priority; None when the locations can't cover the quantity
+    fn allocate(&self, levels: &[WarehouseLevel], quantity: i32) -> Option<Vec<Allocation>>;
+}
+
+// drain locations in priority order, splitting the line when one isn't enough
+pub struct PriorityAllocation;
+
+impl AllocationStrategy for PriorityAllocation {
+    fn allocate(&self, levels: &[WarehouseLevel], quantity: i32) -> Option<Vec<Allocation>> {
+        let mut remaining = quantity;
+        let mut allocations = Vec::new();
+
+        for level in levels.iter().filter(|l| l.quantity > 0) {
+            if remaining == 0 {
+                break;
+            }
+            let take = remaining.min(level.quantity);
+            allocations.push(Allocation { warehouse_id: level.warehouse_id, quantity: take });
+            remaining -= take;
+        }
+
+        (remaining == 0).then_some(allocations)
+    }
+}
+
+// ship from a single location when any can cover the whole line, otherwise fall back to priority order
+pub struct SingleLocationAllocation;
+
+impl AllocationStrategy for SingleLocationAllocation {
+    fn allocate(&self, levels: &[WarehouseLevel], quantity: i32) -> Option<Vec<Allocation>> {
+        match levels.iter().find(|l| l.quantity >= quantity) {
+            Some(level) => Some(vec![Allocation { warehouse_id: level.warehouse_id, quantity }]),
+            None => PriorityAllocation.allocate(levels, quantity),
+        }
+    }
+}
+
+// ALLOCATION_STRATEGY=priority|single-location (default: single-location)
+pub fn allocation_strategy() -> Box<dyn AllocationStrategy> {
+    match env::var("ALLOCATION_STRATEGY").as_deref() {
+        Ok("priority") => Box::new(PriorityAllocation),
+        _ => Box::new(SingleLocationAllocation),
+    }
+}
+
+pub async fn create_warehouse(pool: &PgPool, data: CreateWarehouse) -> Result<Warehouse, sqlx::Error> {
+    let now = Utc::now().naive_utc();
+
+    sqlx::query_as::<_, Warehouse>(
+        r#"
+        INSERT INTO warehouses (id, name, code, address, priority, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $6)
+        RETURNING id, name, code, address, priority, is_default, is_active, created_at, updated_at
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(data.name)
+    .bind(data.code)
+    .bind(data.address)
+    .bind(data.priority.unwrap_or(0))
+    .bind(now)
+    .fetch_one(pool)
+    .await
+}
+
+pub const WAREHOUSE_SORTS: &[SortKey] = &[
+    SortKey { name: "priority", expr: "priority", sql_type: "integer", direction: SortDirection::Asc },
+    SortKey { name: "name", expr: "name", sql_type: "text", direction: SortDirection::Asc },
+];
+
+pub async fn list_warehouses(pool: &PgPool, page: &PageRequest<'_>) -> Result<Paged<Warehouse>, sqlx::Error> {
+    let mut builder =
+        QueryBuilder::new("SELECT id, name, code, address, priority, is_default, is_active, created_at, updated_at");
+    page.push_sort_columns(&mut builder, "id");
+    builder.push(" FROM warehouses WHERE TRUE");
+    page.push_cursor_filter(&mut builder, "id");
+    page.push_order_and_limit(&mut builder, "id");
+
+    let rows = builder.build_query_as::<Keyed<Warehouse>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM warehouses").fetch_one(pool).await?;
+
+    Ok(page.finish(rows, total))
+}
+
+pub async fn update_warehouse(pool: &PgPool, id: Uuid, update: UpdateWarehouse) -> Result<Warehouse, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    // only one location can be the default
+    if update.is_default == Some(true) {
+        sqlx::query("UPDATE warehouses SET is_default = FALSE WHERE is_default AND id <> $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let warehouse = sqlx::query_as::<_, Warehouse>(
+        r#"
+        UPDATE warehouses
+        SET
+            name = COALESCE($1, name),
+            address = COALESCE($2, address),
+            priority = COALESCE($3, priority),
+            is_default = COALESCE($4, is_default),
+            is_active = COALESCE($5, is_active),
+            updated_at = $6
+        WHERE id = $7
+        RETURNING id, name, code, address, priority, is_default, is_active, created_at, updated_at
+        "#,
+    )
+    .bind(update.name)
+    .bind(update.address)
+    .bind(update.priority)
+    .bind(update.is_default)
+    .bind(update.is_active)
+    .bind(Utc::now().naive_utc())
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(warehouse)
+}
+
+pub const WAREHOUSE_STOCK_SORTS: &[SortKey] = &[
+    SortKey { name: "name", expr: "p.name", sql_type: "text", direction: SortDirection::Asc },
+    SortKey { name: "quantity", expr: "ws.quantity", sql_type: "integer", direction: SortDirection::Desc },
+];
+
+pub async fn warehouse_stock(
+    pool: &PgPool,
+    warehouse_id: Uuid,
+    page: &PageRequest<'_>,
+) -> Result<Paged<WarehouseStockLevel>, sqlx::Error> {
+    let mut builder = QueryBuilder::new("SELECT ws.product_id, ws.variant_id, p.name, ws.quantity, ws.updated_at");
+    page.push_sort_columns(&mut builder, "ws.id");
+    builder
+        .push(" FROM warehouse_stock ws JOIN products p ON p.id = ws.product_id WHERE ws.quantity > 0 AND ws.warehouse_id = ")
+        .push_bind(warehouse_id);
+    page.push_cursor_filter(&mut builder, "ws.id");
+    page.push_order_and_limit(&mut builder, "ws.id");
+
+    let rows = builder.build_query_as::<Keyed<WarehouseStockLevel>>().fetch_all(pool).await?;
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM warehouse_stock WHERE warehouse_id = $1 AND quantity > 0")
+        .bind(warehouse_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(page.finish(rows, total))
+}
+
+pub async fn default_warehouse_id(conn: &mut PgConnection) -> Result<Uuid, sqlx::Error> {
+    sqlx::query_scalar("SELECT id FROM warehouses WHERE is_default")
+        .fetch_one(conn)
+        .await
+}
+
+// active locations holding the product (or variant), locked for the rest of the transaction
+pub async fn locked_levels(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+) -> Result<Vec<WarehouseLevel>, sqlx::Error> {
+    sqlx::query_as::<_, WarehouseLevel>(
+        r#"
+        SELECT ws.warehouse_id, ws.quantity
+        FROM warehouse_stock ws
+        JOIN warehouses w ON w.id = ws.warehouse_id
+        WHERE ws.product_id = $1 AND ws.variant_id IS NOT DISTINCT FROM $2 AND w.is_active
+        ORDER BY w.priority, w.code
+        FOR UPDATE OF ws
+        "#,
+    )
+    .bind(product_id)
+    .bind(variant_id)
+    .fetch_all(conn)
+    .await
+}
+
+// change stock at one location; false when a decrease would go below zero
+pub async fn adjust_location(
+    conn: &mut PgConnection,
+    warehouse_id: Uuid,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    delta: i32,
+) -> Result<bool, sqlx::Error> {
+    let now = Utc::now().naive_utc();
+
+    let updated: Option<i32> = if delta >= 0 {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO warehouse_stock (id, warehouse_id, product_id, variant_id, quantity, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT ON CONSTRAINT warehouse_stock_location_key
+            DO UPDATE SET quantity = warehouse_stock.quantity + EXCLUDED.quantity, updated_at = EXCLUDED.updated_at
+            RETURNING quantity
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(warehouse_id)
+        .bind(product_id)
+        .bind(variant_id)
+        .bind(delta)
+        .bind(now)
+        .fetch_optional(conn)
+        .await?
+    } else {
+        sqlx::query_scalar(
+            r#"
+            UPDATE warehouse_stock
+            SET quantity = quantity + $1, updated_at = $2
+            WHERE warehouse_id = $3 AND product_id = $4 AND variant_id IS NOT DISTINCT FROM $5
+              AND quantity + $1 >= 0
+            RETURNING quantity
+            "#,
+        )
+        .bind(delta)
+        .bind(now)
+        .bind(warehouse_id)
+        .bind(product_id)
+        .bind(variant_id)
+        .fetch_optional(conn)
+        .await?
+    };
+
+    Ok(updated.is_some())
+}
+
+// per-location stock for several products, keyed by (product_id, variant_id)
+pub async fn locations_for_products(
+    pool: &PgPool,
+    product_ids: &[Uuid],
+) -> Result<HashMap<(Uuid, Option<Uuid>), Vec<LocationStock>>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, LocationStock>(
+        r#"
+        SELECT ws.product_id, ws.variant_id, ws.warehouse_id, w.code AS warehouse_code, ws.quantity
+        FROM warehouse_stock ws
+        JOIN warehouses w ON w.id = ws.warehouse_id
+        WHERE ws.product_id = ANY($1) AND w.is_active AND ws.quantity > 0
+        ORDER BY w.priority, w.code
+        "#,
+    )
+    .bind(product_ids)
+    .fetch_all(pool)
+    .await?;
+
+    let mut grouped: HashMap<(Uuid, Option<Uuid>), Vec<LocationStock>> = HashMap::new();
+    for row in rows {
+        grouped.entry((row.product_id, row.variant_id)).or_default().push(row);
+    }
+
+    Ok(grouped)
+}
+
+// move stock between locations; the total is unchanged, so only transfer rows hit the ledger
+pub async fn transfer_stock(
+    pool: &PgPool,
+    transfer: CreateStockTransfer,
+    actor_id: Option<Uuid>,
+) -> Result<StockTransfer, InventoryError> {
+    let mut tx = pool.begin().await?;
+
+    let taken = adjust_location(
+        &mut tx,
+        transfer.from_warehouse_id,
+        transfer.product_id,
+        transfer.variant_id,
+        -transfer.quantity,
+    )
+    .await?;
+    if !taken {
+        return Err(InventoryError::InsufficientStock);
+    }
+
+    adjust_location(&mut tx, transfer.to_warehouse_id, transfer.product_id, transfer.variant_id, transfer.quantity)
+        .await?;
+
+    for (warehouse_id, quantity) in [
+        (transfer.from_warehouse_id, -transfer.quantity),
+        (transfer.to_warehouse_id, transfer.quantity),
+    ] {
+        let movement = NewStockMovement {
+            product_id: transfer.product_id,
+            variant_id: transfer.variant_id,
+            warehouse_id: Some(warehouse_id),
+            kind: StockMovementKind::Transfer,
+            quantity,
+            reason: transfer.reason.clone(),
+            actor_id,
+        };
+        insert_movement(&mut tx, &movement).await?;
+    }
+
+    let record = sqlx::query_as::<_, StockTransfer>(
+        r#"
+        INSERT INTO stock_transfers (id, from_warehouse_id, to_warehouse_id, product_id, variant_id, quantity, reason, actor_id, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING id, from_warehouse_id, to_warehouse_id, product_id, variant_id, quantity, reason, actor_id, created_at
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(transfer.from_warehouse_id)
+    .bind(transfer.to_warehouse_id)
+    .bind(transfer.product_id)
+    .bind(transfer.variant_id)
+    .bind(transfer.quantity)
+    .bind(&transfer.reason)
+    .bind(actor_id)
+    .bind(Utc::now().naive_utc())
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(record)
+}